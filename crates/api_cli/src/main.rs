@@ -1,4 +1,6 @@
 use clap::{Args, Parser, Subcommand};
+#[cfg(target_os = "linux")]
+use platform_linux::LinuxCapturer;
 #[cfg(target_os = "macos")]
 use platform_mac::MacCapturer;
 #[cfg(target_os = "windows")]
@@ -175,7 +177,50 @@ async fn handle_interactive_capture_async(args: CaptureInteractiveArgs) {
             }
         }
     }
-    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[cfg(target_os = "linux")]
+    {
+        // winit 在 Linux 上同样要求事件循环运行在主线程
+        let shot_result = tokio::task::block_in_place(|| {
+            let selector: Box<dyn ui_overlay::RegionSelector> =
+                ui_overlay::create_gui_region_selector();
+            LinuxCapturer::capture_region_interactive_custom(selector.as_ref())
+        });
+
+        match shot_result {
+            Ok(shot) => {
+                export_screenshot_async(
+                    shot,
+                    args.template,
+                    args.out_dir,
+                    "交互式截图",
+                    args.clipboard,
+                )
+                .await;
+            }
+            Err(e) => {
+                // 使用完整错误链匹配，底层 X11 错误被 context 包裹
+                match format!("{e:#}").as_str() {
+                    s if s.contains("DISPLAY") => {
+                        eprintln!("❌ X11 连接失败：{e:#}");
+                        eprintln!("💡 提示：请在 X11 会话中运行，或确认 DISPLAY 环境变量已设置（Wayland 下需 XWayland）。");
+                    }
+                    s if s.contains("canceled") => {
+                        eprintln!("⚠️  操作已取消");
+                        std::process::exit(0);
+                    }
+                    s if s.contains("display") => {
+                        eprintln!("❌ 显示器检测失败：{e}");
+                        eprintln!("💡 提示：请确认显示器连接正常，或尝试重新启动应用。");
+                    }
+                    _ => {
+                        eprintln!("❌ 交互框选失败: {e}");
+                    }
+                }
+                std::process::exit(2);
+            }
+        }
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
     {
         eprintln!("❌ 当前平台暂不支持交互式截图");
         std::process::exit(3);
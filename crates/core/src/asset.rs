@@ -0,0 +1,196 @@
+//! 贴图（Image 标注）使用的图片数据：内嵌 PNG 与按 id 引用的资源库
+use crate::error::{Error, ErrorKind, Result};
+use crate::model::{Annotation, AnnotationKind};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// PNG 文件签名
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// 已校验的 PNG 数据（共享只读，克隆不复制像素）
+///
+/// JSON 中序列化为 base64 字符串；构造与反序列化时校验 PNG 签名与大小上限，
+/// 非 PNG 内容不会进入标注数据。
+#[derive(Clone, PartialEq, Eq)]
+pub struct PngData(Arc<[u8]>);
+
+impl PngData {
+    /// 单张贴图的编码数据上限
+    pub const MAX_LEN: usize = 16 * 1024 * 1024;
+
+    pub fn new(bytes: impl Into<Arc<[u8]>>) -> Result<Self> {
+        let bytes = bytes.into();
+        if bytes.len() > Self::MAX_LEN {
+            return Err(Error::new(
+                ErrorKind::Validation,
+                format!(
+                    "png data too large: {} > {} bytes",
+                    bytes.len(),
+                    Self::MAX_LEN
+                ),
+            ));
+        }
+        if !bytes.starts_with(&PNG_SIGNATURE) {
+            return Err(Error::new(ErrorKind::Validation, "not png data"));
+        }
+        Ok(Self(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// 只输出长度，避免调试日志里出现整段图片数据
+impl std::fmt::Debug for PngData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PngData({} bytes)", self.0.len())
+    }
+}
+
+impl Serialize for PngData {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_str(&STANDARD.encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for PngData {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        // base64 膨胀约 4/3，先按长度拒绝超限数据再解码
+        if s.len() > Self::MAX_LEN / 3 * 4 + 4 {
+            return Err(serde::de::Error::custom("png data too large"));
+        }
+        let bytes = STANDARD
+            .decode(s.as_bytes())
+            .map_err(|e| serde::de::Error::custom(format!("invalid png base64: {e}")))?;
+        PngData::new(bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 贴图的图片来源
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImageSource {
+    /// 内嵌在标注中的 PNG
+    Embedded { png: PngData },
+    /// 引用 `ImageAssets` 中的图片（同一 logo / 印章多处使用时只存一份）
+    Asset { id: Uuid },
+}
+
+/// 图片资源库：id -> PNG
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageAssets {
+    items: BTreeMap<Uuid, PngData>,
+}
+
+impl ImageAssets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入图片并返回新分配的 id
+    pub fn insert(&mut self, png: PngData) -> Uuid {
+        let id = Uuid::now_v7();
+        self.items.insert(id, png);
+        id
+    }
+
+    /// 以指定 id 加入（例如从项目文件恢复），已存在时覆盖
+    pub fn insert_with_id(&mut self, id: Uuid, png: PngData) {
+        self.items.insert(id, png);
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&PngData> {
+        self.items.get(&id)
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Option<PngData> {
+        self.items.remove(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Uuid, &PngData)> {
+        self.items.iter().map(|(id, png)| (*id, png))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// 取来源对应的 PNG：内嵌直接返回，资源引用在库中查找
+    pub fn lookup<'a>(&'a self, source: &'a ImageSource) -> Option<&'a PngData> {
+        match source {
+            ImageSource::Embedded { png } => Some(png),
+            ImageSource::Asset { id } => self.get(*id),
+        }
+    }
+
+    /// 将标注中的资源引用替换为内嵌数据（共享同一份字节），供渲染 / 导出使用；
+    /// 库中不存在的引用保持原样，渲染时跳过
+    pub fn embed(&self, annotations: &[Annotation]) -> Vec<Annotation> {
+        annotations
+            .iter()
+            .map(|a| {
+                let mut a = a.clone();
+                if let AnnotationKind::Image { source } = &mut a.kind {
+                    if let ImageSource::Asset { id } = source {
+                        if let Some(png) = self.get(*id) {
+                            *source = ImageSource::Embedded { png: png.clone() };
+                        }
+                    }
+                }
+                a
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png() -> PngData {
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend_from_slice(b"rest");
+        PngData::new(bytes).unwrap()
+    }
+
+    #[test]
+    fn png_data_requires_signature() {
+        let err = PngData::new(b"GIF89a".to_vec()).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::Validation));
+        assert_eq!(png().as_bytes().len(), 12);
+    }
+
+    #[test]
+    fn png_data_round_trips_as_base64() {
+        let json = serde_json::to_string(&png()).unwrap();
+        assert!(json.starts_with("\"iVBORw0KGg"), "{json}");
+        let back: PngData = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, png());
+
+        assert!(serde_json::from_str::<PngData>("\"not base64!\"").is_err());
+        // 合法 base64 但不是 PNG
+        assert!(serde_json::from_str::<PngData>("\"R0lGODlh\"").is_err());
+    }
+
+    #[test]
+    fn lookup_resolves_assets() {
+        let mut assets = ImageAssets::new();
+        let id = assets.insert(png());
+        assert_eq!(assets.lookup(&ImageSource::Asset { id }), Some(&png()));
+        assert!(assets
+            .lookup(&ImageSource::Asset { id: Uuid::now_v7() })
+            .is_none());
+        let embedded = ImageSource::Embedded { png: png() };
+        assert_eq!(ImageAssets::new().lookup(&embedded), Some(&png()));
+    }
+}
@@ -0,0 +1,281 @@
+//! 颜色模型：标注描边 / 填充色
+//!
+//! 支持的文本格式：`#RGB`、`#RGBA`、`#RRGGBB`、`#RRGGBBAA`、`rgb()/rgba()`、`hsl()/hsla()`
+//! 以及常用颜色名。序列化统一输出 `#RRGGBB`（不透明）或 `#RRGGBBAA`，与旧版 JSON 兼容。
+use crate::error::{Error, ErrorKind, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// sRGB 颜色，alpha 与标注 `opacity` 相乘后作为最终不透明度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// 解析颜色文本，失败返回 `ErrorKind::Validation`
+    pub fn parse(s: &str) -> Result<Self> {
+        let t = s.trim();
+        let invalid = || Error::new(ErrorKind::Validation, format!("invalid color: {s:?}"));
+        if let Some(hex) = t.strip_prefix('#') {
+            return parse_hex(hex).ok_or_else(invalid);
+        }
+        let lower = t.to_ascii_lowercase();
+        if let Some((name, args)) = lower
+            .strip_suffix(')')
+            .and_then(|body| body.split_once('('))
+        {
+            return parse_function(name.trim(), args).ok_or_else(invalid);
+        }
+        if let Some(c) = named(&lower) {
+            return Ok(c);
+        }
+        // 兼容旧版：不带 # 的 6 位十六进制
+        if t.len() == 6 {
+            if let Some(c) = parse_hex(t) {
+                return Ok(c);
+            }
+        }
+        Err(invalid())
+    }
+
+    /// 与标注 opacity 合成后的最终 alpha（0..=255）
+    pub fn alpha_with(&self, opacity: f32) -> u8 {
+        (self.a as f32 * opacity.clamp(0.0, 1.0)) as u8
+    }
+
+    /// 规范化的十六进制表示：不透明时 `#RRGGBB`，否则 `#RRGGBBAA`
+    pub fn to_hex(&self) -> String {
+        if self.a == 255 {
+            format!("#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+        } else {
+            format!("#{:02X}{:02X}{:02X}{:02X}", self.r, self.g, self.b, self.a)
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Color::parse(s)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        Color::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let nibble = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| v * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 => Some(Color::rgb(nibble(0)?, nibble(1)?, nibble(2)?)),
+        4 => Some(Color::rgba(nibble(0)?, nibble(1)?, nibble(2)?, nibble(3)?)),
+        6 => Some(Color::rgb(byte(0)?, byte(2)?, byte(4)?)),
+        8 => Some(Color::rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+        _ => None,
+    }
+}
+
+/// `rgb(r, g, b)`、`rgba(r, g, b, a)`、`hsl(h, s%, l%)`、`hsla(h, s%, l%, a)`；
+/// 分量也可用空格分隔，alpha 接受 0..1 或百分比
+fn parse_function(name: &str, args: &str) -> Option<Color> {
+    let parts: Vec<&str> = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let alpha = match parts.len() {
+        3 => 255,
+        4 => unit(parts[3], 1.0).map(|v| (v * 255.0).round() as u8)?,
+        _ => return None,
+    };
+    match name {
+        "rgb" | "rgba" => {
+            let ch = |p: &str| unit(p, 255.0).map(|v| (v * 255.0).round() as u8);
+            Some(Color::rgba(
+                ch(parts[0])?,
+                ch(parts[1])?,
+                ch(parts[2])?,
+                alpha,
+            ))
+        }
+        "hsl" | "hsla" => {
+            let h = parts[0].strip_suffix("deg").unwrap_or(parts[0]);
+            let h = h.parse::<f32>().ok()?.rem_euclid(360.0);
+            let s = parts[1].strip_suffix('%')?.parse::<f32>().ok()? / 100.0;
+            let l = parts[2].strip_suffix('%')?.parse::<f32>().ok()? / 100.0;
+            let (r, g, b) = hsl_to_rgb(h, s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+            Some(Color::rgba(r, g, b, alpha))
+        }
+        _ => None,
+    }
+}
+
+/// 数值分量归一到 0..1：百分比按 100 计，否则除以 `max`；超出范围视为无效
+fn unit(p: &str, max: f32) -> Option<f32> {
+    let v = match p.strip_suffix('%') {
+        Some(pct) => pct.parse::<f32>().ok()? / 100.0,
+        None => p.parse::<f32>().ok()? / max,
+    };
+    (v.is_finite() && (0.0..=1.0).contains(&v)).then_some(v)
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let to = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (to(r), to(g), to(b))
+}
+
+fn named(name: &str) -> Option<Color> {
+    let c = match name {
+        "black" => Color::BLACK,
+        "white" => Color::WHITE,
+        "transparent" => Color::TRANSPARENT,
+        "red" => Color::rgb(255, 0, 0),
+        "green" => Color::rgb(0, 128, 0),
+        "lime" => Color::rgb(0, 255, 0),
+        "blue" => Color::rgb(0, 0, 255),
+        "yellow" => Color::rgb(255, 255, 0),
+        "cyan" | "aqua" => Color::rgb(0, 255, 255),
+        "magenta" | "fuchsia" => Color::rgb(255, 0, 255),
+        "orange" => Color::rgb(255, 165, 0),
+        "purple" => Color::rgb(128, 0, 128),
+        "pink" => Color::rgb(255, 192, 203),
+        "brown" => Color::rgb(165, 42, 42),
+        "gray" | "grey" => Color::rgb(128, 128, 128),
+        "silver" => Color::rgb(192, 192, 192),
+        "maroon" => Color::rgb(128, 0, 0),
+        "olive" => Color::rgb(128, 128, 0),
+        "navy" => Color::rgb(0, 0, 128),
+        "teal" => Color::rgb(0, 128, 128),
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_formats() {
+        let red = Color::rgb(255, 0, 0);
+        for s in [
+            "#F00",
+            "#ff0000",
+            "#FF0000FF",
+            "FF0000",
+            "rgb(255, 0, 0)",
+            "rgba(255,0,0,1)",
+            "rgb(100% 0% 0%)",
+            "hsl(0, 100%, 50%)",
+            "hsl(360deg 100% 50%)",
+            "red",
+            " Red ",
+        ] {
+            assert_eq!(Color::parse(s).unwrap(), red, "{s}");
+        }
+        assert_eq!(Color::parse("#0F08").unwrap(), Color::rgba(0, 255, 0, 136));
+        assert_eq!(
+            Color::parse("rgba(0, 0, 255, 0.5)").unwrap(),
+            Color::rgba(0, 0, 255, 128)
+        );
+        assert_eq!(
+            Color::parse("hsla(120, 100%, 25%, 50%)").unwrap(),
+            Color::rgba(0, 128, 0, 128)
+        );
+    }
+
+    #[test]
+    fn invalid_is_validation_error() {
+        for s in [
+            "",
+            "#12",
+            "#GGGGGG",
+            "rgb(1,2)",
+            "rgb(300,0,0)",
+            "notacolor",
+            "bad",
+        ] {
+            let err = Color::parse(s).unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::Validation), "{s}");
+        }
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let c: Color = serde_json::from_str("\"#3366ff\"").unwrap();
+        assert_eq!(serde_json::to_string(&c).unwrap(), "\"#3366FF\"");
+        let c = Color::rgba(1, 2, 3, 4);
+        let back: Color = serde_json::from_str(&serde_json::to_string(&c).unwrap()).unwrap();
+        assert_eq!(back, c);
+        assert!(serde_json::from_str::<Color>("\"nope\"").is_err());
+    }
+
+    #[test]
+    fn alpha_composes_with_opacity() {
+        assert_eq!(Color::rgb(0, 0, 0).alpha_with(0.8), 204);
+        assert_eq!(Color::rgba(0, 0, 0, 128).alpha_with(0.5), 64);
+        assert_eq!(Color::rgba(0, 0, 0, 200).alpha_with(2.0), 200);
+    }
+
+    #[test]
+    fn legacy_annotation_json() {
+        let json = r##"{"meta":{"id":"0190a0a0-0000-7000-8000-000000000001","x":0.0,"y":0.0,
+            "w":10.0,"h":10.0,"rotation":0,"opacity":1.0,"stroke_color":"#FF0000",
+            "fill_color":null,"stroke_width":2.0,"z":0,"locked":false,"created_at":0},
+            "kind":{"type":"rect","corner_radius":0}}"##;
+        let a: crate::model::Annotation = serde_json::from_str(json).unwrap();
+        assert_eq!(a.meta.stroke_color, Some(Color::rgb(255, 0, 0)));
+        assert!(a.meta.fill_color.is_none());
+
+        let bad = json.replace("#FF0000", "#FF00000");
+        let err = serde_json::from_str::<crate::model::Annotation>(&bad).unwrap_err();
+        assert!(err.to_string().contains("invalid color"));
+    }
+}
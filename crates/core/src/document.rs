@@ -0,0 +1,411 @@
+//! 标注文档的版本化封装与迁移
+//!
+//! 持久化的标注数据（项目中的 `annotations.json` / `history.json`）外层带版本号：
+//! ```json
+//! { "version": 3, "annotations": [ ... ] }
+//! { "version": 3, "history": { "ops": [ ... ], "cap": 200, "redo": [] } }
+//! ```
+//! 读取时先识别版本，再由 `MigrationRegistry` 逐版本升级每条标注的 JSON，
+//! 最后按当前模型反序列化。没有版本号的裸数组 / 裸 `UndoStack` 视为 v1。
+use crate::error::{Error, ErrorKind, Result};
+use crate::model::Annotation;
+use crate::undo::UndoStack;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// 当前标注数据格式版本；新增需要迁移的字段或语义变更时递增，并注册对应迁移
+pub const ANNOTATION_SCHEMA_VERSION: u32 = 3;
+
+/// 版本化的标注列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationDocument {
+    pub version: u32,
+    pub annotations: Vec<Annotation>,
+}
+
+/// 版本化的撤销历史
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryDocument {
+    pub version: u32,
+    pub history: UndoStack,
+}
+
+impl AnnotationDocument {
+    pub fn new(annotations: Vec<Annotation>) -> Self {
+        Self {
+            version: ANNOTATION_SCHEMA_VERSION,
+            annotations,
+        }
+    }
+
+    /// 解析任意已知版本的 JSON 并升级到当前版本
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        MigrationRegistry::builtin().upgrade_annotations(parse(bytes)?)
+    }
+
+    /// 以当前版本写出（借用标注列表，无需先构造文档）
+    pub fn write<W: Write>(writer: W, annotations: &[Annotation]) -> Result<()> {
+        #[derive(Serialize)]
+        struct Borrowed<'a> {
+            version: u32,
+            annotations: &'a [Annotation],
+        }
+        write_json(
+            writer,
+            &Borrowed {
+                version: ANNOTATION_SCHEMA_VERSION,
+                annotations,
+            },
+        )
+    }
+}
+
+impl HistoryDocument {
+    pub fn new(history: UndoStack) -> Self {
+        Self {
+            version: ANNOTATION_SCHEMA_VERSION,
+            history,
+        }
+    }
+
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        MigrationRegistry::builtin().upgrade_history(parse(bytes)?)
+    }
+
+    pub fn write<W: Write>(writer: W, history: &UndoStack) -> Result<()> {
+        #[derive(Serialize)]
+        struct Borrowed<'a> {
+            version: u32,
+            history: &'a UndoStack,
+        }
+        write_json(
+            writer,
+            &Borrowed {
+                version: ANNOTATION_SCHEMA_VERSION,
+                history,
+            },
+        )
+    }
+}
+
+/// 单步迁移：把 `from` 版本的一条标注 JSON 原地改写为 `from + 1` 版本
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub migrate: fn(&mut Value) -> Result<()>,
+}
+
+/// 迁移注册表：按起始版本索引，升级时从文档版本依次执行到目标版本
+#[derive(Debug, Clone)]
+pub struct MigrationRegistry {
+    target: u32,
+    steps: BTreeMap<u32, Migration>,
+}
+
+impl MigrationRegistry {
+    /// 空注册表，目标版本为 `target`
+    pub fn new(target: u32) -> Self {
+        Self {
+            target,
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// 内置迁移，目标为 `ANNOTATION_SCHEMA_VERSION`
+    pub fn builtin() -> Self {
+        Self::new(ANNOTATION_SCHEMA_VERSION)
+            .with(Migration {
+                from: 1,
+                description: "normalize colors with v1 hex-only semantics",
+                migrate: v1_normalize_colors,
+            })
+            .with(Migration {
+                from: 2,
+                description: "new annotation kinds and defaulted fields; no rewrite",
+                migrate: v2_new_kinds,
+            })
+    }
+
+    /// 注册迁移（同一起始版本重复注册时后者覆盖前者）
+    pub fn with(mut self, migration: Migration) -> Self {
+        self.steps.insert(migration.from, migration);
+        self
+    }
+
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+
+    /// 校验版本是否可升级：0 视为损坏，高于目标视为由更新版本写入，中间缺少步骤视为无法升级
+    pub fn check(&self, version: u32) -> Result<()> {
+        if version == 0 {
+            return Err(Error::new(
+                ErrorKind::Validation,
+                "annotation schema version missing",
+            ));
+        }
+        if version > self.target {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "annotation schema v{} is newer than supported v{}",
+                    version, self.target
+                ),
+            ));
+        }
+        match (version..self.target).find(|v| !self.steps.contains_key(v)) {
+            Some(v) => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("no migration registered from annotation schema v{}", v),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// 将一条标注 JSON 从 `version` 升级到目标版本
+    pub fn upgrade_annotation(&self, annotation: &mut Value, version: u32) -> Result<()> {
+        self.check(version)?;
+        for step in self.steps.range(version..self.target).map(|(_, m)| m) {
+            (step.migrate)(annotation).map_err(|e| {
+                Error::new(
+                    e.kind,
+                    format!(
+                        "migration v{} ({}) failed: {}",
+                        step.from, step.description, e.message
+                    ),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 升级标注文档：接受裸数组（v1）或 `{version, annotations}` 封装
+    pub fn upgrade_annotations(&self, value: Value) -> Result<AnnotationDocument> {
+        let (version, mut list) = match value {
+            Value::Array(list) => (1, list),
+            Value::Object(mut obj) => {
+                let version = envelope_version(&obj)?;
+                match obj.remove("annotations") {
+                    Some(Value::Array(list)) => (version, list),
+                    _ => return Err(invalid("annotation document has no annotations array")),
+                }
+            }
+            _ => return Err(invalid("annotation document must be an array or object")),
+        };
+        self.check(version)?;
+        for a in list.iter_mut() {
+            self.upgrade_annotation(a, version)?;
+        }
+        let annotations = serde_json::from_value(Value::Array(list)).map_err(from_json_error)?;
+        Ok(AnnotationDocument {
+            version: self.target,
+            annotations,
+        })
+    }
+
+    /// 升级撤销历史：接受裸 `UndoStack`（v1）或 `{version, history}` 封装；
+    /// 历史命令中内嵌的每条标注都按同样的步骤迁移
+    pub fn upgrade_history(&self, value: Value) -> Result<HistoryDocument> {
+        let Value::Object(mut obj) = value else {
+            return Err(invalid("history document must be an object"));
+        };
+        let (version, mut stack) = if obj.contains_key("version") {
+            let version = envelope_version(&obj)?;
+            match obj.remove("history") {
+                Some(h) => (version, h),
+                None => return Err(invalid("history document has no history")),
+            }
+        } else {
+            (1, Value::Object(obj))
+        };
+        self.check(version)?;
+        for key in ["ops", "redo"] {
+            if let Some(Value::Array(ops)) = stack.get_mut(key) {
+                for op in ops.iter_mut() {
+                    if let Some(cmd) = op.get_mut("command") {
+                        self.upgrade_command(cmd, version)?;
+                    }
+                }
+            }
+        }
+        let history = serde_json::from_value(stack).map_err(from_json_error)?;
+        Ok(HistoryDocument {
+            version: self.target,
+            history,
+        })
+    }
+
+    fn upgrade_command(&self, cmd: &mut Value, version: u32) -> Result<()> {
+        for key in ["annotation", "before", "after"] {
+            if let Some(a) = cmd.get_mut(key) {
+                self.upgrade_annotation(a, version)?;
+            }
+        }
+        if let Some(Value::Array(children)) = cmd.get_mut("commands") {
+            for c in children.iter_mut() {
+                self.upgrade_command(c, version)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_json<W: Write, T: Serialize>(writer: W, value: &T) -> Result<()> {
+    serde_json::to_writer(writer, value)
+        .map_err(|e| Error::new(ErrorKind::Io, format!("write annotation json: {e}")))
+}
+
+fn parse(bytes: &[u8]) -> Result<Value> {
+    serde_json::from_slice(bytes).map_err(from_json_error)
+}
+
+fn envelope_version(obj: &Map<String, Value>) -> Result<u32> {
+    obj.get("version")
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| invalid("document version must be an unsigned integer"))
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::Validation, msg)
+}
+
+fn from_json_error(e: serde_json::Error) -> Error {
+    Error::new(
+        ErrorKind::Validation,
+        format!("invalid annotation json: {e}"),
+    )
+}
+
+/// v1 → v2：v1 渲染器只认 6 位十六进制（`#` 可省略），其余取值被静默忽略或回退为白色。
+/// 迁移时按 v1 的实际渲染效果改写为规范颜色，保证旧文档外观不变且能通过严格的颜色校验。
+fn v1_normalize_colors(annotation: &mut Value) -> Result<()> {
+    let kind = annotation
+        .pointer("/kind/type")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let Some(Value::Object(meta)) = annotation.get_mut("meta") else {
+        return Err(invalid("annotation has no meta"));
+    };
+    let fill = meta.get("fill_color").and_then(v1_hex);
+    let stroke = meta.get("stroke_color").and_then(v1_hex);
+    let (fill, stroke) = match kind.as_str() {
+        // 描边 / 手绘 / 箭头缺省白色
+        "arrow" | "freehand" => (fill, Some(stroke.unwrap_or_else(|| "#FFFFFF".into()))),
+        // 文字优先 fill，其次 stroke，再次白色
+        "text" => (
+            Some(
+                fill.or_else(|| stroke.clone())
+                    .unwrap_or_else(|| "#FFFFFF".into()),
+            ),
+            stroke,
+        ),
+        _ => (fill, stroke),
+    };
+    meta.insert("fill_color".into(), fill.map_or(Value::Null, Value::String));
+    meta.insert(
+        "stroke_color".into(),
+        stroke.map_or(Value::Null, Value::String),
+    );
+    Ok(())
+}
+
+/// v2 → v3：新增 Ellipse / Line 等标注类型与若干带缺省值的字段，已有文档无需改写。
+/// 递增版本只为让旧版本读到新类型时报告“文档版本过新”，而不是“未知类型”。
+fn v2_new_kinds(_annotation: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+/// 按 v1 规则解析颜色：可选 `#` + 6 位十六进制，返回规范的 `#RRGGBB`
+fn v1_hex(v: &Value) -> Option<String> {
+    let s = v.as_str()?;
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    (hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| format!("#{}", hex.to_ascii_uppercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn version_checks() {
+        let reg = MigrationRegistry::builtin();
+        assert!(reg.check(1).is_ok());
+        assert!(reg.check(ANNOTATION_SCHEMA_VERSION).is_ok());
+        assert!(matches!(
+            reg.check(0).unwrap_err().kind,
+            ErrorKind::Validation
+        ));
+        assert!(matches!(
+            reg.check(ANNOTATION_SCHEMA_VERSION + 1).unwrap_err().kind,
+            ErrorKind::Unsupported
+        ));
+        // 缺少中间步骤
+        let gap = MigrationRegistry::new(3).with(Migration {
+            from: 2,
+            description: "noop",
+            migrate: |_| Ok(()),
+        });
+        assert!(gap.check(2).is_ok());
+        assert!(matches!(
+            gap.check(1).unwrap_err().kind,
+            ErrorKind::Unsupported
+        ));
+    }
+
+    #[test]
+    fn steps_run_in_order() {
+        let step = |from: u32, migrate: fn(&mut Value) -> Result<()>| Migration {
+            from,
+            description: "append",
+            migrate,
+        };
+        // 注册顺序与执行顺序无关
+        let reg = MigrationRegistry::new(4)
+            .with(step(3, |v| push(v, "c")))
+            .with(step(1, |v| push(v, "a")))
+            .with(step(2, |v| push(v, "b")));
+        let mut v = json!({ "trail": [] });
+        reg.upgrade_annotation(&mut v, 1).unwrap();
+        assert_eq!(v["trail"], json!(["a", "b", "c"]));
+        let mut v = json!({ "trail": [] });
+        reg.upgrade_annotation(&mut v, 3).unwrap();
+        assert_eq!(v["trail"], json!(["c"]));
+    }
+
+    fn push(v: &mut Value, s: &str) -> Result<()> {
+        v["trail"].as_array_mut().unwrap().push(json!(s));
+        Ok(())
+    }
+
+    #[test]
+    fn v1_colors_follow_old_renderer() {
+        let mut a = json!({
+            "meta": { "stroke_color": "#F00", "fill_color": "00ff00" },
+            "kind": { "type": "rect", "corner_radius": 0 }
+        });
+        v1_normalize_colors(&mut a).unwrap();
+        assert_eq!(a["meta"]["fill_color"], json!("#00FF00"));
+        assert_eq!(a["meta"]["stroke_color"], Value::Null);
+
+        let mut a = json!({
+            "meta": { "stroke_color": "bogus", "fill_color": null },
+            "kind": { "type": "arrow" }
+        });
+        v1_normalize_colors(&mut a).unwrap();
+        assert_eq!(a["meta"]["stroke_color"], json!("#FFFFFF"));
+
+        let mut a = json!({
+            "meta": { "stroke_color": "#123456", "fill_color": "red" },
+            "kind": { "type": "text" }
+        });
+        v1_normalize_colors(&mut a).unwrap();
+        assert_eq!(a["meta"]["fill_color"], json!("#123456"));
+    }
+}
@@ -0,0 +1,969 @@
+//! 标注几何：旋转、包围盒与基础点包含判断
+//!
+//! 约定：`AnnotationMeta.rotation` 为角度制，绕标注局部包围盒中心旋转；
+//! 屏幕坐标 y 轴向下，正角度在视觉上为顺时针。
+use crate::model::{Annotation, AnnotationKind, ArrowHeadShape, ArrowHeads, LineCap, MeasureMode};
+use crate::snap::Rect;
+
+/// 角度对应的 (sin, cos)；90° 的整数倍返回精确值，避免浮点误差导致边缘抖动
+pub fn rotation_sin_cos(degrees: u16) -> (f32, f32) {
+    match degrees % 360 {
+        0 => (0.0, 1.0),
+        90 => (1.0, 0.0),
+        180 => (0.0, -1.0),
+        270 => (-1.0, 0.0),
+        d => (d as f32).to_radians().sin_cos(),
+    }
+}
+
+/// 将点 p 绕 center 旋转 degrees 度
+pub fn rotate_point(p: (f32, f32), center: (f32, f32), degrees: u16) -> (f32, f32) {
+    let (sin, cos) = rotation_sin_cos(degrees);
+    let (dx, dy) = (p.0 - center.0, p.1 - center.1);
+    (
+        center.0 + dx * cos - dy * sin,
+        center.1 + dx * sin + dy * cos,
+    )
+}
+
+/// `rotate_point` 的逆变换
+pub fn unrotate_point(p: (f32, f32), center: (f32, f32), degrees: u16) -> (f32, f32) {
+    let (sin, cos) = rotation_sin_cos(degrees);
+    let (dx, dy) = (p.0 - center.0, p.1 - center.1);
+    (
+        center.0 + dx * cos + dy * sin,
+        center.1 - dx * sin + dy * cos,
+    )
+}
+
+impl Rect {
+    /// 由两个角点构造（自动规范化负宽高）
+    pub fn from_points(a: (f32, f32), b: (f32, f32)) -> Self {
+        Rect {
+            x: a.0.min(b.0),
+            y: a.1.min(b.1),
+            w: (a.0 - b.0).abs(),
+            h: (a.1 - b.1).abs(),
+        }
+    }
+
+    /// 点集的最小包围盒；空集返回 None
+    pub fn bounding(points: impl IntoIterator<Item = (f32, f32)>) -> Option<Self> {
+        let mut it = points.into_iter();
+        let first = it.next()?;
+        let (mut x0, mut y0, mut x1, mut y1) = (first.0, first.1, first.0, first.1);
+        for (x, y) in it {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+        Some(Rect::from_points((x0, y0), (x1, y1)))
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.x + self.w / 2.0, self.y + self.h / 2.0)
+    }
+
+    pub fn corners(&self) -> [(f32, f32); 4] {
+        [
+            (self.x, self.y),
+            (self.x + self.w, self.y),
+            (self.x + self.w, self.y + self.h),
+            (self.x, self.y + self.h),
+        ]
+    }
+
+    /// 四周各扩展 d（d 为负时收缩，宽高不小于 0）
+    pub fn inflate(&self, d: f32) -> Self {
+        Rect {
+            x: self.x - d,
+            y: self.y - d,
+            w: (self.w + 2.0 * d).max(0.0),
+            h: (self.h + 2.0 * d).max(0.0),
+        }
+    }
+
+    /// 闭区间包含判断（边界上的点算在内）
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.w && y >= self.y && y <= self.y + self.h
+    }
+
+    /// 点是否在内切椭圆中（含边界）；宽或高为 0 时不包含任何点
+    pub fn ellipse_contains(&self, x: f32, y: f32) -> bool {
+        if self.w <= 0.0 || self.h <= 0.0 {
+            return false;
+        }
+        let (cx, cy) = self.center();
+        let (nx, ny) = ((x - cx) / (self.w / 2.0), (y - cy) / (self.h / 2.0));
+        nx * nx + ny * ny <= 1.0
+    }
+
+    /// 点是否在圆角矩形内（含边界）；radius 超过短边一半时按短边一半处理
+    pub fn rounded_contains(&self, x: f32, y: f32, radius: f32) -> bool {
+        if !self.contains(x, y) {
+            return false;
+        }
+        let r = radius.clamp(0.0, self.w.min(self.h) / 2.0);
+        // 到最近圆角圆心的距离；不在角区时 q 即点本身
+        let qx = x.clamp(self.x + r, self.x + self.w - r);
+        let qy = y.clamp(self.y + r, self.y + self.h - r);
+        (x - qx).powi(2) + (y - qy).powi(2) <= r * r
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x <= other.x + other.w
+            && other.x <= self.x + self.w
+            && self.y <= other.y + other.h
+            && other.y <= self.y + self.h
+    }
+}
+
+/// 点是否在三角形内（含边界，顶点顺序任意）
+pub fn point_in_triangle(p: (f32, f32), tri: [(f32, f32); 3]) -> bool {
+    let cross =
+        |a: (f32, f32), b: (f32, f32)| (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+    let d = [
+        cross(tri[0], tri[1]),
+        cross(tri[1], tri[2]),
+        cross(tri[2], tri[0]),
+    ];
+    !(d.iter().any(|&v| v < 0.0) && d.iter().any(|&v| v > 0.0))
+}
+
+/// 点是否在多边形内（非零环绕规则，首尾自动闭合；少于 3 个顶点时不包含任何点）
+pub fn point_in_polygon(p: (f32, f32), points: &[(f32, f32)]) -> bool {
+    if points.len() < 3 {
+        return false;
+    }
+    let mut winding = 0i32;
+    for (i, &a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let side = (b.0 - a.0) * (p.1 - a.1) - (p.0 - a.0) * (b.1 - a.1);
+        if a.1 <= p.1 && b.1 > p.1 && side > 0.0 {
+            winding += 1;
+        } else if a.1 > p.1 && b.1 <= p.1 && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding != 0
+}
+
+/// 箭头头部三角形：尖端为 tip，沿 from -> tip 方向回退 size 处为底边中点，底宽 size；
+/// from 与 tip 重合或 size 不为正时返回 None
+pub fn arrow_head(from: (f32, f32), tip: (f32, f32), size: f32) -> Option<[(f32, f32); 3]> {
+    let (dx, dy) = (tip.0 - from.0, tip.1 - from.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len <= 0.0 || size <= 0.0 {
+        return None;
+    }
+    let (ux, uy) = (dx / len, dy / len);
+    let (bx, by) = (tip.0 - ux * size, tip.1 - uy * size);
+    let w = size / 2.0;
+    Some([tip, (bx - uy * w, by + ux * w), (bx + uy * w, by - ux * w)])
+}
+
+/// 贝塞尔曲线展平为折线（含两端点）：controls 为空时是直线，1 个为二次，2 个及以上取前两个为三次；
+/// 分段数按控制多边形长度估算（约 4px 一段，8..=128 段）
+pub fn flatten_bezier(
+    from: (f32, f32),
+    controls: &[(f32, f32)],
+    to: (f32, f32),
+) -> Vec<(f32, f32)> {
+    let dist = |a: (f32, f32), b: (f32, f32)| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+    let hull: Vec<(f32, f32)> = std::iter::once(from)
+        .chain(controls.iter().take(2).copied())
+        .chain(std::iter::once(to))
+        .collect();
+    if hull.len() == 2 {
+        return hull;
+    }
+    let len: f32 = hull.windows(2).map(|w| dist(w[0], w[1])).sum();
+    let n = if len.is_finite() {
+        ((len / 4.0).ceil() as usize).clamp(8, 128)
+    } else {
+        8
+    };
+    (0..=n)
+        .map(|i| {
+            let t = i as f32 / n as f32;
+            // de Casteljau：逐层线性插值直到剩一个点
+            let mut pts = hull.clone();
+            while pts.len() > 1 {
+                pts = pts
+                    .windows(2)
+                    .map(|w| {
+                        (
+                            w[0].0 + (w[1].0 - w[0].0) * t,
+                            w[0].1 + (w[1].1 - w[0].1) * t,
+                        )
+                    })
+                    .collect();
+            }
+            pts[0]
+        })
+        .collect()
+}
+
+/// 从折线末端裁掉 len 长度（整条不足时保留起点）
+fn trim_path_end(path: &mut Vec<(f32, f32)>, mut len: f32) {
+    while path.len() >= 2 && len > 0.0 {
+        let b = path[path.len() - 1];
+        let a = path[path.len() - 2];
+        let seg = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        if seg > len {
+            let t = (seg - len) / seg;
+            *path.last_mut().unwrap() = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+            return;
+        }
+        len -= seg;
+        path.pop();
+    }
+}
+
+/// 箭头头部的几何（局部坐标），renderer 与命中测试共用
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrowHead {
+    /// 实心三角形：[尖端, 底角, 底角]
+    Triangle([(f32, f32); 3]),
+    /// 开口 V 形，两翼从尖端出发描边：[尖端, 翼端, 翼端]
+    OpenV([(f32, f32); 3]),
+    /// 实心圆
+    Circle { center: (f32, f32), radius: f32 },
+    /// 垂直于线条的短横线
+    Bar((f32, f32), (f32, f32)),
+}
+
+impl ArrowHead {
+    /// 头部轮廓顶点（首尾隐式闭合）：三角 / V 形为三个顶点，横线为两端点，圆形为外切正八边形
+    pub fn outline(&self) -> Vec<(f32, f32)> {
+        match *self {
+            ArrowHead::Triangle(tri) | ArrowHead::OpenV(tri) => tri.to_vec(),
+            ArrowHead::Bar(a, b) => vec![a, b],
+            ArrowHead::Circle { center, radius } => {
+                let r = radius / std::f32::consts::FRAC_PI_8.cos();
+                (0..8)
+                    .map(|i| {
+                        let t = (i as f32 + 0.5) * std::f32::consts::FRAC_PI_4;
+                        (center.0 + r * t.cos(), center.1 + r * t.sin())
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// 箭头的线条路径与头部
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrowGeometry {
+    /// 线条折线；三角形头部所在端已裁到底边，避免线帽穿出尖端
+    pub path: Vec<(f32, f32)>,
+    pub heads: Vec<ArrowHead>,
+}
+
+/// 测量标注的一条尺寸线（局部坐标）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dimension {
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+    /// 物理像素长度
+    pub length: f32,
+}
+
+impl Dimension {
+    /// 端点短线总长（垂直于尺寸线，两侧各一半）
+    pub const TICK: f32 = 8.0;
+
+    /// 标签文字；scale 取所属截图的 `Screenshot.scale`，见 `measure_label`
+    pub fn label(&self, scale: f32) -> String {
+        measure_label(self.length, scale)
+    }
+}
+
+/// 测量标签：物理像素如 "13px"；scale 有效且不为 1 时附加逻辑点，如 "13px / 6.5pt"。
+/// 数值四舍五入到 0.1，整数不带小数
+pub fn measure_label(px: f32, scale: f32) -> String {
+    let fmt = |v: f32| {
+        let r = (v * 10.0).round() / 10.0;
+        if r.fract() == 0.0 {
+            format!("{}", r as i64)
+        } else {
+            format!("{r:.1}")
+        }
+    };
+    if scale.is_finite() && scale > 0.0 && (scale - 1.0).abs() > 1e-3 {
+        format!("{}px / {}pt", fmt(px), fmt(px / scale))
+    } else {
+        format!("{}px", fmt(px))
+    }
+}
+
+/// 标注框（Callout）的轮廓：圆角框体 + 指向 anchor 的三角尾巴，均为局部坐标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalloutShape {
+    pub body: Rect,
+    /// 实际使用的圆角半径（已限制在短边一半以内）
+    pub radius: f32,
+    /// 底边两端点 + 尖端；anchor 落在框体内时没有尾巴
+    pub tail: Option<[(f32, f32); 3]>,
+}
+
+impl CalloutShape {
+    /// 尾巴最大底宽
+    pub const MAX_TAIL_BASE: f32 = 24.0;
+
+    /// 尾巴从 anchor 所在方向的那条边伸出，底宽取该边长的 30%（不超过 `MAX_TAIL_BASE`），
+    /// 底边中点尽量对准 anchor，但不侵入圆角
+    pub fn new(body: Rect, corner_radius: f32, anchor: (f32, f32)) -> Self {
+        let radius = corner_radius.clamp(0.0, body.w.min(body.h) / 2.0);
+        let mut tail = None;
+        if body.w > 0.0 && body.h > 0.0 && !body.contains(anchor.0, anchor.1) {
+            let (cx, cy) = body.center();
+            let (dx, dy) = (anchor.0 - cx, anchor.1 - cy);
+            // anchor 方向与对角线比较，决定从左右边还是上下边伸出
+            let horizontal = dx.abs() * body.h > dy.abs() * body.w;
+            let (start, len, along) = if horizontal {
+                (body.y, body.h, anchor.1)
+            } else {
+                (body.x, body.w, anchor.0)
+            };
+            let base = (len * 0.3).min(Self::MAX_TAIL_BASE);
+            let (lo, hi) = (
+                start + radius + base / 2.0,
+                start + len - radius - base / 2.0,
+            );
+            let mid = if lo <= hi {
+                along.clamp(lo, hi)
+            } else {
+                start + len / 2.0
+            };
+            let (a, b) = (mid - base / 2.0, mid + base / 2.0);
+            tail = Some(if horizontal {
+                let edge = if dx > 0.0 { body.x + body.w } else { body.x };
+                [(edge, a), (edge, b), anchor]
+            } else {
+                let edge = if dy > 0.0 { body.y + body.h } else { body.y };
+                [(a, edge), (b, edge), anchor]
+            });
+        }
+        Self { body, radius, tail }
+    }
+
+    /// 点是否落在框体或尾巴内（含边界）
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        self.body.rounded_contains(x, y, self.radius)
+            || self.tail.is_some_and(|t| point_in_triangle((x, y), t))
+    }
+}
+
+impl Annotation {
+    /// Callout 的框体与尾巴；其他类型返回 None
+    pub fn callout_shape(&self) -> Option<CalloutShape> {
+        let AnnotationKind::Callout {
+            anchor,
+            corner_radius,
+            ..
+        } = &self.kind
+        else {
+            return None;
+        };
+        let m = &self.meta;
+        let body = Rect::from_points((m.x, m.y), (m.x + m.w, m.y + m.h));
+        Some(CalloutShape::new(body, *corner_radius as f32, *anchor))
+    }
+
+    /// Arrow 的线条与头部（局部坐标）；其他类型返回 None
+    ///
+    /// 头部方向取路径在端点处的切线（展平后相邻点的方向），曲线箭头的头部沿曲线末端朝向。
+    pub fn arrow_geometry(&self) -> Option<ArrowGeometry> {
+        let AnnotationKind::Arrow {
+            head_size,
+            controls,
+            heads,
+            head_shape,
+            ..
+        } = &self.kind
+        else {
+            return None;
+        };
+        let m = &self.meta;
+        let mut path = flatten_bezier((m.x, m.y), controls, (m.x + m.w, m.y + m.h));
+        let size = *head_size as f32;
+        let ends: &[bool] = match heads {
+            ArrowHeads::Start => &[true],
+            ArrowHeads::End => &[false],
+            ArrowHeads::Both => &[true, false],
+        };
+        let mut out = Vec::new();
+        let mut trims = Vec::new();
+        for &start in ends {
+            let tip = if start { path[0] } else { path[path.len() - 1] };
+            // 端点处的切线方向：跳过与端点重合的点
+            let from = if start {
+                path.iter().find(|&&q| q != tip)
+            } else {
+                path.iter().rev().find(|&&q| q != tip)
+            };
+            let Some(tri) = from.and_then(|&from| arrow_head(from, tip, size)) else {
+                continue;
+            };
+            let [_, a, b] = tri;
+            out.push(match head_shape {
+                ArrowHeadShape::Triangle => {
+                    trims.push(start);
+                    ArrowHead::Triangle(tri)
+                }
+                ArrowHeadShape::OpenV => ArrowHead::OpenV(tri),
+                ArrowHeadShape::Circle => ArrowHead::Circle {
+                    center: tip,
+                    radius: size / 2.0,
+                },
+                // 底角平移到尖端即为过端点的垂线
+                ArrowHeadShape::Bar => {
+                    let (dx, dy) = (tip.0 - (a.0 + b.0) / 2.0, tip.1 - (a.1 + b.1) / 2.0);
+                    ArrowHead::Bar((a.0 + dx, a.1 + dy), (b.0 + dx, b.1 + dy))
+                }
+            });
+        }
+        // 两端都裁时各自最多裁一半，保证线条不被吃光
+        let total: f32 = path
+            .windows(2)
+            .map(|w| ((w[1].0 - w[0].0).powi(2) + (w[1].1 - w[0].1).powi(2)).sqrt())
+            .sum();
+        let trim = size.min(total / trims.len().max(1) as f32);
+        for start in trims {
+            if start {
+                path.reverse();
+                trim_path_end(&mut path, trim);
+                path.reverse();
+            } else {
+                trim_path_end(&mut path, trim);
+            }
+        }
+        Some(ArrowGeometry { path, heads: out })
+    }
+
+    /// Measure 的尺寸线：Line 模式一条，Rect 模式为上边（宽）与左边（高）两条；长度为 0 的省略。
+    /// 其他类型返回空
+    pub fn dimensions(&self) -> Vec<Dimension> {
+        let AnnotationKind::Measure { mode } = &self.kind else {
+            return Vec::new();
+        };
+        let m = &self.meta;
+        let lines = match mode {
+            MeasureMode::Line => vec![((m.x, m.y), (m.x + m.w, m.y + m.h))],
+            MeasureMode::Rect => {
+                let r = Rect::from_points((m.x, m.y), (m.x + m.w, m.y + m.h));
+                vec![
+                    ((r.x, r.y), (r.x + r.w, r.y)),
+                    ((r.x, r.y), (r.x, r.y + r.h)),
+                ]
+            }
+        };
+        lines
+            .into_iter()
+            .filter_map(|(from, to)| {
+                let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+                (length > 0.0).then_some(Dimension { from, to, length })
+            })
+            .collect()
+    }
+
+    /// Polygon / Polyline 的顶点（其余类型为 None），供顶点级编辑
+    pub fn vertices(&self) -> Option<&[(f32, f32)]> {
+        match &self.kind {
+            AnnotationKind::Polygon { points, .. } | AnnotationKind::Polyline { points, .. } => {
+                Some(points)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn vertices_mut(&mut self) -> Option<&mut Vec<(f32, f32)>> {
+        match &mut self.kind {
+            AnnotationKind::Polygon { points, .. } | AnnotationKind::Polyline { points, .. } => {
+                Some(points)
+            }
+            _ => None,
+        }
+    }
+
+    /// 顶点数下限：Polygon 为 3，Polyline 为 2
+    pub fn min_vertices(&self) -> usize {
+        match self.kind {
+            AnnotationKind::Polygon { .. } => 3,
+            _ => 2,
+        }
+    }
+
+    /// 未旋转时的局部包围盒：Freehand / Highlighter / Polygon / Polyline 取点集范围，Callout 包含尾巴尖端，
+    /// 曲线箭头取展平后的曲线范围，其余取 meta 的 (x,y,w,h)（Arrow 的负宽高已规范化）
+    pub fn local_bounds(&self) -> Rect {
+        let m = &self.meta;
+        match &self.kind {
+            AnnotationKind::Freehand { points, .. }
+            | AnnotationKind::Highlighter { points, .. }
+            | AnnotationKind::Polygon { points, .. }
+            | AnnotationKind::Polyline { points, .. } => {
+                if let Some(r) = Rect::bounding(points.iter().copied()) {
+                    return r;
+                }
+            }
+            AnnotationKind::Arrow { controls, .. } if !controls.is_empty() => {
+                let curve = flatten_bezier((m.x, m.y), controls, (m.x + m.w, m.y + m.h));
+                if let Some(r) = Rect::bounding(curve) {
+                    return r;
+                }
+            }
+            AnnotationKind::Callout { anchor, .. } => {
+                let corners = [(m.x, m.y), (m.x + m.w, m.y + m.h), *anchor];
+                if let Some(r) = Rect::bounding(corners) {
+                    return r;
+                }
+            }
+            _ => {}
+        }
+        Rect::from_points((m.x, m.y), (m.x + m.w, m.y + m.h))
+    }
+
+    /// 旋转中心（局部包围盒中心）
+    pub fn rotation_center(&self) -> (f32, f32) {
+        self.local_bounds().center()
+    }
+
+    /// 局部包围盒旋转后的四个角（顺序：左上、右上、右下、左下）
+    pub fn rotated_corners(&self) -> [(f32, f32); 4] {
+        let local = self.local_bounds();
+        let c = local.center();
+        local
+            .corners()
+            .map(|p| rotate_point(p, c, self.meta.rotation))
+    }
+
+    /// 旋转后的轴对齐包围盒，包含描边半宽（方头直线按端点方块的对角线外扩，测量标注包含端点短线，
+    /// 箭头包含各端头部轮廓，带箭头的折线外扩 head_size 以容纳侧向伸出的头部）
+    pub fn bounds(&self) -> Rect {
+        let mut half = self.stroke_half_width();
+        let mut local = self.local_bounds();
+        match &self.kind {
+            AnnotationKind::Line {
+                cap: LineCap::Square,
+                ..
+            } => half *= std::f32::consts::SQRT_2,
+            AnnotationKind::Measure { .. } => half = half.max(Dimension::TICK / 2.0),
+            AnnotationKind::Arrow { .. } => {
+                if let Some(geo) = self.arrow_geometry() {
+                    let heads = geo.heads.iter().flat_map(ArrowHead::outline);
+                    local =
+                        Rect::bounding(local.corners().into_iter().chain(heads)).unwrap_or(local);
+                }
+            }
+            AnnotationKind::Polyline {
+                start_arrow,
+                end_arrow,
+                head_size,
+                ..
+            } if *start_arrow || *end_arrow => local = local.inflate(*head_size as f32),
+            _ => {}
+        }
+        // 绕局部包围盒中心旋转；角点集合非空，bounding 必有值
+        let c = self.rotation_center();
+        Rect::bounding(
+            local
+                .corners()
+                .map(|p| rotate_point(p, c, self.meta.rotation)),
+        )
+        .unwrap_or(local)
+        .inflate(half)
+    }
+
+    /// Rect / Highlight / Mosaic 实际使用的圆角半径：限制在局部包围盒短边一半以内，其余类型为 0
+    pub fn corner_radius(&self) -> f32 {
+        let radius = match self.kind {
+            AnnotationKind::Rect { corner_radius }
+            | AnnotationKind::Highlight { corner_radius, .. }
+            | AnnotationKind::Mosaic { corner_radius, .. } => corner_radius as f32,
+            _ => return 0.0,
+        };
+        let local = self.local_bounds();
+        radius.min(local.w.min(local.h) / 2.0).max(0.0)
+    }
+
+    /// 描边半宽；荧光笔未设置线宽时按 `HIGHLIGHTER_WIDTH` 计算
+    pub fn stroke_half_width(&self) -> f32 {
+        let default = match self.kind {
+            AnnotationKind::Highlighter { .. } => AnnotationKind::HIGHLIGHTER_WIDTH,
+            _ => 0.0,
+        };
+        self.meta.stroke_width.unwrap_or(default).max(0.0) / 2.0
+    }
+
+    /// 将画布坐标转换到标注未旋转时的局部坐标
+    pub fn to_local(&self, x: f32, y: f32) -> (f32, f32) {
+        unrotate_point((x, y), self.rotation_center(), self.meta.rotation)
+    }
+
+    /// 点是否落在旋转后的局部包围盒内（含描边半宽）
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        let half = self.stroke_half_width();
+        let (lx, ly) = self.to_local(x, y);
+        self.local_bounds().inflate(half).contains(lx, ly)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AnnotationMeta, LineJoin};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn rect_ann(x: f32, y: f32, w: f32, h: f32, rotation: u16) -> Annotation {
+        Annotation {
+            meta: AnnotationMeta {
+                id: Uuid::now_v7(),
+                x,
+                y,
+                w,
+                h,
+                rotation,
+                opacity: 1.0,
+                stroke_color: None,
+                fill_color: None,
+                stroke_width: None,
+                z: 0,
+                locked: false,
+                created_at: Utc::now(),
+            },
+            kind: AnnotationKind::Rect { corner_radius: 0 },
+        }
+    }
+
+    fn approx(a: Rect, b: Rect) -> bool {
+        (a.x - b.x).abs() < 1e-3
+            && (a.y - b.y).abs() < 1e-3
+            && (a.w - b.w).abs() < 1e-3
+            && (a.h - b.h).abs() < 1e-3
+    }
+
+    #[test]
+    fn rotate_round_trip() {
+        let c = (10.0, 10.0);
+        for deg in [0, 30, 45, 90, 180, 270, 359] {
+            let p = rotate_point((13.0, 7.0), c, deg);
+            let q = unrotate_point(p, c, deg);
+            assert!((q.0 - 13.0).abs() < 1e-4 && (q.1 - 7.0).abs() < 1e-4);
+        }
+        // 屏幕坐标下 90° 将右侧点转到下方
+        assert_eq!(rotate_point((1.0, 0.0), (0.0, 0.0), 90), (0.0, 1.0));
+    }
+
+    #[test]
+    fn bounds_follow_rotation() {
+        let a = rect_ann(0.0, 0.0, 20.0, 10.0, 0);
+        assert!(approx(a.bounds(), a.local_bounds()));
+
+        let a = rect_ann(0.0, 0.0, 20.0, 10.0, 90);
+        assert!(approx(
+            a.bounds(),
+            Rect {
+                x: 5.0,
+                y: -5.0,
+                w: 10.0,
+                h: 20.0
+            }
+        ));
+
+        let a = rect_ann(0.0, 0.0, 20.0, 10.0, 180);
+        assert!(approx(a.bounds(), a.local_bounds()));
+
+        let a = rect_ann(0.0, 0.0, 10.0, 10.0, 45);
+        let d = 10.0 * std::f32::consts::SQRT_2;
+        assert!(approx(
+            a.bounds(),
+            Rect {
+                x: 5.0 - d / 2.0,
+                y: 5.0 - d / 2.0,
+                w: d,
+                h: d
+            }
+        ));
+    }
+
+    #[test]
+    fn contains_point_respects_rotation() {
+        // 40x10 横条旋转 90° 后变成竖条
+        let a = rect_ann(0.0, 15.0, 40.0, 10.0, 90);
+        assert!(a.contains_point(20.0, 2.0));
+        assert!(a.contains_point(20.0, 38.0));
+        assert!(!a.contains_point(2.0, 20.0));
+        assert!(!a.contains_point(38.0, 20.0));
+
+        let mut a = rect_ann(0.0, 0.0, 10.0, 10.0, 45);
+        // 45° 后角点 (0,0) 附近已不在形状内，而上方顶点附近在
+        assert!(!a.contains_point(0.5, 0.5));
+        assert!(a.contains_point(5.0, -1.5));
+        assert!(!a.contains_point(5.0, -3.5));
+        a.meta.stroke_width = Some(4.0);
+        assert!(a.contains_point(5.0, -3.5));
+    }
+
+    #[test]
+    fn freehand_bounds_use_points() {
+        let mut a = rect_ann(0.0, 0.0, 0.0, 0.0, 0);
+        a.kind = AnnotationKind::Freehand {
+            points: vec![(10.0, 20.0), (30.0, 25.0), (20.0, 40.0)],
+            smoothing: 0.0,
+            cap: LineCap::Round,
+            join: LineJoin::Round,
+        };
+        assert!(approx(
+            a.local_bounds(),
+            Rect {
+                x: 10.0,
+                y: 20.0,
+                w: 20.0,
+                h: 20.0
+            }
+        ));
+        assert_eq!(a.rotation_center(), (20.0, 30.0));
+    }
+
+    #[test]
+    fn arrow_bounds_include_heads() {
+        let mut a = rect_ann(10.0, 10.0, 20.0, 0.0, 0);
+        let arrow = |heads, head_shape| AnnotationKind::Arrow {
+            head_size: 8,
+            line_style: crate::model::LineStyle::Solid,
+            controls: vec![],
+            heads,
+            head_shape,
+        };
+        // 起点圆形头部伸出线段起点之外，包围盒必须盖住整个圆
+        a.kind = arrow(ArrowHeads::Start, ArrowHeadShape::Circle);
+        let b = a.bounds();
+        assert!(b.contains(6.0, 10.0) && b.contains(10.0, 6.0) && b.contains(10.0, 14.0));
+        // 两端三角形头部向两侧伸出 head_size / 2
+        a.kind = arrow(ArrowHeads::Both, ArrowHeadShape::Triangle);
+        let b = a.bounds();
+        for head in a.arrow_geometry().unwrap().heads {
+            assert!(head.outline().iter().all(|p| b.contains(p.0, p.1)), "{b:?}");
+        }
+        assert!(approx(
+            b,
+            Rect {
+                x: 10.0,
+                y: 6.0,
+                w: 20.0,
+                h: 8.0
+            }
+        ));
+        // 旋转 90° 后头部的侧向伸出落在横向
+        a.meta.rotation = 90;
+        let b = a.bounds();
+        assert!(
+            (b.w - 8.0).abs() < 1e-3 && (b.h - 20.0).abs() < 1e-3,
+            "{b:?}"
+        );
+    }
+
+    #[test]
+    fn polyline_bounds_include_arrow_heads() {
+        let mut a = rect_ann(0.0, 0.0, 0.0, 0.0, 0);
+        let polyline = |start_arrow, end_arrow| AnnotationKind::Polyline {
+            points: vec![(0.0, 0.0), (20.0, 0.0)],
+            line_style: crate::model::LineStyle::Solid,
+            start_arrow,
+            end_arrow,
+            head_size: 8,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+        };
+        a.kind = polyline(false, false);
+        assert!(approx(a.bounds(), a.local_bounds()));
+
+        // 水平折线的终点箭头向两侧伸出 head_size / 2，包围盒必须盖住整个三角形
+        a.kind = polyline(false, true);
+        let head = arrow_head((0.0, 0.0), (20.0, 0.0), 8.0).unwrap();
+        let b = a.bounds();
+        assert!(head.iter().all(|p| b.contains(p.0, p.1)), "{b:?}");
+        assert!(approx(b, a.local_bounds().inflate(8.0)));
+    }
+
+    #[test]
+    fn rounded_rect_trims_corners() {
+        let r = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: 20.0,
+            h: 10.0,
+        };
+        assert!(r.rounded_contains(0.5, 0.5, 0.0));
+        assert!(!r.rounded_contains(0.5, 0.5, 4.0));
+        assert!(r.rounded_contains(4.0, 0.5, 4.0));
+        assert!(
+            r.rounded_contains(10.0, 5.0, 100.0),
+            "radius clamps to half height"
+        );
+        assert!(!r.rounded_contains(0.5, 5.0 - 4.9, 100.0));
+    }
+
+    #[test]
+    fn callout_tail_points_at_anchor() {
+        let body = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: 100.0,
+            h: 40.0,
+        };
+        // anchor 在下方：尾巴从底边伸出，底边中点对准 anchor.x
+        let s = CalloutShape::new(body, 6.0, (30.0, 80.0));
+        assert_eq!(s.tail, Some([(18.0, 40.0), (42.0, 40.0), (30.0, 80.0)]));
+        assert!(s.contains(30.0, 60.0));
+        assert!(!s.contains(60.0, 60.0));
+
+        // anchor 在右侧且靠近角：底边中点被限制在圆角之外
+        let s = CalloutShape::new(body, 6.0, (150.0, 0.0));
+        let [a, b, tip] = s.tail.unwrap();
+        assert_eq!((a.0, b.0, tip), (100.0, 100.0, (150.0, 0.0)));
+        assert_eq!((a.1, b.1), (6.0, 18.0));
+
+        // anchor 在框内没有尾巴
+        assert!(CalloutShape::new(body, 0.0, (50.0, 20.0)).tail.is_none());
+    }
+
+    #[test]
+    fn polygon_winding_and_arrow_head() {
+        let square = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        assert!(point_in_polygon((5.0, 5.0), &square));
+        assert!(!point_in_polygon((15.0, 5.0), &square));
+        // 五角星中心环绕数为 2，非零规则下仍在内部
+        let star = [
+            (5.0, 0.0),
+            (8.0, 10.0),
+            (0.0, 4.0),
+            (10.0, 4.0),
+            (2.0, 10.0),
+        ];
+        assert!(point_in_polygon((5.0, 5.0), &star));
+        assert!(!point_in_polygon((5.0, 5.0), &square[..2]));
+
+        let [tip, a, b] = arrow_head((0.0, 0.0), (10.0, 0.0), 4.0).unwrap();
+        assert_eq!(tip, (10.0, 0.0));
+        assert_eq!((a, b), ((6.0, 2.0), (6.0, -2.0)));
+        assert!(arrow_head((1.0, 1.0), (1.0, 1.0), 4.0).is_none());
+    }
+
+    #[test]
+    fn arrow_geometry_heads_and_curves() {
+        let mut a = rect_ann(0.0, 0.0, 20.0, 0.0, 0);
+        let arrow = |controls, heads, head_shape| AnnotationKind::Arrow {
+            head_size: 4,
+            line_style: crate::model::LineStyle::Solid,
+            controls,
+            heads,
+            head_shape,
+        };
+        a.kind = arrow(vec![], ArrowHeads::Both, ArrowHeadShape::Triangle);
+        let g = a.arrow_geometry().unwrap();
+        // 三角头部两端各裁掉 head_size
+        assert_eq!(g.path, vec![(4.0, 0.0), (16.0, 0.0)]);
+        assert_eq!(
+            g.heads,
+            vec![
+                ArrowHead::Triangle([(0.0, 0.0), (4.0, -2.0), (4.0, 2.0)]),
+                ArrowHead::Triangle([(20.0, 0.0), (16.0, 2.0), (16.0, -2.0)]),
+            ]
+        );
+
+        a.kind = arrow(vec![], ArrowHeads::End, ArrowHeadShape::Bar);
+        let g = a.arrow_geometry().unwrap();
+        assert_eq!(g.path, vec![(0.0, 0.0), (20.0, 0.0)]);
+        assert_eq!(g.heads, vec![ArrowHead::Bar((20.0, 2.0), (20.0, -2.0))]);
+
+        // 二次曲线：包围盒取曲线范围，头部沿末端切线 (10,20) -> (20,0)
+        a.kind = arrow(vec![(10.0, 20.0)], ArrowHeads::End, ArrowHeadShape::OpenV);
+        assert!(approx(
+            a.local_bounds(),
+            Rect {
+                x: 0.0,
+                y: 0.0,
+                w: 20.0,
+                h: 10.0
+            }
+        ));
+        let g = a.arrow_geometry().unwrap();
+        assert_eq!(g.path.last(), Some(&(20.0, 0.0)));
+        let ArrowHead::OpenV([tip, l, r]) = g.heads[0] else {
+            panic!("{:?}", g.heads);
+        };
+        let mid = ((l.0 + r.0) / 2.0, (l.1 + r.1) / 2.0);
+        let (dx, dy) = (tip.0 - mid.0, tip.1 - mid.1);
+        assert!((dx / dy + 0.5).abs() < 0.05, "tangent ({dx}, {dy})");
+
+        // 控制点多于 2 个时只取前两个
+        let cubic = flatten_bezier((0.0, 0.0), &[(0.0, 10.0), (10.0, 10.0)], (10.0, 0.0));
+        let extra = flatten_bezier(
+            (0.0, 0.0),
+            &[(0.0, 10.0), (10.0, 10.0), (99.0, 99.0)],
+            (10.0, 0.0),
+        );
+        assert_eq!(cubic, extra);
+        assert_eq!(cubic[cubic.len() / 2], (5.0, 7.5));
+    }
+
+    #[test]
+    fn measure_labels_and_dimensions() {
+        assert_eq!(measure_label(13.0, 1.0), "13px");
+        assert_eq!(measure_label(13.0, 2.0), "13px / 6.5pt");
+        assert_eq!(measure_label(10.04, 1.5), "10px / 6.7pt");
+        assert_eq!(measure_label(13.0, 0.0), "13px", "invalid scale");
+
+        let mut a = rect_ann(10.0, 10.0, 3.0, 4.0, 0);
+        a.kind = AnnotationKind::Measure {
+            mode: MeasureMode::Line,
+        };
+        let d = a.dimensions();
+        assert_eq!(d.len(), 1);
+        assert_eq!((d[0].from, d[0].to), ((10.0, 10.0), (13.0, 14.0)));
+        assert_eq!(d[0].label(2.0), "5px / 2.5pt");
+        // 包围盒包含端点短线
+        assert!(approx(
+            a.bounds(),
+            a.local_bounds().inflate(Dimension::TICK / 2.0)
+        ));
+
+        // Rect 模式：负宽高规范化，宽沿上边、高沿左边
+        a.meta.w = -20.0;
+        a.kind = AnnotationKind::Measure {
+            mode: MeasureMode::Rect,
+        };
+        let d = a.dimensions();
+        assert_eq!(d[0].from, (-10.0, 10.0));
+        assert_eq!((d[0].to, d[0].label(1.0).as_str()), ((10.0, 10.0), "20px"));
+        assert_eq!((d[1].to, d[1].label(1.0).as_str()), ((-10.0, 14.0), "4px"));
+        a.meta.h = 0.0;
+        assert_eq!(a.dimensions().len(), 1, "zero height is omitted");
+    }
+
+    #[test]
+    fn callout_bounds_include_anchor() {
+        let mut a = rect_ann(10.0, 10.0, 40.0, 20.0, 0);
+        a.kind = AnnotationKind::Callout {
+            content: "hi".into(),
+            font_size: 12,
+            anchor: (0.0, 60.0),
+            corner_radius: 4,
+        };
+        assert!(approx(
+            a.local_bounds(),
+            Rect {
+                x: 0.0,
+                y: 10.0,
+                w: 50.0,
+                h: 50.0
+            }
+        ));
+        assert!(a.callout_shape().is_some());
+    }
+}
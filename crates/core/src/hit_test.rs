@@ -0,0 +1,772 @@
+//! 命中测试：判断点是否落在标注上（考虑旋转、描边宽度与容差）
+use crate::geometry::{
+    arrow_head, point_in_polygon, point_in_triangle, rotate_point, ArrowHead, Dimension,
+};
+use crate::model::{Annotation, AnnotationKind, LineCap, MagnifierShape, SpotlightShape};
+use crate::snap::Rect;
+
+/// 点到线段的距离
+pub fn distance_to_segment(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (a.0 + dx * t, a.1 + dy * t);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+/// 点到折线的最小距离；少于 2 个点时退化为到单点距离
+pub fn distance_to_polyline(p: (f32, f32), points: &[(f32, f32)]) -> f32 {
+    match points {
+        [] => f32::INFINITY,
+        [only] => distance_to_segment(p, *only, *only),
+        _ => points
+            .windows(2)
+            .map(|w| distance_to_segment(p, w[0], w[1]))
+            .fold(f32::INFINITY, f32::min),
+    }
+}
+
+impl Annotation {
+    /// 点 (x, y) 是否命中该标注
+    ///
+    /// - 有填充的 Rect、Highlight、Mosaic、Blur、Redact、Image、Text：区域内命中
+    /// - 仅描边的 Rect：只有边框附近命中（描边半宽 + 容差）
+    /// - Rect / Highlight / Mosaic 有圆角时，圆角外不命中（`corner_radius`）
+    /// - Arrow：到线条（直线或展平的曲线）距离 ≤ 描边半宽 + 容差，或落在箭头头部
+    /// - Freehand / Highlighter：到原始折线距离 ≤ 描边半宽 + 容差（荧光笔缺省线宽见 `HIGHLIGHTER_WIDTH`）
+    /// - Polygon：有填充时多边形内命中，仅描边时只有闭合边附近命中
+    /// - Polyline：到折线距离 ≤ 描边半宽 + 容差，或落在两端箭头内
+    /// - Ellipse：有填充时椭圆内命中，仅描边时只有边框环带命中
+    /// - Line：按线端样式（平头 / 圆头 / 方头）判断到线段的距离
+    /// - StepMarker：包围盒内居中的圆内命中
+    /// - Callout：圆角框体（含文字区域）或尾巴三角形内命中，整体视为一个对象
+    /// - Spotlight：亮区（矩形或椭圆）内命中，被压暗的外部不算
+    /// - Magnifier：插图（圆或矩形）内命中，source 区域不算
+    /// - Measure：到任一尺寸线距离 ≤ 端点短线半长 + 容差
+    pub fn hit_test(&self, x: f32, y: f32, tolerance: f32) -> bool {
+        let m = &self.meta;
+        let tol = tolerance.max(0.0);
+        let half = self.stroke_half_width();
+        let p = self.to_local(x, y);
+        match &self.kind {
+            AnnotationKind::Rect { .. }
+            | AnnotationKind::Highlight { .. }
+            | AnnotationKind::Mosaic { .. } => {
+                let local = self.local_bounds();
+                let radius = self.corner_radius();
+                // 外扩 / 内缩 d 后的圆角半径；没有圆角时保持直角
+                let grown = |d: f32| {
+                    if radius > 0.0 {
+                        (radius + d).max(0.0)
+                    } else {
+                        0.0
+                    }
+                };
+                let outlined = matches!(self.kind, AnnotationKind::Rect { .. })
+                    && m.fill_color.is_none()
+                    && m.stroke_color.is_some()
+                    && half > 0.0;
+                if !outlined {
+                    let area = local.inflate(half + tol);
+                    return area.rounded_contains(p.0, p.1, grown(half + tol));
+                }
+                // 描边沿外框向内绘制，命中带为 [外框 + 容差, 外框 - 描边宽 - 容差]
+                let width = half * 2.0;
+                let inner = local.inflate(-(width + tol));
+                local.inflate(tol).rounded_contains(p.0, p.1, grown(tol))
+                    && !(inner.w > 0.0
+                        && inner.h > 0.0
+                        && inner.rounded_contains(p.0, p.1, grown(-(width + tol))))
+            }
+            AnnotationKind::Blur { .. }
+            | AnnotationKind::Redact {}
+            | AnnotationKind::Image { .. }
+            | AnnotationKind::Text { .. } => {
+                self.local_bounds().inflate(half + tol).contains(p.0, p.1)
+            }
+            AnnotationKind::Arrow { head_size, .. } => {
+                let Some(geo) = self.arrow_geometry() else {
+                    return false;
+                };
+                if distance_to_polyline(p, &geo.path) <= half.max(0.5) + tol {
+                    return true;
+                }
+                let hs = *head_size as f32;
+                geo.heads.iter().any(|head| match *head {
+                    // 三角 / V 形头部近似为底边中点到尖端的线段加粗，小箭头也容易点中
+                    ArrowHead::Triangle([tip, a, b]) | ArrowHead::OpenV([tip, a, b]) => {
+                        let base = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+                        distance_to_segment(p, base, tip) <= hs / 2.0 + tol
+                    }
+                    ArrowHead::Circle { center, radius } => {
+                        ((p.0 - center.0).powi(2) + (p.1 - center.1).powi(2)).sqrt() <= radius + tol
+                    }
+                    ArrowHead::Bar(a, b) => distance_to_segment(p, a, b) <= half.max(0.5) + tol,
+                })
+            }
+            AnnotationKind::Freehand { points, .. }
+            | AnnotationKind::Highlighter { points, .. } => {
+                distance_to_polyline(p, points) <= half.max(0.5) + tol
+            }
+            AnnotationKind::Polygon { points, .. } => {
+                let outlined = m.fill_color.is_none() && m.stroke_color.is_some() && half > 0.0;
+                if !outlined && point_in_polygon(p, points) {
+                    return true;
+                }
+                let ring: Vec<(f32, f32)> = points.iter().chain(points.first()).copied().collect();
+                distance_to_polyline(p, &ring) <= half.max(0.5) + tol
+            }
+            AnnotationKind::Polyline {
+                points,
+                start_arrow,
+                end_arrow,
+                head_size,
+                ..
+            } => {
+                if distance_to_polyline(p, points) <= half.max(0.5) + tol {
+                    return true;
+                }
+                let n = points.len();
+                if n < 2 {
+                    return false;
+                }
+                let heads = [
+                    (*start_arrow, points[1], points[0]),
+                    (*end_arrow, points[n - 2], points[n - 1]),
+                ];
+                heads.iter().any(|&(on, from, tip)| {
+                    on && arrow_head(from, tip, *head_size as f32).is_some_and(|t| {
+                        point_in_triangle(p, t)
+                            || distance_to_polyline(p, &[t[0], t[1], t[2], t[0]]) <= tol
+                    })
+                })
+            }
+            AnnotationKind::StepMarker { .. } => {
+                let local = self.local_bounds();
+                let d = local.w.min(local.h);
+                let (cx, cy) = local.center();
+                let r = d / 2.0 + tol;
+                (p.0 - cx).powi(2) + (p.1 - cy).powi(2) <= r * r
+            }
+            AnnotationKind::Spotlight { shape, .. } => {
+                let local = self.local_bounds().inflate(tol);
+                match shape {
+                    SpotlightShape::Rect => local.contains(p.0, p.1),
+                    SpotlightShape::Ellipse => local.ellipse_contains(p.0, p.1),
+                }
+            }
+            AnnotationKind::Magnifier { shape, .. } => {
+                // 边框沿插图边界向内绘制，不额外外扩
+                let local = self.local_bounds().inflate(tol);
+                match shape {
+                    MagnifierShape::Rect => local.contains(p.0, p.1),
+                    MagnifierShape::Circle => local.ellipse_contains(p.0, p.1),
+                }
+            }
+            AnnotationKind::Measure { .. } => {
+                let band = half.max(Dimension::TICK / 2.0) + tol;
+                self.dimensions()
+                    .iter()
+                    .any(|d| distance_to_segment(p, d.from, d.to) <= band)
+            }
+            AnnotationKind::Callout { .. } => {
+                let Some(s) = self.callout_shape() else {
+                    return false;
+                };
+                s.body
+                    .inflate(tol)
+                    .rounded_contains(p.0, p.1, s.radius + tol)
+                    || s.tail.is_some_and(|t| {
+                        point_in_triangle(p, t)
+                            || distance_to_polyline(p, &[t[0], t[1], t[2], t[0]]) <= tol
+                    })
+            }
+            AnnotationKind::Ellipse {} => {
+                let local = self.local_bounds();
+                let outlined = m.fill_color.is_none() && m.stroke_color.is_some() && half > 0.0;
+                if !local.inflate(tol).ellipse_contains(p.0, p.1) {
+                    return false;
+                }
+                // 与 Rect 一致：描边向内绘制，空心部分不命中
+                !outlined
+                    || !local
+                        .inflate(-(half * 2.0 + tol))
+                        .ellipse_contains(p.0, p.1)
+            }
+            AnnotationKind::Line { cap, .. } => {
+                let (a, b) = ((m.x, m.y), (m.x + m.w, m.y + m.h));
+                let half = half.max(0.5);
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let len = (dx * dx + dy * dy).sqrt();
+                if len <= 0.0 || *cap == LineCap::Round {
+                    return distance_to_segment(p, a, b) <= half + tol;
+                }
+                // 沿线方向的投影 t 与垂直距离 d；方头两端各外延半个线宽
+                let (ux, uy) = (dx / len, dy / len);
+                let (px, py) = (p.0 - a.0, p.1 - a.1);
+                let t = px * ux + py * uy;
+                let d = (px * uy - py * ux).abs();
+                let ext = if *cap == LineCap::Square { half } else { 0.0 };
+                d <= half + tol && t >= -ext - tol && t <= len + ext + tol
+            }
+        }
+    }
+
+    /// 空间索引使用的包围盒：`bounds()` 再外扩命中测试超出轮廓的部分
+    ///
+    /// 箭头头部按 head_size / 2（圆形头部按半径），保证粗筛不会漏掉精确命中的点；
+    /// 折线两端箭头的外扩已计入 `bounds()`
+    pub fn hit_bounds(&self) -> Rect {
+        let extent = match &self.kind {
+            AnnotationKind::Arrow { head_size, .. } => self.arrow_geometry().map_or(0.0, |geo| {
+                geo.heads
+                    .iter()
+                    .map(|head| match *head {
+                        ArrowHead::Circle { radius, .. } => radius,
+                        _ => *head_size as f32 / 2.0,
+                    })
+                    .fold(0.0, f32::max)
+            }),
+            _ => 0.0,
+        };
+        self.bounds().inflate(extent)
+    }
+
+    /// 旋转后的轮廓是否与矩形相交（框选用；先用包围盒粗筛）
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        if !self.bounds().intersects(rect) {
+            return false;
+        }
+        // 矩形完全覆盖包围盒，或任一矩形角点命中形状，或任一旋转角点落在矩形内
+        let b = self.bounds();
+        if rect.contains(b.x, b.y) && rect.contains(b.x + b.w, b.y + b.h) {
+            return true;
+        }
+        if rect.corners().iter().any(|c| self.contains_point(c.0, c.1)) {
+            return true;
+        }
+        if self
+            .rotated_corners()
+            .iter()
+            .any(|c| rect.contains(c.0, c.1))
+        {
+            return true;
+        }
+        // 旋转矩形与轴对齐矩形边相交的情况
+        let corners = self.rotated_corners();
+        let rc = rect.corners();
+        if (0..4).any(|i| {
+            let (a1, a2) = (corners[i], corners[(i + 1) % 4]);
+            (0..4).any(|j| segments_intersect(a1, a2, rc[j], rc[(j + 1) % 4]))
+        }) {
+            return true;
+        }
+        // 伸出局部包围盒的箭头头部
+        let c = self.rotation_center();
+        self.head_outlines().into_iter().any(|outline| {
+            let pts: Vec<(f32, f32)> = outline
+                .into_iter()
+                .map(|p| rotate_point(p, c, self.meta.rotation))
+                .collect();
+            pts.iter().any(|p| rect.contains(p.0, p.1))
+                || (pts.len() >= 3 && rc.iter().any(|&q| point_in_polygon(q, &pts)))
+                || pts.iter().zip(pts.iter().cycle().skip(1)).any(|(&a, &b)| {
+                    (0..4).any(|j| segments_intersect(a, b, rc[j], rc[(j + 1) % 4]))
+                })
+        })
+    }
+
+    /// 箭头头部轮廓（局部坐标，首尾隐式闭合）：Arrow 的各端头部、Polyline 两端的三角形
+    fn head_outlines(&self) -> Vec<Vec<(f32, f32)>> {
+        match &self.kind {
+            AnnotationKind::Arrow { .. } => self.arrow_geometry().map_or_else(Vec::new, |geo| {
+                geo.heads.iter().map(ArrowHead::outline).collect()
+            }),
+            AnnotationKind::Polyline {
+                points,
+                start_arrow,
+                end_arrow,
+                head_size,
+                ..
+            } if points.len() >= 2 => {
+                let n = points.len();
+                [
+                    (*start_arrow, points[1], points[0]),
+                    (*end_arrow, points[n - 2], points[n - 1]),
+                ]
+                .into_iter()
+                .filter(|(on, ..)| *on)
+                .filter_map(|(_, from, tip)| arrow_head(from, tip, *head_size as f32))
+                .map(|tri| tri.to_vec())
+                .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn segments_intersect(p1: (f32, f32), p2: (f32, f32), q1: (f32, f32), q2: (f32, f32)) -> bool {
+    fn cross(o: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    }
+    let d1 = cross(q1, q2, p1);
+    let d2 = cross(q1, q2, p2);
+    let d3 = cross(p1, p2, q1);
+    let d4 = cross(p1, p2, q2);
+    ((d1 > 0.0) != (d2 > 0.0)) && ((d3 > 0.0) != (d4 > 0.0))
+}
+
+/// 返回渲染顺序中最上层的命中标注（z 最大；z 相同时列表靠后者在上，与渲染的稳定排序一致）
+pub fn hit_test_topmost(
+    annotations: &[Annotation],
+    x: f32,
+    y: f32,
+    tolerance: f32,
+) -> Option<&Annotation> {
+    annotations
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| a.hit_test(x, y, tolerance))
+        .max_by_key(|(i, a)| (a.meta.z, *i))
+        .map(|(_, a)| a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::model::{
+        AnnotationMeta, ArrowHeadShape, ArrowHeads, LineCap, LineJoin, LineStyle, MeasureMode,
+    };
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn ann(x: f32, y: f32, w: f32, h: f32, kind: AnnotationKind) -> Annotation {
+        Annotation {
+            meta: AnnotationMeta {
+                id: Uuid::now_v7(),
+                x,
+                y,
+                w,
+                h,
+                rotation: 0,
+                opacity: 1.0,
+                stroke_color: None,
+                fill_color: None,
+                stroke_width: None,
+                z: 0,
+                locked: false,
+                created_at: Utc::now(),
+            },
+            kind,
+        }
+    }
+
+    #[test]
+    fn outlined_rect_hits_only_near_border() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            100.0,
+            50.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        a.meta.stroke_color = Some(Color::rgb(0xFF, 0x00, 0x00));
+        a.meta.stroke_width = Some(4.0);
+        assert!(a.hit_test(1.0, 25.0, 0.0));
+        assert!(a.hit_test(-2.0, 25.0, 3.0));
+        assert!(!a.hit_test(50.0, 25.0, 3.0), "hollow center");
+
+        a.meta.fill_color = Some(Color::rgb(0x00, 0xFF, 0x00));
+        assert!(a.hit_test(50.0, 25.0, 0.0), "filled center");
+    }
+
+    #[test]
+    fn rounded_rect_skips_corners() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            100.0,
+            50.0,
+            AnnotationKind::Rect { corner_radius: 200 },
+        );
+        // 半径限制在短边一半
+        assert_eq!(a.corner_radius(), 25.0);
+        a.meta.fill_color = Some(Color::rgb(0x00, 0xFF, 0x00));
+        assert!(a.hit_test(50.0, 25.0, 0.0));
+        assert!(!a.hit_test(2.0, 2.0, 0.0), "outside the rounded corner");
+        assert!(a.hit_test(2.0, 2.0, 10.0), "tolerance grows the corner");
+
+        a.meta.fill_color = None;
+        a.meta.stroke_color = Some(Color::rgb(0xFF, 0x00, 0x00));
+        a.meta.stroke_width = Some(4.0);
+        assert!(a.hit_test(8.0, 8.0, 0.0), "on the curved border");
+        assert!(!a.hit_test(12.0, 12.0, 0.0), "inside the curved border");
+        assert!(!a.hit_test(50.0, 25.0, 0.0));
+
+        let mosaic = ann(
+            0.0,
+            0.0,
+            40.0,
+            40.0,
+            AnnotationKind::Mosaic {
+                level: 1,
+                corner_radius: 10,
+            },
+        );
+        assert!(!mosaic.hit_test(1.0, 1.0, 0.0));
+        assert!(mosaic.hit_test(1.0, 20.0, 0.0));
+    }
+
+    #[test]
+    fn arrow_uses_line_distance() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            100.0,
+            100.0,
+            AnnotationKind::Arrow {
+                head_size: 10,
+                line_style: LineStyle::Solid,
+                controls: vec![],
+                heads: ArrowHeads::End,
+                head_shape: ArrowHeadShape::Triangle,
+            },
+        );
+        a.meta.stroke_width = Some(2.0);
+        assert!(a.hit_test(50.0, 51.0, 1.0));
+        assert!(
+            !a.hit_test(80.0, 20.0, 2.0),
+            "inside bbox but far from line"
+        );
+        assert!(a.hit_test(97.0, 100.0, 0.0), "arrow head");
+    }
+
+    #[test]
+    fn curved_arrow_hits_curve_and_both_heads() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            100.0,
+            0.0,
+            AnnotationKind::Arrow {
+                head_size: 10,
+                line_style: LineStyle::Dashed,
+                controls: vec![(50.0, 100.0)],
+                heads: ArrowHeads::Both,
+                head_shape: ArrowHeadShape::Circle,
+            },
+        );
+        a.meta.stroke_width = Some(2.0);
+        // 二次曲线在 t = 0.5 处经过 (50, 50)，弦上不命中
+        assert!(a.hit_test(50.0, 50.0, 0.0));
+        assert!(!a.hit_test(50.0, 0.0, 2.0), "chord is not the curve");
+        // 两端圆形头部
+        assert!(a.hit_test(-4.0, 0.0, 0.0));
+        assert!(a.hit_test(104.0, 0.0, 0.0));
+        assert!(!a.hit_test(-7.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn measure_hits_dimension_lines() {
+        let a = ann(
+            0.0,
+            0.0,
+            40.0,
+            20.0,
+            AnnotationKind::Measure {
+                mode: MeasureMode::Rect,
+            },
+        );
+        assert!(a.hit_test(20.0, 3.0, 0.0), "width line");
+        assert!(a.hit_test(-3.0, 10.0, 0.0), "height line");
+        assert!(!a.hit_test(20.0, 10.0, 1.0), "inside the measured rect");
+        assert!(!a.hit_test(40.0, 20.0, 1.0), "no lines on the far edges");
+    }
+
+    #[test]
+    fn highlighter_uses_default_width() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Highlighter {
+                points: vec![(0.0, 10.0), (40.0, 10.0)],
+                smoothing: 0.0,
+            },
+        );
+        assert!(a.hit_test(20.0, 17.0, 0.0));
+        assert!(!a.hit_test(20.0, 19.0, 0.0));
+        assert_eq!(a.bounds().y, 2.0);
+        a.meta.stroke_width = Some(4.0);
+        assert!(!a.hit_test(20.0, 17.0, 0.0));
+    }
+
+    #[test]
+    fn freehand_uses_polyline_proximity() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Freehand {
+                points: vec![(0.0, 0.0), (50.0, 0.0), (50.0, 50.0)],
+                smoothing: 0.0,
+                cap: LineCap::Round,
+                join: LineJoin::Round,
+            },
+        );
+        a.meta.stroke_width = Some(4.0);
+        assert!(a.hit_test(25.0, 1.5, 0.0));
+        assert!(a.hit_test(52.0, 30.0, 1.0));
+        assert!(
+            !a.hit_test(20.0, 30.0, 2.0),
+            "inside bounds, off the stroke"
+        );
+    }
+
+    #[test]
+    fn ellipse_outline_and_fill() {
+        let mut a = ann(0.0, 0.0, 40.0, 20.0, AnnotationKind::Ellipse {});
+        a.meta.stroke_color = Some(Color::rgb(0xFF, 0x00, 0x00));
+        a.meta.stroke_width = Some(2.0);
+        assert!(a.hit_test(1.0, 10.0, 0.0));
+        assert!(!a.hit_test(20.0, 10.0, 1.0), "hollow center");
+        assert!(
+            !a.hit_test(2.0, 2.0, 1.0),
+            "bbox corner is outside the ellipse"
+        );
+        a.meta.fill_color = Some(Color::rgb(0x00, 0xFF, 0x00));
+        assert!(a.hit_test(20.0, 10.0, 0.0));
+    }
+
+    #[test]
+    fn line_caps_extend_hit_area() {
+        let line = |cap| {
+            let mut a = ann(
+                0.0,
+                0.0,
+                20.0,
+                0.0,
+                AnnotationKind::Line {
+                    line_style: LineStyle::Solid,
+                    cap,
+                },
+            );
+            a.meta.stroke_width = Some(4.0);
+            a
+        };
+        let butt = line(LineCap::Butt);
+        assert!(butt.hit_test(10.0, 1.5, 0.0));
+        assert!(!butt.hit_test(-1.5, 0.0, 0.0));
+        assert!(line(LineCap::Square).hit_test(-1.5, 1.5, 0.0));
+        let round = line(LineCap::Round);
+        assert!(round.hit_test(-1.5, 0.0, 0.0));
+        assert!(!round.hit_test(-1.8, 1.8, 0.0), "outside the round cap");
+    }
+
+    #[test]
+    fn step_marker_hits_circle() {
+        let a = ann(
+            0.0,
+            0.0,
+            40.0,
+            20.0,
+            AnnotationKind::StepMarker { number: 1 },
+        );
+        assert!(a.hit_test(20.0, 10.0, 0.0));
+        assert!(a.hit_test(11.0, 10.0, 0.0));
+        assert!(
+            !a.hit_test(5.0, 10.0, 1.0),
+            "inside bounds, outside the circle"
+        );
+        assert!(a.hit_test(5.0, 10.0, 6.0));
+    }
+
+    #[test]
+    fn callout_body_and_tail_hit_as_one() {
+        let a = ann(
+            0.0,
+            0.0,
+            100.0,
+            40.0,
+            AnnotationKind::Callout {
+                content: "look here".into(),
+                font_size: 12,
+                anchor: (30.0, 80.0),
+                corner_radius: 8,
+            },
+        );
+        assert!(a.hit_test(50.0, 20.0, 0.0), "text area");
+        assert!(a.hit_test(30.0, 78.0, 0.0), "near the tip");
+        assert!(a.hit_test(30.0, 81.5, 2.0), "tip with tolerance");
+        assert!(!a.hit_test(80.0, 70.0, 2.0), "inside bounds, off the tail");
+        assert!(!a.hit_test(0.5, 0.5, 0.0), "rounded corner");
+        assert!(a.intersects_rect(&Rect {
+            x: 25.0,
+            y: 70.0,
+            w: 10.0,
+            h: 5.0
+        }));
+    }
+
+    #[test]
+    fn spotlight_hits_bright_region_only() {
+        let kind = |shape| AnnotationKind::Spotlight { shape, feather: 8 };
+        let rect = ann(10.0, 10.0, 40.0, 20.0, kind(SpotlightShape::Rect));
+        assert!(rect.hit_test(11.0, 11.0, 0.0));
+        assert!(!rect.hit_test(60.0, 20.0, 2.0), "dimmed area");
+        let ellipse = ann(10.0, 10.0, 40.0, 20.0, kind(SpotlightShape::Ellipse));
+        assert!(ellipse.hit_test(30.0, 20.0, 0.0));
+        assert!(!ellipse.hit_test(11.0, 11.0, 0.0));
+    }
+
+    #[test]
+    fn polygon_and_polyline_hit_test() {
+        // L 形凹多边形：凹口内不命中
+        let points = vec![
+            (0.0, 0.0),
+            (20.0, 0.0),
+            (20.0, 10.0),
+            (10.0, 10.0),
+            (10.0, 20.0),
+            (0.0, 20.0),
+        ];
+        let mut poly = ann(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Polygon {
+                points,
+                join: LineJoin::Round,
+            },
+        );
+        poly.meta.fill_color = Some(crate::Color::rgb(0, 0, 0));
+        assert!(poly.hit_test(5.0, 15.0, 0.0));
+        assert!(!poly.hit_test(15.0, 15.0, 0.0), "notch");
+        // 仅描边：闭合边（含最后一个顶点回到起点的边）附近命中，内部不命中
+        poly.meta.fill_color = None;
+        poly.meta.stroke_color = Some(crate::Color::rgb(0, 0, 0));
+        poly.meta.stroke_width = Some(2.0);
+        assert!(!poly.hit_test(5.0, 15.0, 0.0));
+        assert!(poly.hit_test(0.5, 10.0, 0.0), "closing edge");
+
+        let line = |start_arrow, end_arrow| {
+            ann(
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                AnnotationKind::Polyline {
+                    points: vec![(0.0, 0.0), (40.0, 0.0), (40.0, 40.0)],
+                    line_style: crate::model::LineStyle::Solid,
+                    start_arrow,
+                    end_arrow,
+                    head_size: 10,
+                    cap: LineCap::Round,
+                    join: LineJoin::Round,
+                },
+            )
+        };
+        let plain = line(false, false);
+        assert!(plain.hit_test(20.0, 0.4, 0.0));
+        assert!(plain.hit_test(40.0, 20.0, 0.0));
+        assert!(
+            !plain.hit_test(20.0, 20.0, 2.0),
+            "open polyline has no fill"
+        );
+        assert!(!plain.hit_test(41.5, 36.0, 0.0));
+        assert!(
+            line(false, true).hit_test(41.5, 36.0, 0.0),
+            "end arrow head"
+        );
+        assert!(!line(false, true).hit_test(6.0, 2.5, 0.0));
+        assert!(
+            line(true, false).hit_test(6.0, 2.5, 0.0),
+            "start arrow head"
+        );
+    }
+
+    #[test]
+    fn magnifier_hits_inset_only() {
+        let kind = |shape| AnnotationKind::Magnifier {
+            source: (100.0, 100.0, 10.0, 10.0),
+            zoom: 4.0,
+            shape,
+            filter: crate::model::SampleFilter::Nearest,
+        };
+        let circle = ann(10.0, 10.0, 40.0, 40.0, kind(MagnifierShape::Circle));
+        assert!(circle.hit_test(30.0, 30.0, 0.0));
+        assert!(!circle.hit_test(11.0, 11.0, 0.0), "outside the circle");
+        assert!(!circle.hit_test(105.0, 105.0, 2.0), "source region");
+        let rect = ann(10.0, 10.0, 40.0, 40.0, kind(MagnifierShape::Rect));
+        assert!(rect.hit_test(11.0, 11.0, 0.0));
+    }
+
+    #[test]
+    fn rotated_hit_test() {
+        let mut a = ann(
+            0.0,
+            45.0,
+            100.0,
+            10.0,
+            AnnotationKind::Highlight {
+                mode: crate::model::BlendMode::Multiply,
+                corner_radius: 0,
+            },
+        );
+        a.meta.rotation = 90;
+        assert!(a.hit_test(50.0, 5.0, 0.0));
+        assert!(!a.hit_test(5.0, 50.0, 0.0));
+    }
+
+    #[test]
+    fn topmost_respects_z_and_order() {
+        let filled = |z: i32| {
+            let mut a = ann(
+                0.0,
+                0.0,
+                10.0,
+                10.0,
+                AnnotationKind::Rect { corner_radius: 0 },
+            );
+            a.meta.fill_color = Some(Color::rgb(0x00, 0x00, 0x00));
+            a.meta.z = z;
+            a
+        };
+        let list = vec![filled(2), filled(1), filled(2)];
+        let top = hit_test_topmost(&list, 5.0, 5.0, 0.0).unwrap();
+        assert_eq!(top.meta.id, list[2].meta.id);
+        assert!(hit_test_topmost(&list, 50.0, 50.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn marquee_intersection() {
+        let mut a = ann(
+            0.0,
+            0.0,
+            40.0,
+            40.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        a.meta.rotation = 45;
+        let r = |x, y, w, h| Rect { x, y, w, h };
+        assert!(a.intersects_rect(&r(15.0, 15.0, 10.0, 10.0)), "inside");
+        assert!(
+            a.intersects_rect(&r(-100.0, -100.0, 300.0, 300.0)),
+            "covers"
+        );
+        // 包围盒角落（旋转后空出的区域）不算相交
+        assert!(!a.intersects_rect(&r(-8.0, -8.0, 4.0, 4.0)));
+        assert!(
+            a.intersects_rect(&r(18.0, -12.0, 4.0, 10.0)),
+            "crosses edge"
+        );
+    }
+}
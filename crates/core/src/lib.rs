@@ -1,11 +1,25 @@
+pub mod asset;
+pub mod color;
+pub mod document;
 pub mod error;
+pub mod geometry;
+pub mod hit_test;
 pub mod model;
 pub mod naming;
+pub mod project;
 pub mod snap;
+pub mod spatial;
 pub mod undo;
 
+pub use asset::*;
+pub use color::*;
+pub use document::*;
 pub use error::*;
+pub use geometry::*;
+pub use hit_test::*;
 pub use model::*;
 pub use naming::*;
+pub use project::*;
 pub use snap::*;
+pub use spatial::*;
 pub use undo::*;
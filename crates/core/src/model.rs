@@ -1,3 +1,5 @@
+use crate::asset::ImageSource;
+use crate::color::Color;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -11,7 +13,7 @@ pub struct Frame {
     pub bytes: Arc<[u8]>, // BGRA or RGBA depending on platform normalization
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PixelFormat {
     Bgra8,
     Rgba8,
@@ -42,8 +44,9 @@ pub struct AnnotationMeta {
     pub h: f32,
     pub rotation: u16,
     pub opacity: f32,
-    pub stroke_color: Option<String>,
-    pub fill_color: Option<String>,
+    /// 描边色；旧版 JSON 中的十六进制字符串可直接反序列化
+    pub stroke_color: Option<Color>,
+    pub fill_color: Option<Color>,
     pub stroke_width: Option<f32>,
     pub z: i32,
     pub locked: bool,
@@ -55,28 +58,130 @@ pub struct AnnotationMeta {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AnnotationKind {
-    Rect {
-        corner_radius: u8,
-    },
+    /// 矩形：corner_radius 为圆角半径，超过短边一半时按短边一半处理（见 `Annotation::corner_radius`）
+    Rect { corner_radius: u8 },
+    /// 箭头：起点 (x, y)，终点 (x + w, y + h)；controls 为贝塞尔控制点（画布坐标，与 Callout 的
+    /// anchor 相同不随 x/y 平移）：空为直线，1 个为二次曲线，2 个为三次曲线，多余的忽略
     Arrow {
         head_size: u8,
         line_style: LineStyle,
+        #[serde(default)]
+        controls: Vec<(f32, f32)>,
+        #[serde(default)]
+        heads: ArrowHeads,
+        #[serde(default)]
+        head_shape: ArrowHeadShape,
     },
     Text {
         content: String,
         font_family: String,
         font_size: u32,
     },
+    /// 高亮区域；corner_radius 与 Rect 相同
     Highlight {
         mode: BlendMode,
+        #[serde(default)]
+        corner_radius: u8,
     },
+    /// 马赛克区域；corner_radius 与 Rect 相同，圆角外保留原图
     Mosaic {
         level: u8,
+        #[serde(default)]
+        corner_radius: u8,
     },
+    /// 高斯模糊打码：radius 为模糊半径（像素），σ = radius / 2；与 Mosaic 一样从原始底图采样
+    Blur { radius: u8 },
+    /// 涂黑打码：以 fill_color（缺省黑色）不透明覆盖区域，忽略 opacity 与颜色 alpha；
+    /// 渲染时先于所有采样底图的标注（Mosaic / Blur 等）作用到底图，保证原像素不出现在任何导出结果中
+    Redact {},
+    /// 聚光灯：(x, y, w, h) 为亮区，亮区外按 fill_color（缺省黑色）与 opacity 整体压暗；
+    /// feather 为亮区边缘向外的羽化宽度（像素）。同一图上多个聚光灯的亮区取并集，只压暗一次
+    Spotlight {
+        #[serde(default)]
+        shape: SpotlightShape,
+        #[serde(default)]
+        feather: u8,
+    },
+    /// 放大镜：(x, y, w, h) 为放大后的插图，source 为被放大的区域 (x, y, w, h)（画布坐标，与
+    /// Callout 的 anchor 相同不随 x/y 平移）。插图中心对齐 source 中心、按 zoom 倍放大，
+    /// 像素从原始底图采样；边框取 stroke_color / stroke_width（缺省白色 2px）
+    Magnifier {
+        source: (f32, f32, f32, f32),
+        zoom: f32,
+        #[serde(default)]
+        shape: MagnifierShape,
+        #[serde(default)]
+        filter: SampleFilter,
+    },
+    /// 贴图（logo、状态印章、其他截图等）：图片缩放到 (x, y, w, h)，按图片 alpha 与 opacity 合成
+    Image { source: ImageSource },
+    /// 手绘：线端与连接缺省为圆形（旧文档没有这两个字段）
     Freehand {
         points: Vec<(f32, f32)>,
         smoothing: f32,
+        #[serde(default = "round_cap")]
+        cap: LineCap,
+        #[serde(default)]
+        join: LineJoin,
+    },
+    /// 荧光笔：与 Freehand 相同的点集与平滑，整笔先栅格化为覆盖率再按 Multiply 混合一次，
+    /// 笔画自身重叠处不会加深；颜色取 stroke_color（缺省黄色），线宽缺省 `HIGHLIGHTER_WIDTH`
+    Highlighter {
+        points: Vec<(f32, f32)>,
+        smoothing: f32,
+    },
+    /// 多边形：points 为精确顶点（画布坐标，与 Freehand 相同不随 x/y 平移），首尾自动闭合；
+    /// fill_color 按非零环绕规则填充，描边以边为中心，顶点处按 join 连接
+    Polygon {
+        points: Vec<(f32, f32)>,
+        #[serde(default)]
+        join: LineJoin,
+    },
+    /// 折线：points 为精确顶点（不做平滑），两端可选箭头，箭头大小为 head_size；
+    /// 线端（虚线时为每一段的两端）与连接缺省为圆形
+    Polyline {
+        points: Vec<(f32, f32)>,
+        line_style: LineStyle,
+        #[serde(default)]
+        start_arrow: bool,
+        #[serde(default)]
+        end_arrow: bool,
+        head_size: u8,
+        #[serde(default = "round_cap")]
+        cap: LineCap,
+        #[serde(default)]
+        join: LineJoin,
+    },
+    /// 内切于 (x, y, w, h) 的椭圆；描边沿边界向内绘制
+    Ellipse {},
+    /// 无箭头直线，端点与 Arrow 相同：(x, y) -> (x + w, y + h)
+    Line {
+        line_style: LineStyle,
+        #[serde(default)]
+        cap: LineCap,
+    },
+    /// 编号步骤标记：内切圆 + 居中编号；number 为 0 表示由 AnnotationService 自动编号
+    StepMarker { number: u32 },
+    /// 标注框：(x, y, w, h) 为圆角文本框，content 在框内自动换行；
+    /// 尾巴从框边指向 anchor（画布坐标，与 Freehand 点相同不随 x/y 平移）
+    Callout {
+        content: String,
+        font_size: u32,
+        anchor: (f32, f32),
+        corner_radius: u8,
     },
+    /// 测量标注：Line 模式为 (x, y) -> (x + w, y + h) 的尺寸线；Rect 模式测量 (x, y, w, h) 的宽和高，
+    /// 尺寸线沿上边与左边。标签同时给出物理像素与逻辑点；逻辑点在渲染时按所属截图的
+    /// `Screenshot.scale` 换算（`Dimension::label`），标注本身不保存缩放
+    Measure {
+        #[serde(default)]
+        mode: MeasureMode,
+    },
+}
+
+impl AnnotationKind {
+    /// 荧光笔未设置 stroke_width 时的线宽
+    pub const HIGHLIGHTER_WIDTH: f32 = 16.0;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +190,101 @@ pub enum LineStyle {
     Dashed,
 }
 
+/// 箭头头部所在的端点
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArrowHeads {
+    #[default]
+    End,
+    Start,
+    Both,
+}
+
+/// 箭头头部形状（大小均为 head_size）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArrowHeadShape {
+    /// 实心三角形，线条止于三角形底边
+    #[default]
+    Triangle,
+    /// 开口 V 形，两翼与线条同宽描边
+    OpenV,
+    /// 以端点为圆心、直径 head_size 的实心圆
+    Circle,
+    /// 过端点且垂直于线条的短横线
+    Bar,
+}
+
+/// 测量方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeasureMode {
+    /// 两点间距离
+    #[default]
+    Line,
+    /// 矩形的宽和高
+    Rect,
+}
+
+/// 线端样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineCap {
+    /// 平头，止于端点
+    #[default]
+    Butt,
+    /// 圆头，端点外延半个线宽
+    Round,
+    /// 方头，端点外延半个线宽
+    Square,
+}
+
+/// Freehand / Polyline 旧文档的线端缺省值
+fn round_cap() -> LineCap {
+    LineCap::Round
+}
+
+/// 折线拐点处的连接样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineJoin {
+    /// 以拐点为圆心、半个线宽为半径的圆角
+    #[default]
+    Round,
+    /// 外侧边延长相交成尖角；尖角长度超过 `MITER_LIMIT` 倍半线宽时退化为 Bevel
+    Miter,
+    /// 外侧两角连成直线切角
+    Bevel,
+}
+
+impl LineJoin {
+    /// 尖角长度（拐点到尖端）与半线宽之比的上限，与 SVG 的 stroke-miterlimit 缺省值相同
+    pub const MITER_LIMIT: f32 = 4.0;
+}
+
+/// 聚光灯亮区形状
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpotlightShape {
+    #[default]
+    Rect,
+    /// 内切于 (x, y, w, h) 的椭圆
+    Ellipse,
+}
+
+/// 放大镜插图形状
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MagnifierShape {
+    /// 内切于 (x, y, w, h) 的圆（宽高不等时为椭圆）
+    #[default]
+    Circle,
+    Rect,
+}
+
+/// 底图采样方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SampleFilter {
+    /// 最近邻：放大后保留像素边界，适合查看 1px 细节
+    #[default]
+    Nearest,
+    /// 双线性插值：过渡平滑
+    Bilinear,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BlendMode {
     Multiply,
@@ -116,7 +316,7 @@ pub fn push_history_trim(list: &mut Vec<HistoryItem>, item: HistoryItem, capacit
     list.push(item);
     if list.len() > capacity {
         // 按创建时间倒序保留最近 capacity 个
-        list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        list.sort_by_key(|b| std::cmp::Reverse(b.created_at));
         list.truncate(capacity);
     }
 }
@@ -152,8 +352,83 @@ mod history_tests {
     }
 }
 
+#[cfg(test)]
+mod kind_tests {
+    use super::*;
+    use serde_json::json;
+
+    fn kind(v: serde_json::Value) -> AnnotationKind {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn callout_round_trips_as_one_kind() {
+        let k = kind(json!({
+            "type": "callout", "content": "look here", "font_size": 12,
+            "anchor": [40.0, 90.0], "corner_radius": 6
+        }));
+        match &k {
+            AnnotationKind::Callout {
+                content, anchor, ..
+            } => assert_eq!((content.as_str(), *anchor), ("look here", (40.0, 90.0))),
+            k => panic!("unexpected kind {k:?}"),
+        }
+        let v = serde_json::to_value(&k).unwrap();
+        assert_eq!(v["type"], "callout");
+        assert_eq!(v["anchor"], json!([40.0, 90.0]));
+    }
+
+    #[test]
+    fn magnifier_shape_and_filter_default() {
+        let k = kind(json!({"type": "magnifier", "source": [10.0, 10.0, 8.0, 8.0], "zoom": 3.0}));
+        match k {
+            AnnotationKind::Magnifier {
+                source,
+                zoom,
+                shape,
+                filter,
+            } => {
+                assert_eq!((source, zoom), ((10.0, 10.0, 8.0, 8.0), 3.0));
+                assert_eq!(
+                    (shape, filter),
+                    (MagnifierShape::Circle, SampleFilter::Nearest)
+                );
+            }
+            k => panic!("unexpected kind {k:?}"),
+        }
+    }
+
+    #[test]
+    fn measure_mode_defaults_to_line() {
+        match kind(json!({"type": "measure"})) {
+            AnnotationKind::Measure { mode } => assert_eq!(mode, MeasureMode::Line),
+            k => panic!("unexpected kind {k:?}"),
+        }
+    }
+
+    #[test]
+    fn stroke_caps_and_joins_default_to_round() {
+        let polyline = kind(json!({
+            "type": "polyline", "points": [[0.0, 0.0], [10.0, 0.0]],
+            "line_style": "Solid", "head_size": 8
+        }));
+        match polyline {
+            AnnotationKind::Polyline { cap, join, .. } => {
+                assert_eq!((cap, join), (LineCap::Round, LineJoin::Round));
+            }
+            k => panic!("unexpected kind {k:?}"),
+        }
+        let polygon =
+            kind(json!({"type": "polygon", "points": [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]]}));
+        match polygon {
+            AnnotationKind::Polygon { join, .. } => assert_eq!(join, LineJoin::Round),
+            k => panic!("unexpected kind {k:?}"),
+        }
+    }
+}
+
 // serde helper for DateTime<Utc> as millis
-mod ts_millis {
+pub(crate) mod ts_millis {
     use chrono::{DateTime, TimeZone, Utc};
     use serde::{self, Deserialize, Deserializer, Serializer};
     pub fn serialize<S>(dt: &DateTime<Utc>, s: S) -> Result<S::Ok, S::Error>
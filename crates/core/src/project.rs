@@ -0,0 +1,130 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::model::{ts_millis, PixelFormat};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 当前项目文件格式版本；读取时高于此版本的文件被拒绝
+///
+/// - v1：`annotations.json` / `history.json` 为裸数据
+/// - v2：二者改为带 schema 版本的 `AnnotationDocument` / `HistoryDocument`
+pub const PROJECT_FORMAT_VERSION: u8 = 2;
+
+/// 项目文件中单个帧的描述（像素数据以无损 PNG 存放在 `path` 指向的条目中）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectFrameEntry {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    /// 捕获时的原始像素格式，加载时还原
+    pub pixel_format: PixelFormat,
+}
+
+/// 项目文件中的一张贴图资源（PNG 原样存放在 `path` 指向的条目中）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectAssetEntry {
+    pub id: Uuid,
+    pub path: String,
+}
+
+/// 项目清单（manifest.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectManifest {
+    /// 版本字段（用于序列化向前兼容）
+    pub version: u8,
+    pub screenshot_id: Uuid,
+    pub scale: f32,
+    #[serde(with = "ts_millis")]
+    pub captured_at: DateTime<Utc>,
+    #[serde(with = "ts_millis")]
+    pub saved_at: DateTime<Utc>,
+    pub primary: ProjectFrameEntry,
+    /// 各显示器帧，与 `FrameSet.all` 顺序一致
+    #[serde(default)]
+    pub frames: Vec<ProjectFrameEntry>,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// 缩略图条目路径（PNG）
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// 撤销历史条目路径（`UndoStack` JSON），未保存历史时为空
+    #[serde(default)]
+    pub history: Option<String>,
+    /// Image 标注按 id 引用的贴图资源
+    #[serde(default)]
+    pub assets: Vec<ProjectAssetEntry>,
+}
+
+impl ProjectManifest {
+    /// 校验版本：0 视为损坏，高于 `PROJECT_FORMAT_VERSION` 视为由更新版本写入
+    pub fn check_version(&self) -> Result<()> {
+        match self.version {
+            0 => Err(Error::new(
+                ErrorKind::Validation,
+                "project manifest version missing",
+            )),
+            v if v > PROJECT_FORMAT_VERSION => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "project format v{} is newer than supported v{}",
+                    v, PROJECT_FORMAT_VERSION
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(version: u8) -> ProjectManifest {
+        ProjectManifest {
+            version,
+            screenshot_id: Uuid::now_v7(),
+            scale: 1.0,
+            captured_at: Utc::now(),
+            saved_at: Utc::now(),
+            primary: ProjectFrameEntry {
+                path: "frames/primary.png".into(),
+                width: 4,
+                height: 4,
+                pixel_format: PixelFormat::Rgba8,
+            },
+            frames: vec![],
+            title: None,
+            thumbnail: None,
+            history: None,
+            assets: vec![],
+        }
+    }
+
+    #[test]
+    fn version_check() {
+        assert!(manifest(PROJECT_FORMAT_VERSION).check_version().is_ok());
+        assert!(matches!(
+            manifest(0).check_version().unwrap_err().kind,
+            ErrorKind::Validation
+        ));
+        assert!(matches!(
+            manifest(PROJECT_FORMAT_VERSION + 1)
+                .check_version()
+                .unwrap_err()
+                .kind,
+            ErrorKind::Unsupported
+        ));
+    }
+
+    #[test]
+    fn manifest_ignores_unknown_fields() {
+        let mut v = serde_json::to_value(manifest(1)).unwrap();
+        v["future_field"] = serde_json::json!({"a": 1});
+        v.as_object_mut().unwrap().remove("frames");
+        v.as_object_mut().unwrap().remove("assets");
+        let m: ProjectManifest = serde_json::from_value(v).unwrap();
+        assert!(m.frames.is_empty());
+        assert!(m.assets.is_empty());
+        assert_eq!(m.primary.pixel_format, PixelFormat::Rgba8);
+    }
+}
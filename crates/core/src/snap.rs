@@ -1,7 +1,7 @@
 /// 吸附算法：给定当前矩形 (x,y,w,h) 与一组参考线 (水平/垂直), 若距离阈值内则对齐
 /// 简化：参考线集合以像素坐标表示；阈值默认 6px；返回 (dx, dy, snapped_x, snapped_y)
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -0,0 +1,289 @@
+//! 标注空间索引（四叉树），用于框选与命中测试的候选粗筛
+use crate::model::Annotation;
+use crate::snap::Rect;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const NODE_CAPACITY: usize = 8;
+const MAX_DEPTH: u8 = 10;
+/// 初始根节点边长；超出时按需扩大并重建
+const INITIAL_EXTENT: f32 = 4096.0;
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: Rect,
+    depth: u8,
+    items: Vec<(Uuid, Rect)>,
+    children: Option<Box<[Node; 4]>>,
+}
+
+impl Node {
+    fn new(bounds: Rect, depth: u8) -> Self {
+        Self {
+            bounds,
+            depth,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn covers(&self, r: &Rect) -> bool {
+        let b = &self.bounds;
+        r.x >= b.x && r.y >= b.y && r.x + r.w <= b.x + b.w && r.y + r.h <= b.y + b.h
+    }
+
+    /// 完整容纳 r 的子节点下标（按固定顺序取第一个，插入与删除路径一致）
+    fn child_for(&self, r: &Rect) -> Option<usize> {
+        self.children
+            .as_ref()
+            .and_then(|c| c.iter().position(|n| n.covers(r)))
+    }
+
+    fn insert(&mut self, id: Uuid, r: Rect) {
+        if let Some(i) = self.child_for(&r) {
+            if let Some(children) = self.children.as_mut() {
+                children[i].insert(id, r);
+                return;
+            }
+        }
+        self.items.push((id, r));
+        if self.children.is_none() && self.items.len() > NODE_CAPACITY && self.depth < MAX_DEPTH {
+            self.split();
+        }
+    }
+
+    fn split(&mut self) {
+        let b = self.bounds;
+        let (hw, hh) = (b.w / 2.0, b.h / 2.0);
+        let d = self.depth + 1;
+        let quad = |x, y| Node::new(Rect { x, y, w: hw, h: hh }, d);
+        self.children = Some(Box::new([
+            quad(b.x, b.y),
+            quad(b.x + hw, b.y),
+            quad(b.x, b.y + hh),
+            quad(b.x + hw, b.y + hh),
+        ]));
+        for (id, r) in std::mem::take(&mut self.items) {
+            self.insert(id, r);
+        }
+    }
+
+    fn remove(&mut self, id: Uuid, r: &Rect) -> bool {
+        if let Some(pos) = self.items.iter().position(|(i, _)| *i == id) {
+            self.items.swap_remove(pos);
+            return true;
+        }
+        match (self.child_for(r), self.children.as_mut()) {
+            (Some(i), Some(children)) => children[i].remove(id, r),
+            _ => false,
+        }
+    }
+
+    fn query(&self, r: &Rect, out: &mut Vec<Uuid>) {
+        if !self.bounds.intersects(r) {
+            return;
+        }
+        out.extend(
+            self.items
+                .iter()
+                .filter(|(_, b)| b.intersects(r))
+                .map(|(id, _)| *id),
+        );
+        if let Some(children) = &self.children {
+            for c in children.iter() {
+                c.query(r, out);
+            }
+        }
+    }
+}
+
+/// 以标注的命中包围盒（`Annotation::hit_bounds()`，旋转后的包围盒加命中外扩）建立的四叉树
+///
+/// 不跨越子节点边界的条目下沉到子节点，跨越的留在当前节点；
+/// 插入超出根范围时扩大根节点并重建。
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    root: Node,
+    entries: HashMap<Uuid, Rect>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        let half = INITIAL_EXTENT / 2.0;
+        Self {
+            root: Node::new(
+                Rect {
+                    x: -half,
+                    y: -half,
+                    w: INITIAL_EXTENT,
+                    h: INITIAL_EXTENT,
+                },
+                0,
+            ),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn from_annotations(annotations: &[Annotation]) -> Self {
+        let mut idx = Self::new();
+        for a in annotations {
+            idx.upsert(a);
+        }
+        idx
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 插入或更新标注的命中包围盒
+    pub fn upsert(&mut self, ann: &Annotation) {
+        self.insert_rect(ann.meta.id, ann.hit_bounds());
+    }
+
+    pub fn insert_rect(&mut self, id: Uuid, r: Rect) {
+        if let Some(old) = self.entries.get(&id) {
+            if *old == r {
+                return;
+            }
+            let old = *old;
+            self.root.remove(id, &old);
+        }
+        self.entries.insert(id, r);
+        if !self.root.covers(&r) {
+            self.grow_to(&r);
+            return;
+        }
+        self.root.insert(id, r);
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        match self.entries.remove(&id) {
+            Some(r) => self.root.remove(id, &r),
+            None => false,
+        }
+    }
+
+    pub fn bounds_of(&self, id: Uuid) -> Option<Rect> {
+        self.entries.get(&id).copied()
+    }
+
+    /// 包围盒与 r 相交的标注 id（无序）
+    pub fn query_rect(&self, r: &Rect) -> Vec<Uuid> {
+        let mut out = Vec::new();
+        self.root.query(r, &mut out);
+        out
+    }
+
+    /// 包围盒包含点 (x, y)（外扩 tolerance）的标注 id
+    pub fn query_point(&self, x: f32, y: f32, tolerance: f32) -> Vec<Uuid> {
+        let t = tolerance.max(0.0);
+        self.query_rect(&Rect {
+            x: x - t,
+            y: y - t,
+            w: 2.0 * t,
+            h: 2.0 * t,
+        })
+    }
+
+    /// 扩大根节点直到覆盖 r，然后重建
+    fn grow_to(&mut self, r: &Rect) {
+        let mut b = self.root.bounds;
+        while !(r.x >= b.x && r.y >= b.y && r.x + r.w <= b.x + b.w && r.y + r.h <= b.y + b.h) {
+            let (cx, cy) = b.center();
+            b = Rect {
+                x: cx - b.w,
+                y: cy - b.h,
+                w: b.w * 2.0,
+                h: b.h * 2.0,
+            };
+            if !b.w.is_finite() {
+                break;
+            }
+        }
+        self.root = Node::new(b, 0);
+        for (id, r) in &self.entries {
+            self.root.insert(*id, *r);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(x: f32, y: f32, w: f32, h: f32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    #[test]
+    fn query_matches_brute_force() {
+        let mut idx = SpatialIndex::new();
+        let mut rects = Vec::new();
+        // 伪随机分布（线性同余），覆盖分裂与跨边界情况
+        let mut seed = 7u32;
+        let mut next = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as f32 / 65536.0
+        };
+        for _ in 0..300 {
+            let rect = r(
+                next() * 3000.0 - 500.0,
+                next() * 2000.0 - 500.0,
+                next() * 120.0,
+                next() * 80.0,
+            );
+            let id = Uuid::now_v7();
+            idx.insert_rect(id, rect);
+            rects.push((id, rect));
+        }
+        for q in [
+            r(0.0, 0.0, 100.0, 100.0),
+            r(-600.0, -600.0, 10.0, 10.0),
+            r(1000.0, 500.0, 700.0, 300.0),
+        ] {
+            let mut got = idx.query_rect(&q);
+            let mut want: Vec<Uuid> = rects
+                .iter()
+                .filter(|(_, b)| b.intersects(&q))
+                .map(|(id, _)| *id)
+                .collect();
+            got.sort();
+            want.sort();
+            assert_eq!(got, want);
+        }
+    }
+
+    #[test]
+    fn update_remove_and_grow() {
+        let mut idx = SpatialIndex::new();
+        let a = Uuid::now_v7();
+        idx.insert_rect(a, r(10.0, 10.0, 5.0, 5.0));
+        assert_eq!(idx.query_point(12.0, 12.0, 0.0), vec![a]);
+
+        idx.insert_rect(a, r(100.0, 100.0, 5.0, 5.0));
+        assert!(idx.query_point(12.0, 12.0, 0.0).is_empty());
+        assert_eq!(idx.query_point(102.0, 102.0, 0.0), vec![a]);
+
+        // 超出初始范围触发扩容
+        let far = Uuid::now_v7();
+        idx.insert_rect(far, r(50_000.0, -40_000.0, 10.0, 10.0));
+        assert_eq!(idx.query_point(50_005.0, -39_995.0, 0.0), vec![far]);
+        assert_eq!(idx.query_point(102.0, 102.0, 0.0), vec![a]);
+
+        assert!(idx.remove(a));
+        assert!(!idx.remove(a));
+        assert_eq!(idx.len(), 1);
+        assert!(idx.query_point(102.0, 102.0, 0.0).is_empty());
+    }
+}
@@ -1,15 +1,158 @@
 use crate::model::Annotation;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
+/// 可序列化的编辑命令：只记录变更数据，不再保存闭包或整表快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum UndoCommand {
+    /// 在 index 处插入
+    Add {
+        index: usize,
+        annotation: Annotation,
+    },
+    /// 删除，撤销时插回原 index
+    Remove {
+        index: usize,
+        annotation: Annotation,
+    },
+    /// 修改前后的完整标注（按 id 定位）
+    Update {
+        before: Box<Annotation>,
+        after: Box<Annotation>,
+    },
+    /// 图层顺序调整
+    Reorder {
+        id: Uuid,
+        before_z: i32,
+        after_z: i32,
+    },
+    /// 组合命令：按顺序应用，逆序撤销
+    Batch { commands: Vec<UndoCommand> },
+}
+
+impl UndoCommand {
+    pub fn apply(&self, annotations: &mut Vec<Annotation>) {
+        match self {
+            UndoCommand::Add { index, annotation } => {
+                annotations.insert((*index).min(annotations.len()), annotation.clone());
+            }
+            UndoCommand::Remove { annotation, .. } => {
+                remove_by_id(annotations, annotation.meta.id);
+            }
+            UndoCommand::Update { after, .. } => replace_by_id(annotations, after),
+            UndoCommand::Reorder { id, after_z, .. } => set_z(annotations, *id, *after_z),
+            UndoCommand::Batch { commands } => {
+                for c in commands {
+                    c.apply(annotations);
+                }
+            }
+        }
+    }
+
+    pub fn revert(&self, annotations: &mut Vec<Annotation>) {
+        match self {
+            UndoCommand::Add { annotation, .. } => {
+                remove_by_id(annotations, annotation.meta.id);
+            }
+            UndoCommand::Remove { index, annotation } => {
+                annotations.insert((*index).min(annotations.len()), annotation.clone());
+            }
+            UndoCommand::Update { before, .. } => replace_by_id(annotations, before),
+            UndoCommand::Reorder { id, before_z, .. } => set_z(annotations, *id, *before_z),
+            UndoCommand::Batch { commands } => {
+                for c in commands.iter().rev() {
+                    c.revert(annotations);
+                }
+            }
+        }
+    }
+
+    /// 命令涉及的标注 id（用于同步索引等派生状态）
+    pub fn affected_ids(&self) -> Vec<Uuid> {
+        match self {
+            UndoCommand::Add { annotation, .. } | UndoCommand::Remove { annotation, .. } => {
+                vec![annotation.meta.id]
+            }
+            UndoCommand::Update { after, .. } => vec![after.meta.id],
+            UndoCommand::Reorder { id, .. } => vec![*id],
+            UndoCommand::Batch { commands } => {
+                commands.iter().flat_map(|c| c.affected_ids()).collect()
+            }
+        }
+    }
+
+    /// 合并连续命令（拖拽）：同一目标保留最初的 before 与最新的 after，其余情况组合为 Batch
+    fn merge(self, next: UndoCommand) -> UndoCommand {
+        match (self, next) {
+            (UndoCommand::Update { before, .. }, UndoCommand::Update { after, .. })
+                if before.meta.id == after.meta.id =>
+            {
+                UndoCommand::Update { before, after }
+            }
+            (
+                UndoCommand::Reorder { id, before_z, .. },
+                UndoCommand::Reorder {
+                    id: next_id,
+                    after_z,
+                    ..
+                },
+            ) if id == next_id => UndoCommand::Reorder {
+                id,
+                before_z,
+                after_z,
+            },
+            (UndoCommand::Batch { mut commands }, next) => {
+                commands.push(next);
+                UndoCommand::Batch { commands }
+            }
+            (prev, next) => UndoCommand::Batch {
+                commands: vec![prev, next],
+            },
+        }
+    }
+}
+
+fn remove_by_id(annotations: &mut Vec<Annotation>, id: Uuid) {
+    if let Some(i) = annotations.iter().position(|a| a.meta.id == id) {
+        annotations.remove(i);
+    }
+}
+
+fn replace_by_id(annotations: &mut [Annotation], ann: &Annotation) {
+    if let Some(a) = annotations.iter_mut().find(|a| a.meta.id == ann.meta.id) {
+        *a = ann.clone();
+    }
+}
+
+fn set_z(annotations: &mut [Annotation], id: Uuid, z: i32) {
+    if let Some(a) = annotations.iter_mut().find(|a| a.meta.id == id) {
+        a.meta.z = z;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UndoOp {
-    pub apply: Box<dyn Fn(&mut UndoContext) + Send + Sync>,
-    pub revert: Box<dyn Fn(&mut UndoContext) + Send + Sync>,
+    pub command: UndoCommand,
+    #[serde(default)]
     pub merge_key: Option<String>, // 用于拖拽合并
 }
 
-pub struct UndoContext {
-    pub annotations: Vec<Annotation>,
+impl UndoOp {
+    pub fn new(command: UndoCommand) -> Self {
+        Self {
+            command,
+            merge_key: None,
+        }
+    }
+
+    pub fn with_merge_key(mut self, key: impl Into<String>) -> Self {
+        self.merge_key = Some(key.into());
+        self
+    }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UndoStack {
     ops: Vec<UndoOp>,
     cap: usize,
@@ -29,11 +172,16 @@ impl UndoStack {
         // 新的操作会清空 redo 栈
         self.redo.clear();
         if let Some(k) = &op.merge_key {
-            if let Some(last) = self.ops.last() {
+            if let Some(last) = self.ops.last_mut() {
                 if last.merge_key.as_ref() == Some(k) {
-                    // 替换合并
-                    let len = self.ops.len();
-                    self.ops[len - 1] = op;
+                    // 与上一条合并：撤销时回到合并序列开始前的状态
+                    let prev = std::mem::replace(
+                        &mut last.command,
+                        UndoCommand::Batch {
+                            commands: Vec::new(),
+                        },
+                    );
+                    last.command = prev.merge(op.command);
                     return;
                 }
             }
@@ -45,9 +193,9 @@ impl UndoStack {
         }
     }
 
-    pub fn undo(&mut self, ctx: &mut UndoContext) -> bool {
+    pub fn undo(&mut self, annotations: &mut Vec<Annotation>) -> bool {
         if let Some(op) = self.ops.pop() {
-            (op.revert)(ctx);
+            op.command.revert(annotations);
             // 放入 redo 栈
             self.redo.push(op);
             true
@@ -56,9 +204,9 @@ impl UndoStack {
         }
     }
 
-    pub fn redo(&mut self, ctx: &mut UndoContext) -> bool {
+    pub fn redo(&mut self, annotations: &mut Vec<Annotation>) -> bool {
         if let Some(op) = self.redo.pop() {
-            (op.apply)(ctx);
+            op.command.apply(annotations);
             self.ops.push(op); // 重新进入主栈
             true
         } else {
@@ -73,6 +221,16 @@ impl UndoStack {
     pub fn is_empty(&self) -> bool {
         self.ops.is_empty()
     }
+
+    /// 可撤销的操作（旧 -> 新）
+    pub fn undo_ops(&self) -> &[UndoOp] {
+        &self.ops
+    }
+
+    /// 可重做的操作（栈顶在末尾）
+    pub fn redo_ops(&self) -> &[UndoOp] {
+        &self.redo
+    }
 }
 
 #[cfg(test)]
@@ -102,71 +260,117 @@ mod tests {
         }
     }
 
+    fn update(list: &mut [Annotation], f: impl FnOnce(&mut Annotation)) -> UndoCommand {
+        let before = list[0].clone();
+        f(&mut list[0]);
+        UndoCommand::Update {
+            before: Box::new(before),
+            after: Box::new(list[0].clone()),
+        }
+    }
+
     #[test]
     fn merge_drag_ops() {
-        let mut ctx = UndoContext {
-            annotations: vec![dummy_annotation(0)],
-        };
+        let mut list = vec![dummy_annotation(0)];
         let mut stack = UndoStack::new(10);
-        let mut prev_x = ctx.annotations[0].meta.x;
-        ctx.annotations[0].meta.x = 5.0;
-        stack.push(UndoOp {
-            merge_key: Some("drag".into()),
-            apply: Box::new(|_| {}),
-            revert: Box::new(move |c: &mut UndoContext| {
-                c.annotations[0].meta.x = prev_x;
-            }),
-        });
-        prev_x = 5.0;
-        ctx.annotations[0].meta.x = 8.0;
-        stack.push(UndoOp {
-            merge_key: Some("drag".into()),
-            apply: Box::new(|_| {}),
-            revert: Box::new(move |c: &mut UndoContext| {
-                c.annotations[0].meta.x = prev_x;
-            }),
-        });
+        let cmd = update(&mut list, |a| a.meta.x = 5.0);
+        stack.push(UndoOp::new(cmd).with_merge_key("drag"));
+        let cmd = update(&mut list, |a| a.meta.x = 8.0);
+        stack.push(UndoOp::new(cmd).with_merge_key("drag"));
         assert_eq!(stack.len(), 1); // 合并
+
+        // 撤销回到拖拽开始前，重做回到拖拽结束
+        assert!(stack.undo(&mut list));
+        assert_eq!(list[0].meta.x, 0.0);
+        assert!(stack.redo(&mut list));
+        assert_eq!(list[0].meta.x, 8.0);
     }
 
     #[test]
     fn separate_property_changes() {
-        let mut ctx = UndoContext {
-            annotations: vec![dummy_annotation(0)],
-        };
+        let mut list = vec![dummy_annotation(0)];
         let mut stack = UndoStack::new(10);
-        let before_w = ctx.annotations[0].meta.w; // 10
-        ctx.annotations[0].meta.w = 20.0; // new value
-        stack.push(UndoOp {
-            merge_key: None,
-            apply: Box::new(|c: &mut UndoContext| {
-                c.annotations[0].meta.w = 20.0;
-            }),
-            revert: Box::new(move |c: &mut UndoContext| {
-                c.annotations[0].meta.w = before_w;
-            }),
-        });
-        let before_h = ctx.annotations[0].meta.h; // 10
-        ctx.annotations[0].meta.h = 30.0; // new value
-        stack.push(UndoOp {
-            merge_key: None,
-            apply: Box::new(|c: &mut UndoContext| {
-                c.annotations[0].meta.h = 30.0;
-            }),
-            revert: Box::new(move |c: &mut UndoContext| {
-                c.annotations[0].meta.h = before_h;
-            }),
-        });
+        let cmd = update(&mut list, |a| a.meta.w = 20.0);
+        stack.push(UndoOp::new(cmd));
+        let cmd = update(&mut list, |a| a.meta.h = 30.0);
+        stack.push(UndoOp::new(cmd));
         assert_eq!(stack.len(), 2);
-        assert!(stack.undo(&mut ctx));
-        assert_eq!(ctx.annotations[0].meta.h, 10.0);
-        assert!(stack.undo(&mut ctx));
-        assert_eq!(ctx.annotations[0].meta.w, 10.0);
+        assert!(stack.undo(&mut list));
+        assert_eq!(list[0].meta.h, 10.0);
+        assert!(stack.undo(&mut list));
+        assert_eq!(list[0].meta.w, 10.0);
 
         // Redo 两次
-        assert!(stack.redo(&mut ctx));
-        assert_eq!(ctx.annotations[0].meta.w, 20.0);
-        assert!(stack.redo(&mut ctx));
-        assert_eq!(ctx.annotations[0].meta.h, 30.0);
+        assert!(stack.redo(&mut list));
+        assert_eq!(list[0].meta.w, 20.0);
+        assert!(stack.redo(&mut list));
+        assert_eq!(list[0].meta.h, 30.0);
+    }
+
+    #[test]
+    fn remove_restores_original_position() {
+        let mut list = vec![
+            dummy_annotation(0),
+            dummy_annotation(1),
+            dummy_annotation(2),
+        ];
+        let removed = list[1].clone();
+        let cmd = UndoCommand::Remove {
+            index: 1,
+            annotation: removed.clone(),
+        };
+        cmd.apply(&mut list);
+        assert_eq!(list.len(), 2);
+        cmd.revert(&mut list);
+        assert_eq!(list[1].meta.id, removed.meta.id);
+    }
+
+    #[test]
+    fn batch_reverts_in_reverse_order() {
+        let mut list = Vec::new();
+        let a = dummy_annotation(0);
+        let cmd = UndoCommand::Batch {
+            commands: vec![
+                UndoCommand::Add {
+                    index: 0,
+                    annotation: a.clone(),
+                },
+                UndoCommand::Reorder {
+                    id: a.meta.id,
+                    before_z: 0,
+                    after_z: 3,
+                },
+            ],
+        };
+        cmd.apply(&mut list);
+        assert_eq!(list[0].meta.z, 3);
+        cmd.revert(&mut list);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn stack_round_trips_through_serde() {
+        let mut list = vec![dummy_annotation(0)];
+        let mut stack = UndoStack::new(10);
+        let cmd = update(&mut list, |a| a.meta.x = 4.0);
+        stack.push(UndoOp::new(cmd).with_merge_key("drag"));
+        let extra = dummy_annotation(1);
+        stack.push(UndoOp::new(UndoCommand::Add {
+            index: 1,
+            annotation: extra.clone(),
+        }));
+        list.push(extra);
+        assert!(stack.undo(&mut list));
+
+        let json = serde_json::to_string(&stack).unwrap();
+        assert!(json.contains(r#""op":"update""#));
+        let mut restored: UndoStack = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored.redo_ops().len(), 1);
+        assert!(restored.redo(&mut list));
+        assert_eq!(list.len(), 2);
+        assert!(restored.undo(&mut list));
+        assert!(restored.undo(&mut list));
+        assert_eq!(list[0].meta.x, 0.0);
     }
 }
@@ -0,0 +1,274 @@
+//! 冻结的各版本标注数据（tests/fixtures）必须始终能升级到当前版本。
+//! 新增 schema 版本时：保留旧 fixture 不动，补充新版本 fixture 与对应断言。
+//! 单个标注类型的字段缺省值不属于迁移，放在 model.rs 的单元测试中。
+use screenshot_core::{
+    AnnotationDocument, AnnotationKind, ArrowHeadShape, ArrowHeads, BlendMode, Color, ErrorKind,
+    HistoryDocument, ImageSource, LineCap, LineJoin, LineStyle, MagnifierShape, MeasureMode,
+    MigrationRegistry, SampleFilter, SpotlightShape, UndoCommand, ANNOTATION_SCHEMA_VERSION,
+};
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+#[test]
+fn annotations_v1_upgrade() {
+    let doc = AnnotationDocument::from_json(&fixture("annotations_v1.json")).unwrap();
+    assert_eq!(doc.version, ANNOTATION_SCHEMA_VERSION);
+    let a = &doc.annotations;
+    assert_eq!(a.len(), 4);
+
+    // 合法的 v1 十六进制（含省略 #）保留
+    assert_eq!(a[0].meta.fill_color, Some(Color::rgb(0x33, 0x66, 0xFF)));
+    assert_eq!(a[0].meta.stroke_color, Some(Color::WHITE));
+    assert_eq!(a[0].meta.opacity, 0.8);
+
+    // v1 不识别的颜色名：箭头回退白色
+    assert_eq!(a[1].meta.stroke_color, Some(Color::WHITE));
+    assert!(a[1].meta.locked);
+    assert_eq!(a[1].meta.rotation, 45);
+    assert!(matches!(
+        a[1].kind,
+        AnnotationKind::Arrow {
+            head_size: 8,
+            line_style: LineStyle::Dashed,
+            ..
+        }
+    ));
+
+    // 文字：fill 与 stroke 在 v1 下都无效 → 白色
+    assert_eq!(a[2].meta.fill_color, Some(Color::WHITE));
+    assert_eq!(a[2].meta.stroke_color, None);
+
+    // 手绘无颜色 → 白色描边
+    assert_eq!(a[3].meta.stroke_color, Some(Color::WHITE));
+    match &a[3].kind {
+        AnnotationKind::Freehand { points, .. } => assert_eq!(points.len(), 2),
+        k => panic!("unexpected kind {k:?}"),
+    }
+}
+
+#[test]
+fn annotations_v2_upgrade() {
+    let doc = AnnotationDocument::from_json(&fixture("annotations_v2.json")).unwrap();
+    assert_eq!(doc.version, ANNOTATION_SCHEMA_VERSION);
+    assert_eq!(doc.annotations.len(), 2);
+    assert_eq!(
+        doc.annotations[0].meta.fill_color,
+        Some(Color::rgba(0xFF, 0xEE, 0x00, 0x80))
+    );
+    assert!(matches!(
+        doc.annotations[1].kind,
+        AnnotationKind::Mosaic {
+            level: 1,
+            corner_radius: 0
+        }
+    ));
+}
+
+#[test]
+fn annotations_v3_current() {
+    // v3 新增的每个类型与字段各出现一次，且都取非缺省值，字段丢失或改名会被发现
+    let doc = AnnotationDocument::from_json(&fixture("annotations_v3.json")).unwrap();
+    let k: Vec<&AnnotationKind> = doc.annotations.iter().map(|a| &a.kind).collect();
+    assert_eq!(k.len(), 19);
+    assert!(matches!(k[0], AnnotationKind::Rect { corner_radius: 6 }));
+    match k[1] {
+        AnnotationKind::Arrow {
+            head_size: 10,
+            line_style: LineStyle::Dashed,
+            controls,
+            heads: ArrowHeads::Both,
+            head_shape: ArrowHeadShape::Circle,
+        } => assert_eq!(controls, &[(30.0, 0.0), (60.0, 60.0)]),
+        k => panic!("unexpected kind {k:?}"),
+    }
+    assert!(matches!(
+        k[2],
+        AnnotationKind::Highlight {
+            mode: BlendMode::Screen,
+            corner_radius: 4
+        }
+    ));
+    assert!(matches!(
+        k[3],
+        AnnotationKind::Mosaic {
+            level: 2,
+            corner_radius: 8
+        }
+    ));
+    assert!(matches!(k[4], AnnotationKind::Blur { radius: 6 }));
+    assert!(matches!(k[5], AnnotationKind::Redact {}));
+    assert!(matches!(
+        k[6],
+        AnnotationKind::Spotlight {
+            shape: SpotlightShape::Ellipse,
+            feather: 12
+        }
+    ));
+    match k[7] {
+        AnnotationKind::Magnifier {
+            source,
+            zoom,
+            shape: MagnifierShape::Rect,
+            filter: SampleFilter::Bilinear,
+        } => assert_eq!((*source, *zoom), ((10.0, 10.0, 8.0, 8.0), 4.0)),
+        k => panic!("unexpected kind {k:?}"),
+    }
+    match k[8] {
+        AnnotationKind::Image {
+            source: ImageSource::Asset { id },
+        } => assert_eq!(id.as_u128(), 0x01920000_0000_7000_8000_0000000000aa),
+        k => panic!("unexpected kind {k:?}"),
+    }
+    match k[9] {
+        AnnotationKind::Image {
+            source: ImageSource::Embedded { png },
+        } => assert!(png.as_bytes().starts_with(b"\x89PNG")),
+        k => panic!("unexpected kind {k:?}"),
+    }
+    assert!(matches!(
+        k[10],
+        AnnotationKind::Freehand {
+            cap: LineCap::Square,
+            join: LineJoin::Miter,
+            ..
+        }
+    ));
+    match k[11] {
+        AnnotationKind::Highlighter { points, smoothing } => {
+            assert_eq!((points.len(), *smoothing), (2, 0.0))
+        }
+        k => panic!("unexpected kind {k:?}"),
+    }
+    match k[12] {
+        AnnotationKind::Polygon {
+            points,
+            join: LineJoin::Miter,
+        } => assert_eq!(points.len(), 3),
+        k => panic!("unexpected kind {k:?}"),
+    }
+    assert!(matches!(
+        k[13],
+        AnnotationKind::Polyline {
+            start_arrow: true,
+            end_arrow: true,
+            head_size: 8,
+            cap: LineCap::Square,
+            join: LineJoin::Bevel,
+            ..
+        }
+    ));
+    assert!(matches!(k[14], AnnotationKind::Ellipse {}));
+    assert!(matches!(
+        k[15],
+        AnnotationKind::Line {
+            line_style: LineStyle::Dashed,
+            cap: LineCap::Round
+        }
+    ));
+    assert!(matches!(k[16], AnnotationKind::StepMarker { number: 3 }));
+    match k[17] {
+        AnnotationKind::Callout {
+            content,
+            font_size: 12,
+            anchor,
+            corner_radius: 6,
+        } => assert_eq!((content.as_str(), *anchor), ("look here", (150.0, 90.0))),
+        k => panic!("unexpected kind {k:?}"),
+    }
+    assert!(matches!(
+        k[18],
+        AnnotationKind::Measure {
+            mode: MeasureMode::Rect
+        }
+    ));
+}
+
+#[test]
+fn v2_reader_reports_v3_as_newer() {
+    // 只认识 v2 的旧版本读到含新类型的 v3 文档时，应报告版本过新而不是未知类型
+    let old = MigrationRegistry::new(2);
+    let value: serde_json::Value = serde_json::from_slice(&fixture("annotations_v3.json")).unwrap();
+    let err = old.upgrade_annotations(value).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::Unsupported));
+    assert!(err.to_string().contains("newer than supported"), "{err}");
+}
+
+#[test]
+fn history_v1_upgrade() {
+    let doc = HistoryDocument::from_json(&fixture("history_v1.json")).unwrap();
+    let ops = doc.history.undo_ops();
+    assert_eq!(ops.len(), 2);
+    match &ops[0].command {
+        // "#0F0" 在 v1 下无效，矩形填充被忽略
+        UndoCommand::Add { annotation, .. } => {
+            assert_eq!(annotation.meta.fill_color, None);
+            assert_eq!(annotation.meta.stroke_color, Some(Color::rgb(0, 0xFF, 0)));
+        }
+        c => panic!("unexpected command {c:?}"),
+    }
+    match &ops[1].command {
+        UndoCommand::Batch { commands } => match &commands[0] {
+            UndoCommand::Update { after, .. } => {
+                assert_eq!(after.meta.x, 5.0);
+                assert_eq!(after.meta.fill_color, None);
+            }
+            c => panic!("unexpected command {c:?}"),
+        },
+        c => panic!("unexpected command {c:?}"),
+    }
+    assert_eq!(ops[1].merge_key.as_deref(), Some("drag"));
+
+    // 升级后的历史可继续撤销
+    let mut stack = doc.history;
+    let mut list = Vec::new();
+    ops_replay(&stack, &mut list);
+    assert_eq!(list[0].meta.z, 3);
+    stack.undo(&mut list);
+    assert_eq!((list[0].meta.x, list[0].meta.z), (0.0, 0));
+}
+
+/// 按顺序重放 undo 栈中的命令，重建保存时的标注列表
+fn ops_replay(stack: &screenshot_core::UndoStack, list: &mut Vec<screenshot_core::Annotation>) {
+    for op in stack.undo_ops() {
+        op.command.apply(list);
+    }
+}
+
+#[test]
+fn round_trip_writes_current_version() {
+    let doc = AnnotationDocument::from_json(&fixture("annotations_v1.json")).unwrap();
+    let json = serde_json::to_vec(&doc).unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(v["version"], ANNOTATION_SCHEMA_VERSION);
+    let again = AnnotationDocument::from_json(&json).unwrap();
+    assert_eq!(again.annotations.len(), doc.annotations.len());
+    assert_eq!(
+        again.annotations[0].meta.fill_color,
+        doc.annotations[0].meta.fill_color
+    );
+}
+
+#[test]
+fn rejects_future_and_malformed_documents() {
+    let future = format!(
+        r#"{{"version": {}, "annotations": []}}"#,
+        ANNOTATION_SCHEMA_VERSION + 1
+    );
+    let err = AnnotationDocument::from_json(future.as_bytes()).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::Unsupported));
+
+    for bad in [
+        r#"{"annotations": []}"#,
+        r#"{"version": 2}"#,
+        r#""text""#,
+        r#"{"version": 2, "annotations": [{"meta": {}}]}"#,
+    ] {
+        let err = AnnotationDocument::from_json(bad.as_bytes()).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::Validation), "{bad}");
+    }
+}
@@ -0,0 +1,720 @@
+// Linux platform implementation for screenshot toolkit (X11)
+use anyhow::{Context, Result};
+use chrono::Utc;
+use screenshot_core::{Frame, FrameSet, PixelFormat, Screenshot};
+use services::Capturer;
+use std::sync::Arc;
+use uuid::Uuid;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::shm::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{self, ConnectionExt as _, ImageFormat, ImageOrder};
+use x11rb::rust_connection::RustConnection;
+
+/// 显示器信息：包含位置、尺寸、DPI等元数据
+#[derive(Debug, Clone)]
+pub struct DisplayInfo {
+    /// 显示器ID（RandR monitor 序号；无 RandR 时为 0）
+    pub id: u32,
+    /// 显示器名称
+    pub name: String,
+    /// 是否为主显示器
+    pub is_primary: bool,
+    /// 在虚拟桌面中的位置（左上角坐标）
+    pub x: i32,
+    pub y: i32,
+    /// 显示器的像素尺寸
+    pub width: u32,
+    pub height: u32,
+    /// DPI缩放因子（X11 坐标即物理像素，恒为 1.0）
+    pub scale_factor: f64,
+}
+
+/// 虚拟桌面边界框
+#[derive(Debug, Clone)]
+pub struct VirtualBounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 虚拟桌面坐标系统：X11 下所有显示器共享 root window 坐标空间
+#[derive(Debug, Clone)]
+pub struct VirtualDesktop {
+    pub displays: Vec<DisplayInfo>,
+    pub total_bounds: VirtualBounds,
+}
+
+impl VirtualDesktop {
+    /// 检测并构建虚拟桌面坐标系统
+    pub fn detect() -> Result<Self> {
+        let session = X11Session::connect()?;
+        Self::from_displays(session.displays()?)
+    }
+
+    fn from_displays(displays: Vec<DisplayInfo>) -> Result<Self> {
+        if displays.is_empty() {
+            anyhow::bail!("未找到任何显示器");
+        }
+        let (min_x, min_y, max_x, max_y) = displays.iter().fold(
+            (i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+            |(min_x, min_y, max_x, max_y), d| {
+                (
+                    min_x.min(d.x),
+                    min_y.min(d.y),
+                    max_x.max(d.x + d.width as i32),
+                    max_y.max(d.y + d.height as i32),
+                )
+            },
+        );
+        Ok(Self {
+            displays,
+            total_bounds: VirtualBounds {
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+                width: (max_x - min_x) as u32,
+                height: (max_y - min_y) as u32,
+            },
+        })
+    }
+
+    /// 获取主显示器信息（RandR 未标记主显示器时取第一个）
+    pub fn primary_display(&self) -> Option<&DisplayInfo> {
+        self.displays
+            .iter()
+            .find(|d| d.is_primary)
+            .or_else(|| self.displays.first())
+    }
+
+    /// 根据虚拟坐标找到对应的显示器
+    pub fn find_display_at(&self, x: i32, y: i32) -> Option<&DisplayInfo> {
+        self.displays
+            .iter()
+            .find(|d| x >= d.x && x < d.x + d.width as i32 && y >= d.y && y < d.y + d.height as i32)
+    }
+
+    /// 获取跨越指定区域的所有显示器
+    pub fn displays_in_region(&self, x: i32, y: i32, width: u32, height: u32) -> Vec<&DisplayInfo> {
+        let x2 = x + width as i32;
+        let y2 = y + height as i32;
+        self.displays
+            .iter()
+            .filter(|d| {
+                let dx2 = d.x + d.width as i32;
+                let dy2 = d.y + d.height as i32;
+                !(x2 <= d.x || x >= dx2 || y2 <= d.y || y >= dy2)
+            })
+            .collect()
+    }
+}
+
+/// Linux 捕获器：基于 X11 (XGetImage / MIT-SHM) 的全屏、区域与多显示器截图
+pub struct LinuxCapturer;
+
+impl LinuxCapturer {
+    /// 全屏截图：捕获主显示器的完整屏幕
+    pub fn capture_full() -> Result<Screenshot> {
+        use infra::metrics;
+        let timer = metrics::start_timer(
+            "capture_full_duration_us",
+            &[1000, 5000, 10000, 50000, 100000],
+        );
+        metrics::counter("capture_full_start").inc();
+
+        let session = X11Session::connect()?;
+        let desktop = VirtualDesktop::from_displays(session.displays()?)?;
+        let primary = desktop
+            .primary_display()
+            .ok_or_else(|| anyhow::anyhow!("未找到主显示器"))?;
+        let frame = session
+            .grab(primary.x, primary.y, primary.width, primary.height)
+            .context("X11 主显示器图像捕获失败")?;
+
+        metrics::counter("capture_full_ok").inc();
+        drop(timer);
+        Ok(Self::build_screenshot(frame.clone(), vec![frame]))
+    }
+
+    /// 区域截图：坐标相对主显示器左上角，超出部分被裁剪
+    pub fn capture_region(x: u32, y: u32, width: u32, height: u32) -> Result<Screenshot> {
+        use infra::metrics;
+        let timer = metrics::start_timer(
+            "capture_region_duration_us",
+            &[1000, 5000, 10000, 50000, 100000],
+        );
+        metrics::counter("capture_region_start").inc();
+
+        let session = X11Session::connect()?;
+        let desktop = VirtualDesktop::from_displays(session.displays()?)?;
+        let primary = desktop
+            .primary_display()
+            .ok_or_else(|| anyhow::anyhow!("未找到主显示器"))?;
+
+        let crop_x = x.min(primary.width);
+        let crop_y = y.min(primary.height);
+        let crop_w = width.min(primary.width - crop_x);
+        let crop_h = height.min(primary.height - crop_y);
+        if crop_w == 0 || crop_h == 0 {
+            anyhow::bail!("裁剪区域为空");
+        }
+
+        // 直接向服务器请求目标区域，避免先抓整屏再裁剪
+        let frame = session
+            .grab(
+                primary.x + crop_x as i32,
+                primary.y + crop_y as i32,
+                crop_w,
+                crop_h,
+            )
+            .context("X11 区域图像捕获失败")?;
+
+        metrics::counter("capture_region_ok").inc();
+        drop(timer);
+        Ok(Self::build_screenshot(frame.clone(), vec![frame]))
+    }
+
+    /// 多显示器截图：逐个捕获显示器并合成为虚拟桌面
+    ///
+    /// 返回的 `FrameSet.primary` 为合成后的虚拟桌面画布（显示器间隙填 0），
+    /// `FrameSet.all` 按 `VirtualDesktop::displays` 顺序保存每个显示器的独立帧。
+    pub fn capture_all() -> Result<Screenshot> {
+        use infra::metrics;
+        let timer = metrics::start_timer(
+            "capture_all_duration_us",
+            &[5000, 10000, 50000, 100000, 500000],
+        );
+        metrics::counter("capture_all_start").inc();
+
+        let session = X11Session::connect()?;
+        let desktop = VirtualDesktop::from_displays(session.displays()?)?;
+
+        // X11 连接不可跨线程并发请求，逐个抓取；像素格式转换在 grab 内部并行
+        let mut frames = Vec::with_capacity(desktop.displays.len());
+        for d in &desktop.displays {
+            let frame = session
+                .grab(d.x, d.y, d.width, d.height)
+                .with_context(|| format!("X11 显示器 {} 图像捕获失败", d.name))?;
+            frames.push(frame);
+        }
+
+        let canvas = compose_virtual_desktop(&desktop, &frames);
+
+        #[cfg(debug_assertions)]
+        tracing::debug!(
+            "X11 虚拟桌面画布: 边界({}, {}) 尺寸 {}x{}，显示器 {} 个",
+            desktop.total_bounds.min_x,
+            desktop.total_bounds.min_y,
+            canvas.width,
+            canvas.height,
+            frames.len()
+        );
+
+        metrics::counter("capture_all_ok").inc();
+        drop(timer);
+        Ok(Self::build_screenshot(canvas, frames))
+    }
+
+    /// 自研框选 UI 接口：以虚拟桌面截图为背景，通过 ui_overlay 选择区域后在内存裁剪
+    pub fn capture_region_interactive_custom(
+        selector: &dyn ui_overlay::RegionSelector,
+    ) -> Result<Screenshot> {
+        use infra::metrics;
+        let timer = metrics::start_timer(
+            "interactive_duration_us",
+            &[1000, 5000, 10000, 50000, 100000, 500000, 1000000],
+        );
+        metrics::counter("interactive_start").inc();
+
+        let desktop = VirtualDesktop::detect().context("检测虚拟桌面失败")?;
+        let virtual_screenshot = Self::capture_all().context("获取虚拟桌面截图失败")?;
+        let virtual_frame = &virtual_screenshot.raw.primary;
+
+        let rgb_data: Vec<u8> = virtual_frame
+            .bytes
+            .chunks_exact(4)
+            .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+            .collect();
+
+        let monitor_layouts: Vec<ui_overlay::MonitorLayout> = desktop
+            .displays
+            .iter()
+            .map(|d| ui_overlay::MonitorLayout {
+                x: d.x,
+                y: d.y,
+                width: d.width,
+                height: d.height,
+                scale_factor: d.scale_factor,
+            })
+            .collect();
+
+        let bounds = &desktop.total_bounds;
+        let rect_opt = selector
+            .select_with_virtual_background(
+                &rgb_data,
+                virtual_frame.width,
+                virtual_frame.height,
+                (bounds.min_x, bounds.min_y, bounds.width, bounds.height),
+                (0, 0),
+                Some(&monitor_layouts),
+            )
+            .map_err(|e| {
+                metrics::counter("interactive_error").inc();
+                anyhow::anyhow!("overlay select: {e}")
+            })?;
+
+        let rect = match rect_opt {
+            Some(r) => {
+                metrics::counter("interactive_ok").inc();
+                r
+            }
+            None => {
+                metrics::counter("interactive_cancel").inc();
+                anyhow::bail!("user canceled interactive selection")
+            }
+        };
+
+        let scale = if rect.scale.is_finite() && rect.scale > 0.0 {
+            rect.scale
+        } else {
+            1.0
+        };
+        let x_virtual = (rect.x * scale).floor() as i32;
+        let y_virtual = (rect.y * scale).floor() as i32;
+        let w = (rect.w * scale).round().max(0.0) as u32;
+        let h = (rect.h * scale).round().max(0.0) as u32;
+
+        let canvas_x = (x_virtual - bounds.min_x).max(0) as u32;
+        let canvas_y = (y_virtual - bounds.min_y).max(0) as u32;
+        let frame = crop_frame(virtual_frame, canvas_x, canvas_y, w, h)
+            .ok_or_else(|| anyhow::anyhow!("empty crop region"))?;
+
+        drop(timer);
+        Ok(Self::build_screenshot(frame.clone(), vec![frame]))
+    }
+
+    fn build_screenshot(primary: Frame, all: Vec<Frame>) -> Screenshot {
+        Screenshot {
+            id: Uuid::now_v7(),
+            raw: Arc::new(FrameSet { primary, all }),
+            scale: 1.0,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl Capturer for LinuxCapturer {
+    fn capture_full(&self) -> Result<Screenshot> {
+        Self::capture_full()
+    }
+}
+
+/// 将各显示器帧按虚拟坐标合成到一张画布（间隙保持全 0）
+fn compose_virtual_desktop(desktop: &VirtualDesktop, frames: &[Frame]) -> Frame {
+    let bounds = &desktop.total_bounds;
+    let (canvas_w, canvas_h) = (bounds.width, bounds.height);
+    let mut canvas = vec![0u8; (canvas_w * canvas_h * 4) as usize];
+    for (d, f) in desktop.displays.iter().zip(frames) {
+        let ox = (d.x - bounds.min_x) as u32;
+        let oy = (d.y - bounds.min_y) as u32;
+        let copy_w = f.width.min(canvas_w.saturating_sub(ox));
+        for row in 0..f.height.min(canvas_h.saturating_sub(oy)) {
+            let src = (row * f.width * 4) as usize;
+            let dst = (((oy + row) * canvas_w + ox) * 4) as usize;
+            let len = (copy_w * 4) as usize;
+            canvas[dst..dst + len].copy_from_slice(&f.bytes[src..src + len]);
+        }
+    }
+    rgba_frame(canvas_w, canvas_h, canvas)
+}
+
+/// 在 RGBA 帧内裁剪矩形，区域为空时返回 None
+fn crop_frame(frame: &Frame, x: u32, y: u32, w: u32, h: u32) -> Option<Frame> {
+    let x2 = x.saturating_add(w).min(frame.width);
+    let y2 = y.saturating_add(h).min(frame.height);
+    let cw = x2.saturating_sub(x);
+    let ch = y2.saturating_sub(y);
+    if cw == 0 || ch == 0 {
+        return None;
+    }
+    let mut bytes = vec![0u8; (cw * ch * 4) as usize];
+    for row in 0..ch {
+        let src = (((y + row) * frame.width + x) * 4) as usize;
+        let dst = (row * cw * 4) as usize;
+        let len = (cw * 4) as usize;
+        bytes[dst..dst + len].copy_from_slice(&frame.bytes[src..src + len]);
+    }
+    Some(rgba_frame(cw, ch, bytes))
+}
+
+fn rgba_frame(width: u32, height: u32, rgba: Vec<u8>) -> Frame {
+    Frame {
+        width,
+        height,
+        pixel_format: PixelFormat::Rgba8,
+        bytes: Arc::from(rgba.into_boxed_slice()),
+    }
+}
+
+/// root window 的 ZPixmap 像素布局（来自连接 setup 与 root visual）
+#[derive(Debug, Clone, Copy)]
+struct PixelLayout {
+    bits_per_pixel: u8,
+    scanline_pad: u8,
+    lsb_first: bool,
+    red_mask: u32,
+    green_mask: u32,
+    blue_mask: u32,
+}
+
+impl PixelLayout {
+    fn stride(&self, width: u32) -> usize {
+        let pad = self.scanline_pad.max(8) as usize;
+        let bits = width as usize * self.bits_per_pixel as usize;
+        bits.div_ceil(pad) * pad / 8
+    }
+
+    /// ZPixmap 原始数据 -> RGBA8（alpha 恒为 255，X 服务器的填充字节不可信）
+    fn convert_to_rgba(&self, raw: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        use rayon::prelude::*;
+
+        if self.bits_per_pixel != 32 {
+            anyhow::bail!(
+                "unsupported X11 pixel format: {} bits per pixel",
+                self.bits_per_pixel
+            );
+        }
+        let stride = self.stride(width);
+        if raw.len() < stride * height as usize {
+            anyhow::bail!(
+                "X11 image data truncated: {} < {}",
+                raw.len(),
+                stride * height as usize
+            );
+        }
+        let (rs, gs, bs) = (
+            self.red_mask.trailing_zeros(),
+            self.green_mask.trailing_zeros(),
+            self.blue_mask.trailing_zeros(),
+        );
+        let row_bytes = width as usize * 4;
+        let mut out = vec![0u8; row_bytes * height as usize];
+        out.par_chunks_exact_mut(row_bytes)
+            .enumerate()
+            .for_each(|(row, dst)| {
+                let src = &raw[row * stride..row * stride + row_bytes];
+                for (d, s) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                    let px = [s[0], s[1], s[2], s[3]];
+                    let p = if self.lsb_first {
+                        u32::from_le_bytes(px)
+                    } else {
+                        u32::from_be_bytes(px)
+                    };
+                    d[0] = ((p & self.red_mask) >> rs) as u8;
+                    d[1] = ((p & self.green_mask) >> gs) as u8;
+                    d[2] = ((p & self.blue_mask) >> bs) as u8;
+                    d[3] = 255;
+                }
+            });
+        Ok(out)
+    }
+}
+
+/// 单次捕获使用的 X11 连接上下文
+struct X11Session {
+    conn: RustConnection,
+    root: xproto::Window,
+    root_width: u16,
+    root_height: u16,
+    layout: PixelLayout,
+    shm: bool,
+}
+
+impl X11Session {
+    fn connect() -> Result<Self> {
+        let (conn, screen_num) =
+            x11rb::connect(None).context("连接 X11 display 失败（请检查 DISPLAY 环境变量）")?;
+        let setup = conn.setup();
+        let screen = setup
+            .roots
+            .get(screen_num)
+            .ok_or_else(|| anyhow::anyhow!("X11 screen {} 不存在", screen_num))?;
+        let format = setup
+            .pixmap_formats
+            .iter()
+            .find(|f| f.depth == screen.root_depth)
+            .ok_or_else(|| anyhow::anyhow!("未找到 depth={} 的像素格式", screen.root_depth))?;
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .flat_map(|d| d.visuals.iter())
+            .find(|v| v.visual_id == screen.root_visual)
+            .ok_or_else(|| anyhow::anyhow!("未找到 root visual"))?;
+        let layout = PixelLayout {
+            bits_per_pixel: format.bits_per_pixel,
+            scanline_pad: format.scanline_pad,
+            lsb_first: setup.image_byte_order == ImageOrder::LSB_FIRST,
+            red_mask: visual.red_mask,
+            green_mask: visual.green_mask,
+            blue_mask: visual.blue_mask,
+        };
+        let (root, root_width, root_height) =
+            (screen.root, screen.width_in_pixels, screen.height_in_pixels);
+        // 远程 display（如 ssh 转发）虽然声明 MIT-SHM 但无法共享内存，attach 失败时回退
+        let shm = conn
+            .extension_information(shm::X11_EXTENSION_NAME)
+            .ok()
+            .flatten()
+            .is_some();
+        Ok(Self {
+            conn,
+            root,
+            root_width,
+            root_height,
+            layout,
+            shm,
+        })
+    }
+
+    /// 通过 RandR 1.5 GetMonitors 枚举显示器；不可用时把整个 root 视为单显示器
+    fn displays(&self) -> Result<Vec<DisplayInfo>> {
+        let monitors = self
+            .conn
+            .randr_get_monitors(self.root, true)
+            .ok()
+            .and_then(|c| c.reply().ok())
+            .map(|r| r.monitors)
+            .unwrap_or_default();
+        if monitors.is_empty() {
+            return Ok(vec![DisplayInfo {
+                id: 0,
+                name: "X11 Screen".into(),
+                is_primary: true,
+                x: 0,
+                y: 0,
+                width: self.root_width as u32,
+                height: self.root_height as u32,
+                scale_factor: 1.0,
+            }]);
+        }
+        Ok(monitors
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let name = self
+                    .conn
+                    .get_atom_name(m.name)
+                    .ok()
+                    .and_then(|c| c.reply().ok())
+                    .map(|r| String::from_utf8_lossy(&r.name).into_owned())
+                    .unwrap_or_else(|| format!("Display {}", i));
+                DisplayInfo {
+                    id: i as u32,
+                    name,
+                    is_primary: m.primary,
+                    x: m.x as i32,
+                    y: m.y as i32,
+                    width: m.width as u32,
+                    height: m.height as u32,
+                    scale_factor: 1.0,
+                }
+            })
+            .collect())
+    }
+
+    /// 抓取 root window 指定区域并转换为 RGBA 帧
+    fn grab(&self, x: i32, y: i32, width: u32, height: u32) -> Result<Frame> {
+        if width == 0 || height == 0 {
+            anyhow::bail!("empty capture region");
+        }
+        let (x, y) = (i16::try_from(x)?, i16::try_from(y)?);
+        let (w, h) = (u16::try_from(width)?, u16::try_from(height)?);
+        let raw = match self.shm.then(|| self.grab_shm(x, y, w, h)) {
+            Some(Ok(raw)) => raw,
+            Some(Err(e)) => {
+                tracing::debug!("MIT-SHM 捕获失败，回退 XGetImage: {e:#}");
+                self.grab_plain(x, y, w, h)?
+            }
+            None => self.grab_plain(x, y, w, h)?,
+        };
+        let rgba = self.layout.convert_to_rgba(&raw, width, height)?;
+        Ok(rgba_frame(width, height, rgba))
+    }
+
+    /// XGetImage：数据随回复传输，适用于任何 X 服务器
+    fn grab_plain(&self, x: i16, y: i16, w: u16, h: u16) -> Result<Vec<u8>> {
+        let reply = self
+            .conn
+            .get_image(ImageFormat::Z_PIXMAP, self.root, x, y, w, h, !0)?
+            .reply()
+            .context("XGetImage 失败")?;
+        Ok(reply.data)
+    }
+
+    /// XShmGetImage：服务器直接写入共享内存段，避免大图在 socket 上传输
+    fn grab_shm(&self, x: i16, y: i16, w: u16, h: u16) -> Result<Vec<u8>> {
+        let size = self.layout.stride(w as u32) * h as usize;
+        let segment = ShmSegment::new(size)?;
+        let seg = self.conn.generate_id()?;
+        self.conn
+            .shm_attach(seg, segment.id as u32, false)?
+            .check()
+            .context("XShmAttach 失败")?;
+        // 服务器已 attach，标记删除后即使进程异常退出也不会泄漏共享内存段
+        segment.mark_removed();
+        let result = self
+            .conn
+            .shm_get_image(
+                self.root,
+                x,
+                y,
+                w,
+                h,
+                !0,
+                ImageFormat::Z_PIXMAP.into(),
+                seg,
+                0,
+            )
+            .map_err(anyhow::Error::from)
+            .and_then(|c| c.reply().context("XShmGetImage 失败"));
+        let _ = self.conn.shm_detach(seg).map(|c| c.check());
+        result?;
+        Ok(segment.bytes().to_vec())
+    }
+}
+
+/// System V 共享内存段（MIT-SHM 使用），Drop 时 detach
+struct ShmSegment {
+    id: libc::c_int,
+    addr: *mut libc::c_void,
+    size: usize,
+}
+
+impl ShmSegment {
+    fn new(size: usize) -> Result<Self> {
+        // SAFETY: 标准 shmget/shmat 调用；失败时返回错误且不持有任何资源
+        unsafe {
+            let id = libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600);
+            if id < 0 {
+                anyhow::bail!("shmget failed: {}", std::io::Error::last_os_error());
+            }
+            let addr = libc::shmat(id, std::ptr::null(), 0);
+            if addr as isize == -1 {
+                let err = std::io::Error::last_os_error();
+                libc::shmctl(id, libc::IPC_RMID, std::ptr::null_mut());
+                anyhow::bail!("shmat failed: {}", err);
+            }
+            Ok(Self { id, addr, size })
+        }
+    }
+
+    fn mark_removed(&self) {
+        // SAFETY: id 来自成功的 shmget；IPC_RMID 仅在最后一次 detach 后真正释放
+        unsafe {
+            libc::shmctl(self.id, libc::IPC_RMID, std::ptr::null_mut());
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: addr 指向 size 字节且在 self 存活期间保持映射
+        unsafe { std::slice::from_raw_parts(self.addr as *const u8, self.size) }
+    }
+}
+
+impl Drop for ShmSegment {
+    fn drop(&mut self) {
+        // SAFETY: addr 来自成功的 shmat，只 detach 一次
+        unsafe {
+            libc::shmdt(self.addr);
+            libc::shmctl(self.id, libc::IPC_RMID, std::ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(x: i32, y: i32, w: u32, h: u32) -> DisplayInfo {
+        DisplayInfo {
+            id: 0,
+            name: "test".into(),
+            is_primary: false,
+            x,
+            y,
+            width: w,
+            height: h,
+            scale_factor: 1.0,
+        }
+    }
+
+    fn solid(w: u32, h: u32, v: u8) -> Frame {
+        rgba_frame(w, h, vec![v; (w * h * 4) as usize])
+    }
+
+    #[test]
+    fn layout_converts_bgrx_to_rgba() {
+        let layout = PixelLayout {
+            bits_per_pixel: 32,
+            scanline_pad: 32,
+            lsb_first: true,
+            red_mask: 0xFF0000,
+            green_mask: 0x00FF00,
+            blue_mask: 0x0000FF,
+        };
+        // 小端 BGRX：B=1 G=2 R=3，填充字节 0
+        let raw = [1u8, 2, 3, 0, 10, 20, 30, 0];
+        let rgba = layout.convert_to_rgba(&raw, 2, 1).unwrap();
+        assert_eq!(rgba, vec![3, 2, 1, 255, 30, 20, 10, 255]);
+    }
+
+    #[test]
+    fn layout_rejects_unsupported_depth() {
+        let layout = PixelLayout {
+            bits_per_pixel: 16,
+            scanline_pad: 32,
+            lsb_first: true,
+            red_mask: 0xF800,
+            green_mask: 0x07E0,
+            blue_mask: 0x001F,
+        };
+        assert!(layout.convert_to_rgba(&[0; 8], 2, 2).is_err());
+    }
+
+    #[test]
+    fn compose_places_monitors_with_gap_and_negative_origin() {
+        // 左侧显示器位于负坐标，两屏之间留 2px 间隙
+        let desktop =
+            VirtualDesktop::from_displays(vec![display(-4, 0, 4, 2), display(2, 1, 3, 2)]).unwrap();
+        assert_eq!(desktop.total_bounds.width, 9);
+        assert_eq!(desktop.total_bounds.height, 3);
+        let canvas = compose_virtual_desktop(&desktop, &[solid(4, 2, 100), solid(3, 2, 200)]);
+        let px = |x: u32, y: u32| canvas.bytes[((y * canvas.width + x) * 4) as usize];
+        assert_eq!(px(0, 0), 100);
+        assert_eq!(px(5, 0), 0, "gap stays empty");
+        assert_eq!(px(6, 1), 200);
+        assert_eq!(px(8, 2), 200);
+        assert_eq!(px(6, 0), 0);
+    }
+
+    #[test]
+    fn crop_clamps_to_frame() {
+        let f = solid(10, 10, 7);
+        let c = crop_frame(&f, 8, 8, 5, 5).unwrap();
+        assert_eq!((c.width, c.height), (2, 2));
+        assert!(crop_frame(&f, 10, 0, 5, 5).is_none());
+    }
+
+    #[test]
+    fn primary_falls_back_to_first_display() {
+        let desktop =
+            VirtualDesktop::from_displays(vec![display(0, 0, 4, 4), display(4, 0, 4, 4)]).unwrap();
+        assert_eq!(desktop.primary_display().unwrap().x, 0);
+        assert_eq!(desktop.displays_in_region(3, 0, 2, 2).len(), 2);
+        assert_eq!(desktop.find_display_at(5, 1).unwrap().x, 4);
+    }
+}
@@ -0,0 +1,106 @@
+//! 需要可用的 X 服务器，CI 中通过 `xvfb-run -a cargo test -p platform_linux` 运行；
+//! 未设置 DISPLAY 时跳过。
+use platform_linux::{LinuxCapturer, VirtualDesktop};
+use services::CaptureService;
+use std::sync::Arc;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt, CreateGCAux, Rectangle};
+use x11rb::wrapper::ConnectionExt as _;
+
+fn has_display() -> bool {
+    if std::env::var_os("DISPLAY").is_none() {
+        eprintln!("skip: DISPLAY 未设置");
+        return false;
+    }
+    true
+}
+
+/// 在 root window 上画一个实心矩形，作为可预测的捕获内容
+fn paint_root(x: i16, y: i16, w: u16, h: u16, rgb: u32) {
+    let (conn, screen_num) = x11rb::connect(None).unwrap();
+    let root = conn.setup().roots[screen_num].root;
+    let gc = conn.generate_id().unwrap();
+    conn.create_gc(gc, root, &CreateGCAux::new().foreground(rgb))
+        .unwrap();
+    conn.poly_fill_rectangle(
+        root,
+        gc,
+        &[Rectangle {
+            x,
+            y,
+            width: w,
+            height: h,
+        }],
+    )
+    .unwrap();
+    conn.free_gc(gc).unwrap();
+    conn.sync().unwrap();
+}
+
+#[test]
+fn capture_full_returns_primary_monitor() {
+    if !has_display() {
+        return;
+    }
+    let desktop = VirtualDesktop::detect().unwrap();
+    let primary = desktop.primary_display().unwrap().clone();
+    let shot = LinuxCapturer::capture_full().unwrap();
+    let f = &shot.raw.primary;
+    assert_eq!((f.width, f.height), (primary.width, primary.height));
+    assert_eq!(f.bytes.len(), (f.width * f.height * 4) as usize);
+    assert_eq!(shot.raw.all.len(), 1);
+    assert!(f.bytes.chunks_exact(4).all(|p| p[3] == 255));
+}
+
+#[test]
+fn capture_region_reads_painted_pixels() {
+    if !has_display() {
+        return;
+    }
+    let desktop = VirtualDesktop::detect().unwrap();
+    let primary = desktop.primary_display().unwrap().clone();
+    paint_root(
+        primary.x as i16 + 10,
+        primary.y as i16 + 10,
+        20,
+        20,
+        0xFF0000,
+    );
+    let shot = LinuxCapturer::capture_region(12, 12, 8, 8).unwrap();
+    let f = &shot.raw.primary;
+    assert_eq!((f.width, f.height), (8, 8));
+    assert!(
+        f.bytes.chunks_exact(4).all(|p| p == [255, 0, 0, 255]),
+        "painted red rect should be captured"
+    );
+    // 完全超出主显示器的区域视为空
+    assert!(LinuxCapturer::capture_region(primary.width, 0, 10, 10).is_err());
+}
+
+#[test]
+fn capture_all_keeps_one_frame_per_monitor() {
+    if !has_display() {
+        return;
+    }
+    let desktop = VirtualDesktop::detect().unwrap();
+    let shot = LinuxCapturer::capture_all().unwrap();
+    assert_eq!(shot.raw.all.len(), desktop.displays.len());
+    for (d, f) in desktop.displays.iter().zip(&shot.raw.all) {
+        assert_eq!((f.width, f.height), (d.width, d.height));
+    }
+    let canvas = &shot.raw.primary;
+    assert_eq!(
+        (canvas.width, canvas.height),
+        (desktop.total_bounds.width, desktop.total_bounds.height)
+    );
+}
+
+#[test]
+fn capture_service_drives_linux_capturer() {
+    if !has_display() {
+        return;
+    }
+    let svc = CaptureService::new(Arc::new(LinuxCapturer));
+    let shot = svc.capture_full().unwrap();
+    assert!(shot.raw.primary.width > 0);
+}
@@ -1,4 +1,12 @@
-use screenshot_core::{Annotation, AnnotationKind, BlendMode, Frame, LineStyle, PixelFormat};
+use screenshot_core::{
+    arrow_head, distance_to_polyline, distance_to_segment, rotation_sin_cos, Annotation,
+    AnnotationKind, ArrowHead, BlendMode, CalloutShape, Color, Dimension, Frame, ImageSource,
+    LineCap, LineJoin, LineStyle, MagnifierShape, PixelFormat, PngData, Rect, SampleFilter,
+    SpotlightShape,
+};
+use smallvec::SmallVec;
+
+mod text;
 
 /// 简单 RGBA 图像结构
 pub struct Image {
@@ -20,6 +28,16 @@ impl Image {
         ((y * self.width + x) * 4) as usize
     }
 
+    /// 整幅图像作为一个分块
+    fn tile(&mut self) -> Tile<'_> {
+        Tile {
+            width: self.width,
+            y0: 0,
+            y1: self.height,
+            pixels: &mut self.pixels,
+        }
+    }
+
     pub fn fill_rgba(&mut self, r: u8, g: u8, b: u8, a: u8) {
         for p in self.pixels.chunks_exact_mut(4) {
             p[0] = r;
@@ -47,279 +65,1184 @@ impl Image {
 
 /// 渲染器：根据注解与原始尺寸输出 RGBA 像素
 pub trait Renderer {
-    fn render(&self, frame: &Frame, annotations: &[Annotation]) -> Image;
+    /// 按设备缩放 1.0 渲染（Measure 标签只有物理像素）
+    fn render(&self, frame: &Frame, annotations: &[Annotation]) -> Image {
+        self.render_scaled(frame, annotations, 1.0)
+    }
+
+    /// scale 为截图的 `Screenshot.scale`（物理像素 / 逻辑点），Measure 标签据此换算逻辑点
+    fn render_scaled(&self, frame: &Frame, annotations: &[Annotation], scale: f32) -> Image;
 }
 
+/// CPU 渲染器：画布切成整行分块，用 rayon 并行渲染
+///
+/// 先把每个标注预处理成绘制操作（几何、字形、马赛克块颜色、模糊结果等只算一次），
+/// 再让每个分块按 z 顺序执行与它相交的操作；像素结果与分块方式无关。
 pub struct SimpleRenderer;
 impl Renderer for SimpleRenderer {
-    fn render(&self, frame: &Frame, annotations: &[Annotation]) -> Image {
-        // 复制底图 (BGRA 或 RGBA) 到 Image
-        let mut img = Image::new(frame.width, frame.height);
-        match frame.pixel_format {
-            PixelFormat::Rgba8 => {
-                img.pixels.copy_from_slice(&frame.bytes);
-            }
-            PixelFormat::Bgra8 => {
-                for (dst, chunk) in img
-                    .pixels
-                    .chunks_exact_mut(4)
-                    .zip(frame.bytes.chunks_exact(4))
-                {
-                    dst[0] = chunk[2]; // R
-                    dst[1] = chunk[1]; // G
-                    dst[2] = chunk[0]; // B
-                    dst[3] = chunk[3]; // A
+    fn render_scaled(&self, frame: &Frame, annotations: &[Annotation], scale: f32) -> Image {
+        render_tiled(frame, annotations, scale, TILE_PIXELS)
+    }
+}
+
+/// 每个分块的目标像素数：分块为整行，行数 = TILE_PIXELS / 画布宽度（至少 1 行）
+const TILE_PIXELS: u32 = 1 << 18;
+
+/// 按每块约 tile_pixels 个像素分块渲染
+fn render_tiled(frame: &Frame, annotations: &[Annotation], scale: f32, tile_pixels: u32) -> Image {
+    use rayon::prelude::*;
+
+    // 复制底图 (BGRA 或 RGBA) 到 Image
+    let mut img = Image::new(frame.width, frame.height);
+    match frame.pixel_format {
+        PixelFormat::Rgba8 => {
+            img.pixels.copy_from_slice(&frame.bytes);
+        }
+        PixelFormat::Bgra8 => {
+            img.pixels
+                .par_chunks_mut(4 * 1024)
+                .zip(frame.bytes.par_chunks(4 * 1024))
+                .for_each(|(dst, src)| {
+                    for (dst, chunk) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                        dst[0] = chunk[2]; // R
+                        dst[1] = chunk[1]; // G
+                        dst[2] = chunk[0]; // B
+                        dst[3] = chunk[3]; // A
+                    }
+                });
+        }
+    }
+    // Redact 先作用到底图：之后所有从底图采样的标注（Mosaic / Blur / Magnifier）都拿不到被涂掉的原像素
+    {
+        let mut tile = img.tile();
+        for ann in annotations {
+            if matches!(ann.kind, AnnotationKind::Redact {}) {
+                apply_redact(&mut tile, ann);
+            }
+        }
+    }
+    // 只有采样类标注需要渲染前的底图副本；没有时省掉整幅拷贝
+    let base_pixels = annotations
+        .iter()
+        .any(samples_base)
+        .then(|| img.pixels.clone());
+    let base = Base {
+        pixels: base_pixels.as_deref().unwrap_or(&[]),
+        width: img.width,
+        height: img.height,
+    };
+    let mut anns: Vec<&Annotation> = annotations.iter().collect();
+    anns.sort_by_key(|a| a.meta.z);
+    let ops = prepare_ops(&anns, base, scale);
+
+    let width = img.width;
+    let rows = (tile_pixels / width.max(1)).max(1);
+    let chunk = (rows as usize * width as usize * 4).max(1);
+    img.pixels
+        .par_chunks_mut(chunk)
+        .enumerate()
+        .for_each(|(i, pixels)| {
+            let y0 = i as u32 * rows;
+            let mut tile = Tile {
+                width,
+                y0,
+                y1: y0 + (pixels.len() / (width as usize * 4)) as u32,
+                pixels,
+            };
+            for op in &ops {
+                if op.touches(&tile) {
+                    (op.draw)(&mut tile);
                 }
             }
+        });
+    img
+}
+
+/// 分块渲染的目标：画布中 [y0, y1) 行，与画布同宽
+///
+/// 每个分块持有互不重叠的连续像素切片，可直接交给 rayon 并行写入；
+/// 绘制函数一律使用画布坐标，只处理落在本分块内的像素。
+struct Tile<'a> {
+    width: u32,
+    y0: u32,
+    y1: u32,
+    pixels: &'a mut [u8],
+}
+
+impl Tile<'_> {
+    /// 画布坐标 (x, y) 处的像素，调用方保证 y 在 [y0, y1) 内
+    #[inline]
+    fn px(&mut self, x: u32, y: u32) -> &mut [u8] {
+        let i = ((y - self.y0) as usize * self.width as usize + x as usize) * 4;
+        &mut self.pixels[i..i + 4]
+    }
+
+    /// 画布坐标 (x, y) 起到该行末尾的像素
+    fn row_from(&mut self, x: u32, y: u32) -> &mut [u8] {
+        let row = (y - self.y0) as usize * self.width as usize;
+        &mut self.pixels[(row + x as usize) * 4..(row + self.width as usize) * 4]
+    }
+
+    /// 区域覆盖到的像素范围（向外取整）截断到本分块内：(xs, ys, xe, ye)，半开区间
+    fn span(&self, r: &Rect) -> (i64, i64, i64, i64) {
+        (
+            (r.x.floor() as i64).max(0),
+            (r.y.floor() as i64).max(self.y0 as i64),
+            ((r.x + r.w).ceil() as i64).min(self.width as i64),
+            ((r.y + r.h).ceil() as i64).min(self.y1 as i64),
+        )
+    }
+
+    fn rows(&self) -> std::ops::Range<u32> {
+        self.y0..self.y1
+    }
+}
+
+/// 采样用底图：已涂黑、尚未绘制其他标注的画布像素（没有采样类标注时为空）
+#[derive(Clone, Copy)]
+struct Base<'a> {
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+/// 是否需要从底图采样（马赛克、模糊、放大镜）
+fn samples_base(ann: &Annotation) -> bool {
+    matches!(
+        ann.kind,
+        AnnotationKind::Mosaic { .. }
+            | AnnotationKind::Blur { .. }
+            | AnnotationKind::Magnifier { .. }
+    )
+}
+
+/// 预处理后的绘制操作：与分块无关的计算在构造时完成，`draw` 在每个与 bounds 相交的分块上执行
+struct Op<'a> {
+    /// 画布坐标下可能写入的区域；None 表示整幅画布
+    bounds: Option<Rect>,
+    draw: Box<dyn Fn(&mut Tile) + Send + Sync + 'a>,
+}
+
+impl<'a> Op<'a> {
+    fn new(bounds: Option<Rect>, draw: impl Fn(&mut Tile) + Send + Sync + 'a) -> Self {
+        Self {
+            bounds,
+            draw: Box::new(draw),
         }
-        let base_pixels = img.pixels.clone(); // Mosaic 采样使用原始拷贝
-        let mut anns: Vec<&Annotation> = annotations.iter().collect();
-        anns.sort_by_key(|a| a.meta.z);
-        for ann in anns {
-            match &ann.kind {
-                AnnotationKind::Rect { .. } => {
-                    let meta = &ann.meta;
-                    let opacity = meta.opacity.clamp(0.0, 1.0);
-                    if let Some(fill) = &meta.fill_color {
-                        if let Some((r, g, b)) = parse_hex_color(fill) {
-                            blend_fill_rect(
-                                &mut img,
-                                meta.x as i32,
-                                meta.y as i32,
-                                meta.w as i32,
-                                meta.h as i32,
-                                r,
-                                g,
-                                b,
-                                (255.0 * opacity) as u8,
+    }
+
+    /// 分块为整行，只需比较纵向范围；按像素向外取整，与各绘制函数的遍历范围一致
+    fn touches(&self, tile: &Tile) -> bool {
+        self.bounds.is_none_or(|b| {
+            (b.y.floor() as i64) < tile.y1 as i64 && ((b.y + b.h).ceil() as i64) > tile.y0 as i64
+        })
+    }
+}
+
+/// 局部区域按标注旋转后在画布上的包围盒
+fn canvas_rect(rot: Option<&Rotation>, local: Rect) -> Rect {
+    match rot {
+        Some(rot) => Rect::bounding(local.corners().map(|p| rot.forward(p))).unwrap_or(local),
+        None => local,
+    }
+}
+
+/// 描边部件覆盖到的画布区域（含 1px 抗锯齿边缘）
+fn parts_bounds(parts: &[Part]) -> Option<Rect> {
+    Rect::bounding(
+        parts
+            .iter()
+            .filter_map(Part::area)
+            .flat_map(|r| r.corners()),
+    )
+    .map(|r| r.inflate(1.0))
+}
+
+/// 按 z 顺序把标注预处理成绘制操作
+fn prepare_ops<'a>(anns: &[&'a Annotation], base: Base<'a>, scale: f32) -> Vec<Op<'a>> {
+    // 所有聚光灯在 z 最低者的位置一次性压暗，亮区取并集
+    let spotlights: Vec<&Annotation> = anns
+        .iter()
+        .copied()
+        .filter(|a| matches!(a.kind, AnnotationKind::Spotlight { .. }))
+        .collect();
+    let mut spotlights_done = false;
+    let mut ops = Vec::with_capacity(anns.len());
+    for &ann in anns {
+        match &ann.kind {
+            AnnotationKind::Rect { .. } => {
+                // 按像素覆盖率抗锯齿，x/y/w/h 的小数部分不再被截断；描边沿边界向内绘制，
+                // 圆角时内边界为同心圆角（半径减去线宽）
+                let meta = &ann.meta;
+                let local = ann.local_bounds();
+                let radius = ann.corner_radius();
+                let rot = Rotation::of(ann);
+                let opacity = meta.opacity.clamp(0.0, 1.0);
+                let bounds = canvas_rect(rot.as_ref(), local.inflate(1.0));
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    if let Some(c) = meta.fill_color {
+                        let a = c.alpha_with(opacity) as f32;
+                        fill_coverage(
+                            tile,
+                            rot.as_ref(),
+                            local,
+                            |x, y| rounded_rect_coverage(&local, radius, x, y),
+                            |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
+                        );
+                    }
+                    if let (Some(width), Some(c)) = (meta.stroke_width, meta.stroke_color) {
+                        if width > 0.0 {
+                            let a = c.alpha_with(opacity) as f32;
+                            let inner = local.inflate(-width);
+                            let inner_radius = (radius - width).max(0.0);
+                            fill_coverage(
+                                tile,
+                                rot.as_ref(),
+                                local,
+                                |x, y| {
+                                    let hole = rounded_rect_coverage(&inner, inner_radius, x, y);
+                                    (rounded_rect_coverage(&local, radius, x, y) - hole).max(0.0)
+                                },
+                                |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
                             );
                         }
                     }
-                    if let Some(width) = meta.stroke_width {
-                        if let Some(stroke_color) = &meta.stroke_color {
-                            if let Some((r, g, b)) = parse_hex_color(stroke_color) {
-                                stroke_rect(
-                                    &mut img,
-                                    meta.x as i32,
-                                    meta.y as i32,
-                                    meta.w as i32,
-                                    meta.h as i32,
-                                    width,
-                                    r,
-                                    g,
-                                    b,
-                                    (255.0 * opacity) as u8,
-                                );
-                            }
+                }));
+            }
+            AnnotationKind::Highlight { mode, .. } => {
+                let meta = &ann.meta;
+                let Some(c) = meta.fill_color else {
+                    continue;
+                };
+                let blend_mode = match mode {
+                    BlendMode::Multiply => Blend::Multiply,
+                    BlendMode::Screen => Blend::Screen,
+                };
+                let a = c.alpha_with(meta.opacity) as f32;
+                let local = ann.local_bounds();
+                let radius = ann.corner_radius();
+                let rot = Rotation::of(ann);
+                let bounds = canvas_rect(rot.as_ref(), local.inflate(1.0));
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    fill_coverage(
+                        tile,
+                        rot.as_ref(),
+                        local,
+                        |x, y| rounded_rect_coverage(&local, radius, x, y),
+                        |dst, cov| {
+                            let alpha = (a * cov).round() as u8;
+                            blend_pixel_mode(dst, (c.r, c.g, c.b, alpha), blend_mode)
+                        },
+                    );
+                }));
+            }
+            AnnotationKind::Arrow { line_style, .. } => {
+                let Some(geo) = ann.arrow_geometry() else {
+                    continue;
+                };
+                let m = &ann.meta;
+                let rot = Rotation::of(ann);
+                let fwd = |p: (f32, f32)| rot.as_ref().map_or(p, |r| r.forward(p));
+                let path: Vec<_> = geo.path.iter().map(|p| fwd(*p)).collect();
+                let width = m.stroke_width.unwrap_or(2.0).max(1.0);
+                let c = m.stroke_color.unwrap_or(Color::WHITE);
+                let color = (c.r, c.g, c.b, c.alpha_with(m.opacity));
+                let stroke = Stroke::round(width);
+                let mut parts = Vec::new();
+                match line_style {
+                    LineStyle::Solid => stroke.outline(&path, false, &mut parts),
+                    // dash = 4 * 线宽，gap = 2 * 线宽，图案沿曲线连续
+                    LineStyle::Dashed => {
+                        for dash in dash_path(&path, 4.0 * width, 2.0 * width) {
+                            stroke.outline(&dash, false, &mut parts);
                         }
                     }
                 }
-                AnnotationKind::Highlight { mode } => {
-                    let meta = &ann.meta;
-                    if let Some(fill) = &meta.fill_color {
-                        if let Some((r, g, b)) = parse_hex_color(fill) {
-                            let blend_mode = match mode {
-                                BlendMode::Multiply => Blend::Multiply,
-                                BlendMode::Screen => Blend::Screen,
-                            };
-                            highlight_rect(
-                                &mut img,
-                                meta.x as i32,
-                                meta.y as i32,
-                                meta.w as i32,
-                                meta.h as i32,
-                                r,
-                                g,
-                                b,
-                                (255.0 * meta.opacity.clamp(0.0, 1.0)) as u8,
-                                blend_mode,
-                            );
+                // 头部始终为实线
+                for head in geo.heads {
+                    match head {
+                        ArrowHead::Triangle(tri) => {
+                            parts.push(Part::Convex(SmallVec::from_slice(&tri.map(fwd))));
+                        }
+                        ArrowHead::OpenV([tip, a, b]) => {
+                            stroke.outline(&[fwd(a), fwd(tip), fwd(b)], false, &mut parts);
+                        }
+                        ArrowHead::Circle { center, radius } => {
+                            parts.push(Part::Disc(fwd(center), radius));
+                        }
+                        ArrowHead::Bar(a, b) => {
+                            stroke.outline(&[fwd(a), fwd(b)], false, &mut parts)
+                        }
+                    }
+                }
+                ops.push(stroke_op(parts, color));
+            }
+            AnnotationKind::Mosaic { level, .. } => {
+                // 圆角时按圆角矩形覆盖率与原图混合，角外像素保持原样
+                let m = &ann.meta;
+                let block = mosaic_block_size(*level).max(1) as f32;
+                let radius = ann.corner_radius();
+                let rot = Rotation::of(ann);
+                let (region, grid) = match rot {
+                    Some(_) => {
+                        let local = Rect {
+                            x: m.x,
+                            y: m.y,
+                            w: m.w,
+                            h: m.h,
+                        };
+                        (local, local)
+                    }
+                    // 轴对齐时区域截断到整像素，分块从图像内的左上角开始
+                    None => {
+                        let region = Rect {
+                            x: (m.x as i32) as f32,
+                            y: (m.y as i32) as f32,
+                            w: (m.w as i32) as f32,
+                            h: (m.h as i32) as f32,
+                        };
+                        let x0 = region.x.max(0.0);
+                        let y0 = region.y.max(0.0);
+                        let x1 = (region.x + region.w).min(base.width as f32);
+                        let y1 = (region.y + region.h).min(base.height as f32);
+                        let grid = Rect {
+                            x: x0,
+                            y: y0,
+                            w: x1 - x0,
+                            h: y1 - y0,
+                        };
+                        (region, grid)
+                    }
+                };
+                if grid.w <= 0.0 || grid.h <= 0.0 {
+                    continue;
+                }
+                let blocks = MosaicBlocks::new(base, rot.as_ref(), grid, block);
+                let bounds = match rot {
+                    Some(_) => canvas_rect(rot.as_ref(), region),
+                    None => grid,
+                };
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    apply_mosaic(tile, &blocks, rot.as_ref(), region, radius)
+                }));
+            }
+            AnnotationKind::Blur { radius } => {
+                let m = &ann.meta;
+                let blurred = match Rotation::of(ann) {
+                    Some(rot) => {
+                        let local = Rect {
+                            x: m.x,
+                            y: m.y,
+                            w: m.w,
+                            h: m.h,
+                        };
+                        BlurredRegion::rotated(base, &rot, local, *radius).map(|blurred| {
+                            Op::new(Some(blurred.bounds()), move |tile| {
+                                apply_blur_rotated(tile, &blurred, &rot, local)
+                            })
+                        })
+                    }
+                    None => BlurredRegion::new(
+                        base, m.x as i32, m.y as i32, m.w as i32, m.h as i32, *radius,
+                    )
+                    .map(|blurred| {
+                        Op::new(Some(blurred.bounds()), move |tile| {
+                            apply_blur(tile, &blurred)
+                        })
+                    }),
+                };
+                ops.extend(blurred);
+            }
+            AnnotationKind::Magnifier { .. } => {
+                let bounds = canvas_rect(Rotation::of(ann).as_ref(), ann.local_bounds());
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    apply_magnifier(tile, base, ann)
+                }));
+            }
+            AnnotationKind::Image { source } => match source {
+                ImageSource::Embedded { png } => {
+                    let local = ann.local_bounds();
+                    if local.w <= 0.0 || local.h <= 0.0 {
+                        continue;
+                    }
+                    let decoded = match decode_png_rgba(png) {
+                        Ok(decoded) => decoded,
+                        Err(e) => {
+                            tracing::warn!("image annotation {} not drawn: {e:#}", ann.meta.id);
+                            continue;
+                        }
+                    };
+                    if decoded.0 == 0 || decoded.1 == 0 {
+                        continue;
+                    }
+                    let bounds = canvas_rect(Rotation::of(ann).as_ref(), local);
+                    ops.push(Op::new(Some(bounds), move |tile| {
+                        apply_image(tile, ann, &decoded)
+                    }));
+                }
+                // 资源引用需先经 `ImageAssets::embed` 解析，未解析时跳过
+                ImageSource::Asset { id } => {
+                    tracing::debug!("image asset {id} not embedded, skipped");
+                }
+            },
+            AnnotationKind::Spotlight { .. } => {
+                if !spotlights_done {
+                    spotlights_done = true;
+                    if let Some(spots) = Spotlights::new(&spotlights) {
+                        ops.push(Op::new(None, move |tile| apply_spotlights(tile, &spots)));
+                    }
+                }
+            }
+            AnnotationKind::Redact {} => {
+                // 按 z 顺序再覆盖一次，盖住更低层的标注
+                let local = ann.local_bounds();
+                let bounds = match Rotation::of(ann) {
+                    Some(rot) => {
+                        canvas_rect(Some(&rot), local.inflate(std::f32::consts::FRAC_1_SQRT_2))
+                    }
+                    None => local,
+                };
+                ops.push(Op::new(Some(bounds), move |tile| apply_redact(tile, ann)));
+            }
+            AnnotationKind::Freehand {
+                points,
+                smoothing,
+                cap,
+                join,
+            } => {
+                if points.len() < 2 {
+                    continue;
+                }
+                let m = &ann.meta;
+                let c = m.stroke_color.unwrap_or(Color::WHITE);
+                let pts = rotated_points(ann, &smooth_points(points, *smoothing));
+                let stroke = Stroke {
+                    width: m.stroke_width.unwrap_or(2.0).max(1.0),
+                    cap: *cap,
+                    join: *join,
+                };
+                let color = (c.r, c.g, c.b, c.alpha_with(m.opacity));
+                ops.push(stroke_op(stroke.parts(&pts, false), color));
+            }
+            AnnotationKind::Highlighter { points, smoothing } => {
+                if points.is_empty() {
+                    continue;
+                }
+                let m = &ann.meta;
+                let pts = rotated_points(ann, &smooth_points(points, *smoothing));
+                let parts = Stroke::round(ann.stroke_half_width() * 2.0).parts(&pts, false);
+                let c = m.stroke_color.unwrap_or(HIGHLIGHTER_COLOR);
+                let a = c.alpha_with(m.opacity) as f32;
+                // 整笔覆盖率取最大值后只做一次 Multiply，折返与自交处不会叠加变深
+                ops.push(Op::new(parts_bounds(&parts), move |tile| {
+                    stroke_coverage(tile, &parts, |dst, cov| {
+                        let alpha = (a * cov).round() as u8;
+                        blend_pixel_mode(dst, (c.r, c.g, c.b, alpha), Blend::Multiply)
+                    });
+                }));
+            }
+            AnnotationKind::Polygon { points, join } => {
+                if points.len() < 3 {
+                    continue;
+                }
+                let m = &ann.meta;
+                let pts = rotated_points(ann, points);
+                let opacity = m.opacity.clamp(0.0, 1.0);
+                if let Some(c) = m.fill_color {
+                    let color = (c.r, c.g, c.b, c.alpha_with(opacity));
+                    let bounds = Rect::bounding(pts.iter().copied()).map(|b| b.inflate(1.0));
+                    let pts = pts.clone();
+                    ops.push(Op::new(bounds, move |tile| fill_polygon(tile, &pts, color)));
+                }
+                if let (Some(width), Some(c)) = (m.stroke_width, m.stroke_color) {
+                    if width > 0.0 {
+                        let stroke = Stroke {
+                            width,
+                            cap: LineCap::Butt,
+                            join: *join,
+                        };
+                        let color = (c.r, c.g, c.b, c.alpha_with(opacity));
+                        ops.push(stroke_op(stroke.parts(&pts, true), color));
+                    }
+                }
+            }
+            AnnotationKind::Polyline {
+                points,
+                line_style,
+                start_arrow,
+                end_arrow,
+                head_size,
+                cap,
+                join,
+            } => {
+                let n = points.len();
+                if n < 2 {
+                    continue;
+                }
+                let m = &ann.meta;
+                let pts = rotated_points(ann, points);
+                let width = m.stroke_width.unwrap_or(2.0).max(1.0);
+                let c = m.stroke_color.unwrap_or(Color::WHITE);
+                let color = (c.r, c.g, c.b, c.alpha_with(m.opacity));
+                let stroke = Stroke {
+                    width,
+                    cap: *cap,
+                    join: *join,
+                };
+                let mut parts = Vec::new();
+                match line_style {
+                    LineStyle::Solid => stroke.outline(&pts, false, &mut parts),
+                    // 与 Line 虚线一致：dash = 4 * 线宽，gap = 2 * 线宽，图案跨折点连续，每段 dash 两端都有线端
+                    LineStyle::Dashed => {
+                        for dash in dash_path(&pts, 4.0 * width, 2.0 * width) {
+                            stroke.outline(&dash, false, &mut parts);
                         }
                     }
                 }
-                AnnotationKind::Arrow {
-                    head_size,
-                    line_style,
-                } => {
-                    let m = &ann.meta;
-                    let (x1, y1) = (m.x as i32, m.y as i32);
-                    let (x2, y2) = ((m.x + m.w) as i32, (m.y + m.h) as i32);
-                    let width_px = m.stroke_width.unwrap_or(2.0).max(1.0) as i32;
-                    let color = m
-                        .stroke_color
-                        .as_ref()
-                        .and_then(|c| parse_hex_color(c))
-                        .unwrap_or((255, 255, 255));
-                    if let LineStyle::Dashed = line_style {
-                        draw_dashed_line(
-                            &mut img,
-                            x1,
-                            y1,
-                            x2,
-                            y2,
-                            width_px,
-                            color,
-                            (255.0 * m.opacity.clamp(0.0, 1.0)) as u8,
+                let hs = *head_size as f32;
+                parts.extend(
+                    [
+                        (*start_arrow, pts[1], pts[0]),
+                        (*end_arrow, pts[n - 2], pts[n - 1]),
+                    ]
+                    .into_iter()
+                    .filter(|(on, ..)| *on)
+                    .filter_map(|(_, from, tip)| arrow_head(from, tip, hs))
+                    .map(|tri| Part::Convex(SmallVec::from_slice(&tri))),
+                );
+                ops.push(stroke_op(parts, color));
+            }
+            AnnotationKind::Text {
+                content,
+                font_family,
+                font_size,
+            } => {
+                let m = &ann.meta;
+                let Some(bitmap) = text::rasterize_text(content, font_family, *font_size as f32)
+                else {
+                    continue;
+                };
+                let c = m.fill_color.or(m.stroke_color).unwrap_or(Color::WHITE);
+                let (r, g, b) = (c.r, c.g, c.b);
+                let a = c.alpha_with(m.opacity) as u32;
+                // 位图左上角对齐到整像素，避免字形被重采样发虚
+                let (ox, oy) = (m.x.round(), m.y.round());
+                let area = Rect {
+                    x: ox,
+                    y: oy,
+                    w: bitmap.width as f32,
+                    h: bitmap.height as f32,
+                };
+                let rot = Rotation::of(ann);
+                let bounds = canvas_rect(rot.as_ref(), area);
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    let put = |tile: &mut Tile, x: u32, y: u32, bx: usize, by: usize| {
+                        let cov = bitmap.at(bx, by) as u32;
+                        if cov > 0 {
+                            blend_over(tile.px(x, y), r, g, b, (a * cov / 255) as u8);
+                        }
+                    };
+                    if let Some(rot) = rot.as_ref() {
+                        rot.for_each_pixel(tile.width, tile.rows(), area, |x, y, lx, ly| {
+                            put(tile, x, y, (lx - ox) as usize, (ly - oy) as usize)
+                        });
+                        return;
+                    }
+                    let (xs, ys, xe, ye) = tile.span(&area);
+                    for y in ys..ye {
+                        for x in xs..xe {
+                            let (bx, by) = ((x - ox as i64) as usize, (y - oy as i64) as usize);
+                            put(tile, x as u32, y as u32, bx, by);
+                        }
+                    }
+                }));
+            }
+            AnnotationKind::Ellipse {} => {
+                let m = &ann.meta;
+                let local = ann.local_bounds();
+                let rot = Rotation::of(ann);
+                let opacity = m.opacity.clamp(0.0, 1.0);
+                let bounds = canvas_rect(rot.as_ref(), local.inflate(1.0));
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    if let Some(c) = m.fill_color {
+                        let a = c.alpha_with(opacity) as f32;
+                        fill_coverage(
+                            tile,
+                            rot.as_ref(),
+                            local,
+                            |x, y| ellipse_coverage(&local, x, y),
+                            |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
                         );
+                    }
+                    if let (Some(width), Some(c)) = (m.stroke_width, m.stroke_color) {
+                        if width > 0.0 {
+                            let a = c.alpha_with(opacity) as f32;
+                            let inner = local.inflate(-width);
+                            fill_coverage(
+                                tile,
+                                rot.as_ref(),
+                                local,
+                                |x, y| {
+                                    (ellipse_coverage(&local, x, y)
+                                        - ellipse_coverage(&inner, x, y))
+                                    .max(0.0)
+                                },
+                                |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
+                            );
+                        }
+                    }
+                }));
+            }
+            AnnotationKind::StepMarker { number } => {
+                let m = &ann.meta;
+                let bounds = ann.local_bounds();
+                let d = bounds.w.min(bounds.h);
+                if d <= 0.0 {
+                    continue;
+                }
+                let (cx, cy) = bounds.center();
+                let circle = Rect {
+                    x: cx - d / 2.0,
+                    y: cy - d / 2.0,
+                    w: d,
+                    h: d,
+                };
+                let rot = Rotation::of(ann);
+                let opacity = m.opacity.clamp(0.0, 1.0);
+                let fill = m.fill_color.unwrap_or(STEP_MARKER_FILL);
+                let label = step_label(&number.to_string(), circle);
+                let bounds = canvas_rect(rot.as_ref(), circle.inflate(1.0));
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    let a = fill.alpha_with(opacity) as f32;
+                    fill_coverage(
+                        tile,
+                        rot.as_ref(),
+                        circle,
+                        |x, y| ellipse_coverage(&circle, x, y),
+                        |dst, cov| blend_over(dst, fill.r, fill.g, fill.b, (a * cov).round() as u8),
+                    );
+                    if let (Some(width), Some(c)) = (m.stroke_width, m.stroke_color) {
+                        if width > 0.0 {
+                            let a = c.alpha_with(opacity) as f32;
+                            let inner = circle.inflate(-width);
+                            fill_coverage(
+                                tile,
+                                rot.as_ref(),
+                                circle,
+                                |x, y| {
+                                    (ellipse_coverage(&circle, x, y)
+                                        - ellipse_coverage(&inner, x, y))
+                                    .max(0.0)
+                                },
+                                |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
+                            );
+                        }
+                    }
+                    // 编号颜色按填充亮度取黑或白，保证对比度
+                    let ink = if luminance(fill) > 150.0 {
+                        Color::BLACK
                     } else {
-                        draw_thick_line(
-                            &mut img,
-                            x1,
-                            y1,
-                            x2,
-                            y2,
-                            width_px,
-                            color,
-                            (255.0 * m.opacity.clamp(0.0, 1.0)) as u8,
-                        );
+                        Color::WHITE
+                    };
+                    if let Some((bitmap, origin)) = &label {
+                        fill_text(tile, rot.as_ref(), bitmap, *origin, circle, ink, opacity);
                     }
-                    let hs = *head_size as f32;
-                    draw_arrow_head(
-                        &mut img,
-                        x1,
-                        y1,
-                        x2,
-                        y2,
-                        hs,
-                        color,
-                        (255.0 * m.opacity.clamp(0.0, 1.0)) as u8,
+                }));
+            }
+            AnnotationKind::Callout {
+                content, font_size, ..
+            } => {
+                let Some(shape) = ann.callout_shape() else {
+                    continue;
+                };
+                let m = &ann.meta;
+                let outer = ann.local_bounds();
+                let rot = Rotation::of(ann);
+                let opacity = m.opacity.clamp(0.0, 1.0);
+                let fill = m.fill_color.unwrap_or(Color::WHITE);
+                let border = match (m.stroke_width, m.stroke_color) {
+                    (Some(width), Some(c)) if width > 0.0 => Some((width, c)),
+                    _ => None,
+                };
+                // 文字在框体内缩描边宽度 + 半个字号的区域内折行，整体居中，左上角对齐到整像素
+                let size = *font_size as f32;
+                let inner = shape
+                    .body
+                    .inflate(-(border.map_or(0.0, |(width, _)| width) + size * 0.5));
+                let text = text::rasterize_block(content, "", size, inner.w, inner.h).map(|b| {
+                    let origin = (
+                        (inner.x + (inner.w - b.width as f32) / 2.0).round(),
+                        (inner.y + (inner.h - b.height as f32) / 2.0).round(),
                     );
-                }
-                AnnotationKind::Mosaic { level } => {
-                    let m = &ann.meta;
-                    let block = mosaic_block_size(*level);
-                    apply_mosaic(
-                        &mut img,
-                        &base_pixels,
-                        m.x as i32,
-                        m.y as i32,
-                        m.w as i32,
-                        m.h as i32,
-                        block,
+                    (b, origin)
+                });
+                // 框体与尾巴作为一个整体描边：描边覆盖率 = 轮廓覆盖率 - 向内腐蚀 width 后的轮廓覆盖率，
+                // 尾巴与框体相接处不会出现分隔线
+                let outline = CalloutCoverage::new(&shape, 0.0);
+                let eroded = border.map(|(width, _)| CalloutCoverage::new(&shape, width));
+                let bounds = canvas_rect(rot.as_ref(), outer.inflate(1.0));
+                ops.push(Op::new(Some(bounds), move |tile| {
+                    let a = fill.alpha_with(opacity) as f32;
+                    fill_coverage(
+                        tile,
+                        rot.as_ref(),
+                        outer,
+                        |x, y| outline.at(x, y),
+                        |dst, cov| blend_over(dst, fill.r, fill.g, fill.b, (a * cov).round() as u8),
                     );
-                }
-                AnnotationKind::Freehand { points, smoothing } => {
-                    if points.len() < 2 {
-                        continue;
+                    if let (Some((_, c)), Some(eroded)) = (border, &eroded) {
+                        let a = c.alpha_with(opacity) as f32;
+                        fill_coverage(
+                            tile,
+                            rot.as_ref(),
+                            outer,
+                            |x, y| (outline.at(x, y) - eroded.at(x, y)).max(0.0),
+                            |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
+                        );
                     }
-                    let m = &ann.meta;
-                    let (r, g, b) = m
-                        .stroke_color
-                        .as_ref()
-                        .and_then(|c| parse_hex_color(c))
-                        .unwrap_or((255, 255, 255));
-                    let a = (255.0 * m.opacity.clamp(0.0, 1.0)) as u8;
-                    let mut pts: Vec<(f32, f32)> = points.clone();
-                    // Chaikin smoothing passes based on smoothing factor (0..1) -> up to 3 passes
-                    let passes = if *smoothing <= 0.0 {
-                        0
-                    } else if *smoothing < 0.34 {
-                        1
-                    } else if *smoothing < 0.67 {
-                        2
+                    let ink = if luminance(fill) > 150.0 {
+                        Color::BLACK
                     } else {
-                        3
+                        Color::WHITE
                     };
-                    for _ in 0..passes {
-                        pts = chaikin(&pts);
-                        if pts.len() > 4096 {
-                            break;
-                        }
+                    if let Some((bitmap, origin)) = &text {
+                        fill_text(tile, rot.as_ref(), bitmap, *origin, inner, ink, opacity);
                     }
-                    let width_px = m.stroke_width.unwrap_or(2.0).max(1.0) as i32;
-                    // 依据 stroke_color + stroke_width; 如果设置 dashed 则依据每段长度绘制
-                    for w in pts.windows(2) {
-                        let (x0, y0) = (w[0].0 as i32, w[0].1 as i32);
-                        let (x1, y1) = (w[1].0 as i32, w[1].1 as i32);
-                        // 目前 Freehand 仅支持实线 (后续可基于 meta / 额外字段扩展虚线)
-                        draw_thick_line(&mut img, x0, y0, x1, y1, width_px, (r, g, b), a);
+                }));
+            }
+            AnnotationKind::Measure { .. } => {
+                let m = &ann.meta;
+                let dims = ann.dimensions();
+                let rot = Rotation::of(ann);
+                let fwd = |p: (f32, f32)| rot.as_ref().map_or(p, |r| r.forward(p));
+                let width = m.stroke_width.unwrap_or(1.0).max(1.0);
+                let c = m.stroke_color.unwrap_or(MEASURE_COLOR);
+                let a = c.alpha_with(m.opacity);
+                // 尺寸线 + 两端垂直短线
+                let stroke = Stroke::round(width);
+                let mut parts = Vec::new();
+                for d in &dims {
+                    let (ux, uy) = (
+                        (d.to.0 - d.from.0) / d.length,
+                        (d.to.1 - d.from.1) / d.length,
+                    );
+                    let (nx, ny) = (-uy * Dimension::TICK / 2.0, ux * Dimension::TICK / 2.0);
+                    stroke.outline(&[fwd(d.from), fwd(d.to)], false, &mut parts);
+                    for (ex, ey) in [d.from, d.to] {
+                        let tick = [fwd((ex - nx, ey - ny)), fwd((ex + nx, ey + ny))];
+                        stroke.outline(&tick, false, &mut parts);
                     }
                 }
-                AnnotationKind::Text {
-                    content,
-                    font_family: _,
-                    font_size,
-                } => {
-                    // 初版占位实现：按固定宽度网格填充字符块，后续引入 fontdue 栅格真正字形
-                    let m = &ann.meta;
-                    let opacity = m.opacity.clamp(0.0, 1.0);
-                    let (r, g, b) = m
-                        .fill_color
-                        .as_ref()
-                        .and_then(|c| parse_hex_color(c))
-                        .or_else(|| m.stroke_color.as_ref().and_then(|c| parse_hex_color(c)))
-                        .unwrap_or((255, 255, 255));
-                    let cell_w = ((*font_size as f32) * 0.6).ceil() as i32; // 粗略宽度
-                    let cell_h = *font_size as i32;
-                    for (i, _ch) in content.chars().enumerate() {
-                        let x = m.x as i32 + i as i32 * cell_w;
-                        let y = m.y as i32;
-                        blend_fill_rect(
-                            &mut img,
-                            x,
-                            y,
-                            cell_w.max(1),
-                            cell_h.max(1),
-                            r,
-                            g,
-                            b,
-                            (255.0 * opacity) as u8,
-                        );
+                ops.push(stroke_op(parts, (c.r, c.g, c.b, a)));
+                // 标签居中压在尺寸线中点上：线条颜色底，文字按底色亮度取黑或白
+                let ink = if luminance(c) > 150.0 {
+                    Color::BLACK
+                } else {
+                    Color::WHITE
+                };
+                let opacity = m.opacity;
+                for d in dims {
+                    let Some(bitmap) =
+                        text::rasterize_text(&d.label(scale), "", MEASURE_LABEL_SIZE)
+                    else {
+                        continue;
+                    };
+                    // 标签左上角取整到像素，避免字形被重采样发虚
+                    let mid = ((d.from.0 + d.to.0) / 2.0, (d.from.1 + d.to.1) / 2.0);
+                    let (w, h) = (bitmap.width as f32, bitmap.height as f32);
+                    let origin = ((mid.0 - w / 2.0).round(), (mid.1 - h / 2.0).round());
+                    let pill = Rect {
+                        x: origin.0,
+                        y: origin.1,
+                        w,
+                        h,
                     }
+                    .inflate(MEASURE_LABEL_PAD);
+                    let bounds = canvas_rect(rot.as_ref(), pill.inflate(1.0));
+                    ops.push(Op::new(Some(bounds), move |tile| {
+                        fill_coverage(
+                            tile,
+                            rot.as_ref(),
+                            pill,
+                            |x, y| rect_coverage(&pill, x, y),
+                            |dst, cov| {
+                                blend_over(dst, c.r, c.g, c.b, (a as f32 * cov).round() as u8)
+                            },
+                        );
+                        fill_text(tile, rot.as_ref(), &bitmap, origin, pill, ink, opacity);
+                    }));
+                }
+            }
+            AnnotationKind::Line { line_style, cap } => {
+                let m = &ann.meta;
+                let (mut p1, mut p2) = ((m.x, m.y), (m.x + m.w, m.y + m.h));
+                if let Some(rot) = Rotation::of(ann) {
+                    p1 = rot.forward(p1);
+                    p2 = rot.forward(p2);
                 }
+                let width = m.stroke_width.unwrap_or(2.0).max(1.0);
+                let c = m.stroke_color.unwrap_or(Color::WHITE);
+                let color = (c.r, c.g, c.b, c.alpha_with(m.opacity));
+                let stroke = Stroke {
+                    width,
+                    cap: *cap,
+                    join: LineJoin::Round,
+                };
+                let parts = match line_style {
+                    LineStyle::Solid => stroke.parts(&[p1, p2], false),
+                    // 与 Arrow 虚线一致：dash = 4 * 线宽，gap = 2 * 线宽，每段 dash 两端都有线端
+                    LineStyle::Dashed => dash_path(&[p1, p2], 4.0 * width, 2.0 * width)
+                        .iter()
+                        .flat_map(|dash| stroke.parts(dash, false))
+                        .collect(),
+                };
+                ops.push(stroke_op(parts, color));
             }
         }
-        img
     }
+    ops
 }
 
-fn parse_hex_color(s: &str) -> Option<(u8, u8, u8)> {
-    let ss = s.strip_prefix('#').unwrap_or(s);
-    if ss.len() == 6 {
-        u32::from_str_radix(ss, 16).ok().map(|v| {
-            (
-                ((v >> 16) & 0xFF) as u8,
-                ((v >> 8) & 0xFF) as u8,
-                (v & 0xFF) as u8,
-            )
-        })
+/// 抗锯齿描边操作：部件在构造时已转换到画布坐标
+fn stroke_op<'a>(parts: Vec<Part>, color: (u8, u8, u8, u8)) -> Op<'a> {
+    Op::new(parts_bounds(&parts), move |tile| {
+        stroke_aa(tile, &parts, color)
+    })
+}
+
+/// 放大镜缺省边框宽度
+const MAGNIFIER_BORDER: f32 = 2.0;
+
+/// 步骤标记未设置填充色时的默认底色
+const STEP_MARKER_FILL: Color = Color::rgb(0xE5, 0x39, 0x35);
+
+/// 荧光笔未设置描边色时的默认颜色
+const HIGHLIGHTER_COLOR: Color = Color::rgb(0xFF, 0xEB, 0x3B);
+
+/// 测量标注未设置描边色时的默认颜色
+const MEASURE_COLOR: Color = Color::rgb(0xFF, 0x2D, 0x55);
+
+/// 测量标签的字号与标签底色的留白
+const MEASURE_LABEL_SIZE: f32 = 11.0;
+const MEASURE_LABEL_PAD: f32 = 2.0;
+
+fn luminance(c: Color) -> f32 {
+    0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32
+}
+
+/// 居中于 circle 的步骤编号：字号取直径的 0.6（数字高约为直径一半），墨迹宽度超过直径 70% 时等比缩小；
+/// 按墨迹包围盒居中，返回位图与左上角（局部坐标，取整到像素）
+fn step_label(digits: &str, circle: Rect) -> Option<(text::TextBitmap, (f32, f32))> {
+    let max_w = circle.w * 0.7;
+    let mut size = circle.w * 0.6;
+    let mut bitmap = text::rasterize_text(digits, "", size)?;
+    let (_, _, w, _) = bitmap.ink_bounds()?;
+    if w as f32 > max_w {
+        size *= max_w / w as f32;
+        bitmap = text::rasterize_text(digits, "", size)?;
+    }
+    let (x, y, w, h) = bitmap.ink_bounds()?;
+    let (cx, cy) = circle.center();
+    let origin = (
+        (cx - x as f32 - w as f32 / 2.0).round(),
+        (cy - y as f32 - h as f32 / 2.0).round(),
+    );
+    Some((bitmap, origin))
+}
+
+/// Callout 轮廓（圆角框体 ∪ 尾巴）的覆盖率：框体与尾巴各取覆盖率后取最大值
+struct CalloutCoverage {
+    body: Rect,
+    radius: f32,
+    tail: Option<Part>,
+}
+
+impl CalloutCoverage {
+    /// inset > 0 时为轮廓向内腐蚀 inset 后的形状：框体内缩、圆角半径减小；尾巴先沿两腰延伸进框体，
+    /// 再各边内移 inset，使相接处的腐蚀结果连成一体
+    fn new(shape: &CalloutShape, inset: f32) -> Self {
+        let tail = shape.tail.and_then(|[a, b, tip]| {
+            if inset <= 0.0 {
+                return Some([a, b, tip]);
+            }
+            let base = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+            if base <= 0.0 {
+                return None;
+            }
+            let height = triangle_area2([a, b, tip]) / base;
+            if height <= 0.0 {
+                return None;
+            }
+            let k = (height + inset + 1.0) / height;
+            let extend = |p: (f32, f32)| (tip.0 + (p.0 - tip.0) * k, tip.1 + (p.1 - tip.1) * k);
+            inset_triangle([extend(a), extend(b), tip], inset)
+        });
+        CalloutCoverage {
+            body: shape.body.inflate(-inset),
+            radius: (shape.radius - inset).max(0.0),
+            tail: tail.map(|t| Part::Convex(SmallVec::from_slice(&t))),
+        }
+    }
+
+    fn at(&self, x: f32, y: f32) -> f32 {
+        let body = rounded_rect_coverage(&self.body, self.radius, x, y);
+        self.tail
+            .as_ref()
+            .map_or(body, |t| body.max(t.coverage((x, y))))
+    }
+}
+
+/// 三角形面积的两倍
+fn triangle_area2([a, b, c]: [(f32, f32); 3]) -> f32 {
+    ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs()
+}
+
+/// 三角形各边向内平移 d：结果与原三角形以内心为中心位似；d 不小于内切圆半径时为空
+fn inset_triangle(t: [(f32, f32); 3], d: f32) -> Option<[(f32, f32); 3]> {
+    // side[i] 为顶点 i 的对边长
+    let side = |i: usize| {
+        let (p, q) = (t[(i + 1) % 3], t[(i + 2) % 3]);
+        ((p.0 - q.0).powi(2) + (p.1 - q.1).powi(2)).sqrt()
+    };
+    let sides = [side(0), side(1), side(2)];
+    let perimeter: f32 = sides.iter().sum();
+    if perimeter <= 0.0 {
+        return None;
+    }
+    let inradius = triangle_area2(t) / perimeter;
+    if d >= inradius {
+        return None;
+    }
+    let center = (
+        (0..3).map(|i| sides[i] * t[i].0).sum::<f32>() / perimeter,
+        (0..3).map(|i| sides[i] * t[i].1).sum::<f32>() / perimeter,
+    );
+    let k = 1.0 - d / inradius;
+    Some(t.map(|p| {
+        (
+            center.0 + (p.0 - center.0) * k,
+            center.1 + (p.1 - center.1) * k,
+        )
+    }))
+}
+
+/// 把文字位图以 origin（局部坐标）为左上角按覆盖率混合，clip 之外的像素不绘制；有旋转时逆映射
+fn fill_text(
+    tile: &mut Tile,
+    rot: Option<&Rotation>,
+    bitmap: &text::TextBitmap,
+    origin: (f32, f32),
+    clip: Rect,
+    color: Color,
+    opacity: f32,
+) {
+    let area = Rect {
+        x: origin.0,
+        y: origin.1,
+        w: bitmap.width as f32,
+        h: bitmap.height as f32,
+    };
+    let a = color.alpha_with(opacity) as f32;
+    fill_coverage(
+        tile,
+        rot,
+        area,
+        |x, y| {
+            let (bx, by) = ((x - area.x).floor(), (y - area.y).floor());
+            if bx < 0.0 || by < 0.0 || !clip.contains(x, y) {
+                return 0.0;
+            }
+            bitmap.at(bx as usize, by as usize) as f32 / 255.0
+        },
+        |dst, cov| blend_over(dst, color.r, color.g, color.b, (a * cov).round() as u8),
+    );
+}
+
+/// 抗锯齿填充：在局部区域 outer（外扩 1px 容纳边缘像素）内逐像素取 coverage（局部坐标下像素中心的覆盖率，
+/// 0..=1），覆盖率非 0 的像素各调用一次 blend；有旋转时逆映射
+fn fill_coverage(
+    tile: &mut Tile,
+    rot: Option<&Rotation>,
+    outer: Rect,
+    coverage: impl Fn(f32, f32) -> f32,
+    mut blend: impl FnMut(&mut [u8], f32),
+) {
+    let outer = outer.inflate(1.0);
+    let (width, rows) = (tile.width, tile.rows());
+    let mut visit = |tile: &mut Tile, x: u32, y: u32, lx: f32, ly: f32| {
+        let c = coverage(lx, ly);
+        if c > 0.0 {
+            blend(tile.px(x, y), c);
+        }
+    };
+    if let Some(rot) = rot {
+        rot.for_each_pixel(width, rows, outer, |x, y, lx, ly| visit(tile, x, y, lx, ly));
+        return;
+    }
+    let (xs, ys, xe, ye) = tile.span(&outer);
+    for y in ys..ye {
+        for x in xs..xe {
+            visit(tile, x as u32, y as u32, x as f32 + 0.5, y as f32 + 0.5);
+        }
+    }
+}
+
+/// 像素（中心 (x, y) 的单位方格）与矩形相交的面积
+fn rect_coverage(r: &Rect, x: f32, y: f32) -> f32 {
+    let cx = ((x + 0.5).min(r.x + r.w) - (x - 0.5).max(r.x)).clamp(0.0, 1.0);
+    let cy = ((y + 0.5).min(r.y + r.h) - (y - 0.5).max(r.y)).clamp(0.0, 1.0);
+    cx * cy
+}
+
+/// 圆角矩形在像素中心处的覆盖率：圆角半径限制在短边一半以内，为 0 时退化为 `rect_coverage`；
+/// 否则按到圆角矩形边界的有向距离计算，边界上为 0.5
+fn rounded_rect_coverage(r: &Rect, radius: f32, x: f32, y: f32) -> f32 {
+    if r.w <= 0.0 || r.h <= 0.0 {
+        return 0.0;
+    }
+    let radius = radius.min(r.w.min(r.h) / 2.0);
+    if radius <= 0.0 {
+        return rect_coverage(r, x, y);
+    }
+    let (cx, cy) = r.center();
+    let qx = (x - cx).abs() - (r.w / 2.0 - radius);
+    let qy = (y - cy).abs() - (r.h / 2.0 - radius);
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    let inside = qx.max(qy).min(0.0);
+    (0.5 - (outside + inside - radius)).clamp(0.0, 1.0)
+}
+
+/// 内切椭圆在像素中心处的覆盖率：用隐函数值除以梯度长度近似到边界的有向距离，边界上为 0.5
+fn ellipse_coverage(r: &Rect, x: f32, y: f32) -> f32 {
+    let (rx, ry) = (r.w / 2.0, r.h / 2.0);
+    if rx <= 0.0 || ry <= 0.0 {
+        return 0.0;
+    }
+    let (cx, cy) = r.center();
+    let (dx, dy) = (x - cx, y - cy);
+    let f = (dx / rx).powi(2) + (dy / ry).powi(2) - 1.0;
+    let grad = 2.0 * ((dx / (rx * rx)).powi(2) + (dy / (ry * ry)).powi(2)).sqrt();
+    if grad == 0.0 {
+        return 1.0;
+    }
+    (0.5 - f / grad).clamp(0.0, 1.0)
+}
+
+/// source-over 混合单个像素
+fn blend_over(dst: &mut [u8], r: u8, g: u8, b: u8, a: u8) {
+    let src_a = a as f32 / 255.0;
+    let dst_a = dst[3] as f32 / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    if out_a > 0.0 {
+        dst[0] = (((r as f32 * src_a) + (dst[0] as f32 * dst_a * (1.0 - src_a))) / out_a) as u8;
+        dst[1] = (((g as f32 * src_a) + (dst[1] as f32 * dst_a * (1.0 - src_a))) / out_a) as u8;
+        dst[2] = (((b as f32 * src_a) + (dst[2] as f32 * dst_a * (1.0 - src_a))) / out_a) as u8;
+        dst[3] = (out_a * 255.0) as u8;
     } else {
-        None
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-fn blend_fill_rect(img: &mut Image, x: i32, y: i32, w: i32, h: i32, r: u8, g: u8, b: u8, a: u8) {
-    let (w0, h0) = (img.width as i32, img.height as i32);
-    let x2 = (x + w).min(w0);
-    let y2 = (y + h).min(h0);
-    let xs = x.max(0);
-    let ys = y.max(0);
-    for yy in ys..y2 {
-        for xx in xs..x2 {
-            let i = ((yy as u32 * img.width + xx as u32) * 4) as usize;
-            let dst = &mut img.pixels[i..i + 4];
-            let src_a = a as f32 / 255.0;
-            let dst_a = dst[3] as f32 / 255.0;
-            let out_a = src_a + dst_a * (1.0 - src_a);
-            if out_a > 0.0 {
-                dst[0] =
-                    (((r as f32 * src_a) + (dst[0] as f32 * dst_a * (1.0 - src_a))) / out_a) as u8;
-                dst[1] =
-                    (((g as f32 * src_a) + (dst[1] as f32 * dst_a * (1.0 - src_a))) / out_a) as u8;
-                dst[2] =
-                    (((b as f32 * src_a) + (dst[2] as f32 * dst_a * (1.0 - src_a))) / out_a) as u8;
-                dst[3] = (out_a * 255.0) as u8;
-            } else {
-                dst[0] = r;
-                dst[1] = g;
-                dst[2] = b;
-                dst[3] = a;
+        dst[0] = r;
+        dst[1] = g;
+        dst[2] = b;
+        dst[3] = a;
+    }
+}
+
+/// 绕标注中心的旋转变换（rotation 为 0 时不构造，走原有轴对齐路径）
+#[derive(Clone, Copy)]
+struct Rotation {
+    cx: f32,
+    cy: f32,
+    sin: f32,
+    cos: f32,
+}
+
+impl Rotation {
+    fn of(ann: &Annotation) -> Option<Self> {
+        if ann.meta.rotation.is_multiple_of(360) {
+            return None;
+        }
+        let (cx, cy) = ann.rotation_center();
+        let (sin, cos) = rotation_sin_cos(ann.meta.rotation);
+        Some(Self { cx, cy, sin, cos })
+    }
+
+    /// 局部坐标 -> 画布坐标
+    fn forward(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (x - self.cx, y - self.cy);
+        (
+            self.cx + dx * self.cos - dy * self.sin,
+            self.cy + dx * self.sin + dy * self.cos,
+        )
+    }
+
+    /// 画布坐标 -> 局部坐标
+    fn inverse(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (x - self.cx, y - self.cy);
+        (
+            self.cx + dx * self.cos + dy * self.sin,
+            self.cy - dx * self.sin + dy * self.cos,
+        )
+    }
+
+    /// 逆映射填充：遍历旋转后包围盒内的像素，像素中心反旋转后落在 outer 内（且不在 hole 内）则交给 f 处理
+    fn fill(&self, tile: &mut Tile, outer: Rect, hole: Option<Rect>, f: impl FnMut(&mut [u8])) {
+        self.fill_where(
+            tile,
+            outer,
+            |lx, ly| !hole.is_some_and(|h| local_contains(&h, lx, ly)),
+            f,
+        );
+    }
+
+    /// 同 `fill`，但由 keep 按局部坐标决定是否处理该像素
+    fn fill_where(
+        &self,
+        tile: &mut Tile,
+        outer: Rect,
+        keep: impl Fn(f32, f32) -> bool,
+        mut f: impl FnMut(&mut [u8]),
+    ) {
+        self.for_each_pixel(tile.width, tile.rows(), outer, |x, y, lx, ly| {
+            if keep(lx, ly) {
+                f(tile.px(x, y));
+            }
+        });
+    }
+
+    /// 遍历旋转后包围盒与 rows 行范围的交集，像素中心反旋转后落在 outer 内时回调
+    /// (画布 x, 画布 y, 局部 x, 局部 y)
+    fn for_each_pixel(
+        &self,
+        width: u32,
+        rows: std::ops::Range<u32>,
+        outer: Rect,
+        mut f: impl FnMut(u32, u32, f32, f32),
+    ) {
+        let corners = outer.corners().map(|p| self.forward(p));
+        let min_x = corners.iter().map(|p| p.0).fold(f32::MAX, f32::min);
+        let max_x = corners.iter().map(|p| p.0).fold(f32::MIN, f32::max);
+        let min_y = corners.iter().map(|p| p.1).fold(f32::MAX, f32::min);
+        let max_y = corners.iter().map(|p| p.1).fold(f32::MIN, f32::max);
+        let xs = (min_x.floor() as i64).max(0);
+        let ys = (min_y.floor() as i64).max(rows.start as i64);
+        let xe = (max_x.ceil() as i64).min(width as i64);
+        let ye = (max_y.ceil() as i64).min(rows.end as i64);
+        for y in ys..ye {
+            for x in xs..xe {
+                let (lx, ly) = self.inverse((x as f32 + 0.5, y as f32 + 0.5));
+                if local_contains(&outer, lx, ly) {
+                    f(x as u32, y as u32, lx, ly);
+                }
             }
         }
     }
 }
 
+/// 半开区间包含判断，与轴对齐填充的像素覆盖规则一致
+#[inline]
+fn local_contains(r: &Rect, x: f32, y: f32) -> bool {
+    x >= r.x && x < r.x + r.w && y >= r.y && y < r.y + r.h
+}
+
 // 添加：Blend 模式与辅助函数
 #[derive(Copy, Clone)]
 enum Blend {
@@ -358,56 +1281,6 @@ fn blend_pixel_mode(dst: &mut [u8], sr: (u8, u8, u8, u8), mode: Blend) {
     dst[3] = (out_a * 255.0) as u8;
 }
 
-#[allow(clippy::too_many_arguments)]
-fn stroke_rect(
-    img: &mut Image,
-    x: i32,
-    y: i32,
-    w: i32,
-    h: i32,
-    th: f32,
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
-) {
-    if th <= 0.0 {
-        return;
-    }
-    let t = th.ceil() as i32;
-    // 四条边: top, bottom, left, right
-    blend_fill_rect(img, x, y, w, t, r, g, b, a);
-    blend_fill_rect(img, x, y + h - t, w, t, r, g, b, a);
-    blend_fill_rect(img, x, y, t, h, r, g, b, a);
-    blend_fill_rect(img, x + w - t, y, t, h, r, g, b, a);
-}
-
-#[allow(clippy::too_many_arguments)]
-fn highlight_rect(
-    img: &mut Image,
-    x: i32,
-    y: i32,
-    w: i32,
-    h: i32,
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
-    mode: Blend,
-) {
-    let (w0, h0) = (img.width as i32, img.height as i32);
-    let x2 = (x + w).min(w0);
-    let y2 = (y + h).min(h0);
-    let xs = x.max(0);
-    let ys = y.max(0);
-    for yy in ys..y2 {
-        for xx in xs..x2 {
-            let i = ((yy as u32 * img.width + xx as u32) * 4) as usize;
-            blend_pixel_mode(&mut img.pixels[i..i + 4], (r, g, b, a), mode);
-        }
-    }
-}
-
 fn mosaic_block_size(level: u8) -> i32 {
     match level {
         0 | 1 => 6,
@@ -417,81 +1290,1007 @@ fn mosaic_block_size(level: u8) -> i32 {
     }
 }
 
-/// 应用马赛克效果（并行版本）
+/// 马赛克分块颜色：在局部坐标中按 block 划分 grid，渲染前一次算好，各分块只查表
+struct MosaicBlocks {
+    grid: Rect,
+    block: f32,
+    nbx: usize,
+    nby: usize,
+    colors: Vec<Option<[u8; 4]>>,
+}
+
+impl MosaicBlocks {
+    fn new(base: Base, rot: Option<&Rotation>, grid: Rect, block: f32) -> Self {
+        let (nbx, nby, colors) =
+            mosaic_block_colors(base.pixels, base.width, base.height, rot, grid, block);
+        Self {
+            grid,
+            block,
+            nbx,
+            nby,
+            colors,
+        }
+    }
+
+    /// 局部坐标 (lx, ly) 所在块的颜色
+    fn color_at(&self, lx: f32, ly: f32) -> Option<[u8; 4]> {
+        let bx = (((lx - self.grid.x) / self.block) as usize).min(self.nbx - 1);
+        let by = (((ly - self.grid.y) / self.block) as usize).min(self.nby - 1);
+        self.colors[by * self.nbx + bx]
+    }
+}
+
+/// 应用马赛克：grid 内的像素替换为所在块的颜色；radius > 0 时按 region 的圆角矩形覆盖率混合
 ///
-/// 性能优化：使用 rayon 并行处理马赛克块
-/// - 每个块独立计算平均值
-/// - 避免数据竞争（每个块写入不同的像素区域）
-fn apply_mosaic(img: &mut Image, base: &[u8], x: i32, y: i32, w: i32, h: i32, block: i32) {
-    use rayon::prelude::*;
+/// 轴对齐时 grid 为 region 截断到图像内的部分，逐画布像素处理；有旋转时 grid 与 region 相同，
+/// 逆映射到局部坐标后查块
+fn apply_mosaic(
+    tile: &mut Tile,
+    blocks: &MosaicBlocks,
+    rot: Option<&Rotation>,
+    region: Rect,
+    radius: f32,
+) {
+    let visit = |tile: &mut Tile, x: u32, y: u32, lx: f32, ly: f32| {
+        if let Some(c) = blocks.color_at(lx, ly) {
+            let cov = if radius > 0.0 {
+                rounded_rect_coverage(&region, radius, lx, ly)
+            } else {
+                1.0
+            };
+            mix_pixel(tile.px(x, y), c, cov);
+        }
+    };
+    if let Some(rot) = rot {
+        rot.for_each_pixel(tile.width, tile.rows(), blocks.grid, |x, y, lx, ly| {
+            visit(tile, x, y, lx, ly)
+        });
+        return;
+    }
+    let (xs, ys, xe, ye) = tile.span(&blocks.grid);
+    for y in ys..ye {
+        for x in xs..xe {
+            visit(tile, x as u32, y as u32, x as f32 + 0.5, y as f32 + 0.5);
+        }
+    }
+}
+
+/// 按覆盖率把整像素颜色（含 alpha）插值写入：覆盖率为 1 时直接替换，用于马赛克圆角边缘
+fn mix_pixel(dst: &mut [u8], c: [u8; 4], cov: f32) {
+    if cov >= 1.0 {
+        dst.copy_from_slice(&c);
+    } else if cov > 0.0 {
+        for (d, s) in dst.iter_mut().zip(c) {
+            *d = (*d as f32 + (s as f32 - *d as f32) * cov).round() as u8;
+        }
+    }
+}
 
-    let (w0, h0) = (img.width as i32, img.height as i32);
-    let x2 = (x + w).min(w0);
-    let y2 = (y + h).min(h0);
-    let xs = x.max(0);
-    let ys = y.max(0);
+/// 在局部坐标中按 block 划分 local（行优先，返回横向块数、纵向块数与各块颜色）；块颜色取块内
+/// 局部像素中心正向映射到画布后在 base 中的平均值，整块落在图像外时为 None
+fn mosaic_block_colors(
+    base: &[u8],
+    width: u32,
+    height: u32,
+    rot: Option<&Rotation>,
+    local: Rect,
+    block: f32,
+) -> (usize, usize, Vec<Option<[u8; 4]>>) {
+    use rayon::prelude::*;
 
-    // 收集所有需要处理的块坐标
-    let blocks: Vec<(i32, i32)> = (ys..y2)
-        .step_by(block as usize)
-        .flat_map(|by| (xs..x2).step_by(block as usize).map(move |bx| (bx, by)))
-        .collect();
+    let nbx = (local.w / block).ceil() as usize;
+    let nby = (local.h / block).ceil() as usize;
+    let (w0, h0) = (width as i32, height as i32);
 
-    // 并行计算每个块的平均颜色
-    let block_colors: Vec<_> = blocks
-        .par_iter()
-        .filter_map(|&(bx, by)| {
-            let bw = (bx + block).min(x2) - bx;
-            let bh = (by + block).min(y2) - by;
-
-            // 计算平均
-            let mut acc_r = 0u32;
-            let mut acc_g = 0u32;
-            let mut acc_b = 0u32;
-            let mut acc_a = 0u32;
+    let block_colors = (0..nbx * nby)
+        .into_par_iter()
+        .map(|bi| {
+            let bx = local.x + (bi % nbx) as f32 * block;
+            let by = local.y + (bi / nbx) as f32 * block;
+            let bx2 = (bx + block).min(local.x + local.w);
+            let by2 = (by + block).min(local.y + local.h);
+            let mut acc = [0u32; 4];
             let mut count = 0u32;
-
-            for yy in by..by + bh {
-                for xx in bx..bx + bw {
-                    let i = ((yy as u32 * img.width + xx as u32) * 4) as usize;
-                    if i + 3 < base.len() {
-                        acc_r += base[i] as u32;
-                        acc_g += base[i + 1] as u32;
-                        acc_b += base[i + 2] as u32;
-                        acc_a += base[i + 3] as u32;
+            let mut ly = by + 0.5;
+            while ly < by2 {
+                let mut lx = bx + 0.5;
+                while lx < bx2 {
+                    let (px, py) = rot.map_or((lx, ly), |r| r.forward((lx, ly)));
+                    let (px, py) = (px.floor() as i32, py.floor() as i32);
+                    if px >= 0 && py >= 0 && px < w0 && py < h0 {
+                        let i = ((py * w0 + px) * 4) as usize;
+                        for c in 0..4 {
+                            acc[c] += base[i + c] as u32;
+                        }
                         count += 1;
                     }
+                    lx += 1.0;
                 }
+                ly += 1.0;
             }
+            (count > 0).then(|| acc.map(|v| (v / count) as u8))
+        })
+        .collect();
+    (nbx, nby, block_colors)
+}
 
-            if count == 0 {
-                return None;
-            }
-
-            let r = (acc_r / count) as u8;
-            let g = (acc_g / count) as u8;
-            let b = (acc_b / count) as u8;
-            let a = (acc_a / count) as u8;
+/// 聚光灯压暗：每个像素取所有聚光灯亮度覆盖率的最大值（亮区并集），再按 1 - 覆盖率 只压暗一次
+///
+/// 压暗颜色与强度取 z 最高（同 z 时列表靠后）的聚光灯：fill_color（缺省黑色）× opacity。
+/// 覆盖率：亮区内为 1；有羽化时在亮区外 feather 像素内线性衰减到 0。
+struct Spotlights {
+    color: Color,
+    alpha: f32,
+    regions: Vec<(Rect, SpotlightShape, f32, Option<Rotation>)>,
+}
 
-            Some((bx, by, bw, bh, r, g, b, a))
+impl Spotlights {
+    /// spotlights 按 z 升序；没有聚光灯或压暗强度为 0 时返回 None
+    fn new(spotlights: &[&Annotation]) -> Option<Self> {
+        let top = spotlights.last()?;
+        let color = top.meta.fill_color.unwrap_or(Color::BLACK);
+        let alpha = color.alpha_with(top.meta.opacity) as f32;
+        if alpha <= 0.0 {
+            return None;
+        }
+        let regions = spotlights
+            .iter()
+            .filter_map(|a| match a.kind {
+                AnnotationKind::Spotlight { shape, feather } => {
+                    Some((a.local_bounds(), shape, feather as f32, Rotation::of(a)))
+                }
+                _ => None,
+            })
+            .collect();
+        Some(Self {
+            color,
+            alpha,
+            regions,
         })
+    }
+
+    /// 画布坐标 (x, y) 处的亮度覆盖率
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        self.regions
+            .iter()
+            .map(|(r, shape, feather, rot)| {
+                let (lx, ly) = rot.as_ref().map_or((x, y), |rot| rot.inverse((x, y)));
+                let d = match shape {
+                    SpotlightShape::Rect => rect_distance(r, lx, ly),
+                    SpotlightShape::Ellipse => ellipse_distance(r, lx, ly),
+                };
+                if d <= 0.0 {
+                    1.0
+                } else if *feather > 0.0 {
+                    (1.0 - d / feather).max(0.0)
+                } else {
+                    0.0
+                }
+            })
+            .fold(0.0f32, f32::max)
+    }
+}
+
+/// 聚光灯压暗作用于整幅画布，逐像素按覆盖率混合压暗颜色
+fn apply_spotlights(tile: &mut Tile, spots: &Spotlights) {
+    let c = spots.color;
+    for y in tile.rows() {
+        for x in 0..tile.width {
+            let cov = spots.coverage(x as f32 + 0.5, y as f32 + 0.5);
+            let a = (spots.alpha * (1.0 - cov)).round() as u8;
+            if a > 0 {
+                blend_over(tile.px(x, y), c.r, c.g, c.b, a);
+            }
+        }
+    }
+}
+
+/// 点到矩形的距离；在矩形内（含边界）为 0
+fn rect_distance(r: &Rect, x: f32, y: f32) -> f32 {
+    let dx = (r.x - x).max(x - (r.x + r.w)).max(0.0);
+    let dy = (r.y - y).max(y - (r.y + r.h)).max(0.0);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// 点到内切椭圆的近似距离：沿中心射线方向的径向距离；在椭圆内为 0
+fn ellipse_distance(r: &Rect, x: f32, y: f32) -> f32 {
+    let (cx, cy) = r.center();
+    let (rx, ry) = (r.w / 2.0, r.h / 2.0);
+    let (dx, dy) = (x - cx, y - cy);
+    if rx <= 0.0 || ry <= 0.0 {
+        return rect_distance(r, x, y);
+    }
+    let k = ((dx / rx).powi(2) + (dy / ry).powi(2)).sqrt();
+    if k <= 1.0 {
+        return 0.0;
+    }
+    (dx * dx + dy * dy).sqrt() * (1.0 - 1.0 / k)
+}
+
+/// 涂黑打码：不透明写入 fill_color（缺省黑色），忽略 opacity 与颜色 alpha
+///
+/// 采用保守覆盖：与区域有任何相交的像素都被覆盖，不会在边缘留下半个像素的原图。
+fn apply_redact(tile: &mut Tile, ann: &Annotation) {
+    let c = ann.meta.fill_color.unwrap_or(Color::BLACK);
+    let px = [c.r, c.g, c.b, 255];
+    let local = ann.local_bounds();
+    if let Some(rot) = Rotation::of(ann) {
+        // 像素中心到区域的距离不超过半个对角线时，像素才可能与区域相交
+        let outer = local.inflate(std::f32::consts::FRAC_1_SQRT_2);
+        rot.fill(tile, outer, None, |dst| dst.copy_from_slice(&px));
+        return;
+    }
+    let (xs, ys, xe, ye) = tile.span(&local);
+    for y in ys..ye {
+        for x in xs..xe {
+            tile.px(x as u32, y as u32).copy_from_slice(&px);
+        }
+    }
+}
+
+/// 归一化的一维高斯核：σ = radius / 2，半宽 ceil(3σ)
+fn gaussian_kernel(radius: u8) -> Vec<f32> {
+    let sigma = radius as f32 / 2.0;
+    let half = (sigma * 3.0).ceil() as i32;
+    let k: Vec<f32> = (-half..=half)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
         .collect();
+    let sum: f32 = k.iter().sum();
+    k.into_iter().map(|v| v / sum).collect()
+}
+
+/// 对底图 base 中 [x0, x1) x [y0, y1) 区域做可分离高斯模糊，返回该区域的 RGBA 像素
+///
+/// 采样超出图像边界时夹到最近的边缘像素（clamp），避免边缘被透明黑色拉暗；
+/// 横向、纵向两遍均按行用 rayon 并行。
+fn blur_region(
+    base: &[u8],
+    width: u32,
+    height: u32,
+    (x0, y0, x1, y1): (i32, i32, i32, i32),
+    radius: u8,
+) -> Vec<u8> {
+    use rayon::prelude::*;
+
+    let kernel = gaussian_kernel(radius);
+    let half = (kernel.len() / 2) as i32;
+    let (w, h) = (width as i32, height as i32);
+    let rw = (x1 - x0) as usize;
+    // 横向一遍需要覆盖纵向卷积用到的所有行（已夹到图像内）
+    let (ty0, ty1) = ((y0 - half).max(0), (y1 + half).min(h));
+    let mut tmp = vec![0f32; (ty1 - ty0) as usize * rw * 4];
+    tmp.par_chunks_mut(rw * 4)
+        .enumerate()
+        .for_each(|(row, out)| {
+            let y = ty0 + row as i32;
+            for (i, px) in out.chunks_exact_mut(4).enumerate() {
+                let x = x0 + i as i32;
+                let mut acc = [0f32; 4];
+                for (k, wk) in kernel.iter().enumerate() {
+                    let sx = (x + k as i32 - half).clamp(0, w - 1);
+                    let si = ((y * w + sx) * 4) as usize;
+                    for c in 0..4 {
+                        acc[c] += base[si + c] as f32 * wk;
+                    }
+                }
+                px.copy_from_slice(&acc);
+            }
+        });
+
+    let mut out = vec![0u8; (y1 - y0) as usize * rw * 4];
+    out.par_chunks_mut(rw * 4)
+        .enumerate()
+        .for_each(|(row, dst)| {
+            let y = y0 + row as i32;
+            for (i, px) in dst.chunks_exact_mut(4).enumerate() {
+                let mut acc = [0f32; 4];
+                for (k, wk) in kernel.iter().enumerate() {
+                    let sy = (y + k as i32 - half).clamp(0, h - 1);
+                    let ti = ((sy - ty0) as usize * rw + i) * 4;
+                    for c in 0..4 {
+                        acc[c] += tmp[ti + c] * wk;
+                    }
+                }
+                for c in 0..4 {
+                    px[c] = acc[c].round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+    out
+}
+
+/// 模糊结果：画布中 [x0, x1) x [y0, y1) 区域的 RGBA 像素，渲染前一次算好，各分块只拷贝
+struct BlurredRegion {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+    pixels: Vec<u8>,
+}
+
+impl BlurredRegion {
+    /// 轴对齐区域（像素覆盖规则与 `apply_mosaic` 一致）；radius 为 0 或区域在图像外时返回 None
+    fn new(base: Base, x: i32, y: i32, w: i32, h: i32, radius: u8) -> Option<Self> {
+        let (x0, y0) = (x.max(0), y.max(0));
+        let x1 = (x + w).min(base.width as i32);
+        let y1 = (y + h).min(base.height as i32);
+        Self::blur(base, (x0, y0, x1, y1), radius)
+    }
+
+    /// 旋转区域：对旋转后包围盒整体模糊，写回时再只取落在旋转区域内的像素
+    fn rotated(base: Base, rot: &Rotation, local: Rect, radius: u8) -> Option<Self> {
+        if local.w <= 0.0 || local.h <= 0.0 {
+            return None;
+        }
+        let b = canvas_rect(Some(rot), local);
+        let (x0, y0) = ((b.x.floor() as i32).max(0), (b.y.floor() as i32).max(0));
+        let x1 = ((b.x + b.w).ceil() as i32).min(base.width as i32);
+        let y1 = ((b.y + b.h).ceil() as i32).min(base.height as i32);
+        Self::blur(base, (x0, y0, x1, y1), radius)
+    }
+
+    fn blur(base: Base, (x0, y0, x1, y1): (i32, i32, i32, i32), radius: u8) -> Option<Self> {
+        if radius == 0 || x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        let pixels = blur_region(
+            base.pixels,
+            base.width,
+            base.height,
+            (x0, y0, x1, y1),
+            radius,
+        );
+        Some(Self {
+            x0,
+            y0,
+            x1,
+            y1,
+            pixels,
+        })
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect {
+            x: self.x0 as f32,
+            y: self.y0 as f32,
+            w: (self.x1 - self.x0) as f32,
+            h: (self.y1 - self.y0) as f32,
+        }
+    }
+
+    /// 画布坐标 (x, y) 处的模糊结果，调用方保证在区域内
+    fn at(&self, x: u32, y: u32) -> &[u8] {
+        let rw = (self.x1 - self.x0) as usize;
+        let i = ((y as i32 - self.y0) as usize * rw + (x as i32 - self.x0) as usize) * 4;
+        &self.pixels[i..i + 4]
+    }
+}
+
+/// 高斯模糊打码（轴对齐区域）：整行拷贝落在本分块内的模糊结果
+fn apply_blur(tile: &mut Tile, blurred: &BlurredRegion) {
+    let row_len = (blurred.x1 - blurred.x0) as usize * 4;
+    let (_, ys, _, ye) = tile.span(&blurred.bounds());
+    let rows = blurred
+        .pixels
+        .chunks_exact(row_len)
+        .skip((ys - blurred.y0 as i64) as usize);
+    for (y, src) in (ys..ye).zip(rows) {
+        tile.row_from(blurred.x0 as u32, y as u32)[..row_len].copy_from_slice(src);
+    }
+}
+
+/// 旋转模糊：只写回落在旋转区域内的像素
+fn apply_blur_rotated(tile: &mut Tile, blurred: &BlurredRegion, rot: &Rotation, local: Rect) {
+    rot.for_each_pixel(tile.width, tile.rows(), local, |x, y, _, _| {
+        tile.px(x, y).copy_from_slice(blurred.at(x, y));
+    });
+}
+
+/// 放大镜插图形状的包含判断（局部坐标）
+fn magnifier_contains(shape: MagnifierShape, r: &Rect, x: f32, y: f32) -> bool {
+    match shape {
+        MagnifierShape::Rect => r.w > 0.0 && r.h > 0.0 && r.contains(x, y),
+        MagnifierShape::Circle => r.ellipse_contains(x, y),
+    }
+}
+
+/// 放大镜外形在像素中心处的覆盖率
+fn magnifier_coverage(shape: MagnifierShape, r: &Rect, x: f32, y: f32) -> f32 {
+    match shape {
+        MagnifierShape::Rect => rect_coverage(r, x, y),
+        MagnifierShape::Circle => ellipse_coverage(r, x, y),
+    }
+}
+
+/// 在底图 base 的画布坐标 (x, y) 处采样；超出图像时夹到最近的边缘像素
+fn sample_base(
+    base: &[u8],
+    width: u32,
+    height: u32,
+    (x, y): (f32, f32),
+    filter: SampleFilter,
+) -> [u8; 4] {
+    let (w, h) = (width as i32, height as i32);
+    let at = |px: i32, py: i32| {
+        let i = ((py.clamp(0, h - 1) * w + px.clamp(0, w - 1)) * 4) as usize;
+        [base[i], base[i + 1], base[i + 2], base[i + 3]]
+    };
+    match filter {
+        SampleFilter::Nearest => at(x.floor() as i32, y.floor() as i32),
+        SampleFilter::Bilinear => {
+            // 以像素中心为采样点插值
+            let (fx, fy) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (fx.floor(), fy.floor());
+            let (tx, ty) = (fx - x0, fy - y0);
+            let (x0, y0) = (x0 as i32, y0 as i32);
+            let (p00, p10) = (at(x0, y0), at(x0 + 1, y0));
+            let (p01, p11) = (at(x0, y0 + 1), at(x0 + 1, y0 + 1));
+            std::array::from_fn(|c| {
+                let top = p00[c] as f32 * (1.0 - tx) + p10[c] as f32 * tx;
+                let bottom = p01[c] as f32 * (1.0 - tx) + p11[c] as f32 * tx;
+                (top * (1.0 - ty) + bottom * ty).round() as u8
+            })
+        }
+    }
+}
+
+/// 放大镜：插图内每个像素中心相对插图中心的偏移除以 zoom，加到 source 中心得到底图采样点
+///
+/// 采样点按画布坐标计算，插图旋转时只旋转外形，放大内容保持正向。
+/// zoom 非正或非有限值时按 1 处理；边框沿插图边界向内绘制。
+fn apply_magnifier(tile: &mut Tile, base: Base, ann: &Annotation) {
+    let AnnotationKind::Magnifier {
+        source,
+        zoom,
+        shape,
+        filter,
+    } = ann.kind
+    else {
+        return;
+    };
+    let local = ann.local_bounds();
+    if local.w <= 0.0 || local.h <= 0.0 {
+        return;
+    }
+    let m = &ann.meta;
+    let zoom = if zoom.is_finite() && zoom > 0.0 {
+        zoom
+    } else {
+        1.0
+    };
+    let (sx, sy) = (source.0 + source.2 / 2.0, source.1 + source.3 / 2.0);
+    let (cx, cy) = local.center();
+    let opacity = m.opacity.clamp(0.0, 1.0);
+    let rot = Rotation::of(ann);
+    let put = |tile: &mut Tile, x: u32, y: u32| {
+        let p = (
+            sx + (x as f32 + 0.5 - cx) / zoom,
+            sy + (y as f32 + 0.5 - cy) / zoom,
+        );
+        let c = sample_base(base.pixels, base.width, base.height, p, filter);
+        let a = (c[3] as f32 * opacity).round() as u8;
+        blend_over(tile.px(x, y), c[0], c[1], c[2], a);
+    };
+    if let Some(rot) = rot.as_ref() {
+        rot.for_each_pixel(tile.width, tile.rows(), local, |x, y, lx, ly| {
+            if magnifier_contains(shape, &local, lx, ly) {
+                put(tile, x, y);
+            }
+        });
+    } else {
+        let (xs, ys, xe, ye) = tile.span(&local);
+        for y in ys..ye {
+            for x in xs..xe {
+                if magnifier_contains(shape, &local, x as f32 + 0.5, y as f32 + 0.5) {
+                    put(tile, x as u32, y as u32);
+                }
+            }
+        }
+    }
+    let border = m.stroke_width.unwrap_or(MAGNIFIER_BORDER);
+    if border > 0.0 {
+        let c = m.stroke_color.unwrap_or(Color::WHITE);
+        let a = c.alpha_with(opacity) as f32;
+        let inner = local.inflate(-border);
+        fill_coverage(
+            tile,
+            rot.as_ref(),
+            local,
+            |x, y| {
+                (magnifier_coverage(shape, &local, x, y) - magnifier_coverage(shape, &inner, x, y))
+                    .max(0.0)
+            },
+            |dst, cov| blend_over(dst, c.r, c.g, c.b, (a * cov).round() as u8),
+        );
+    }
+}
+
+/// 贴图解码后的像素上限（RGBA8 字节数），超过时不绘制
+const MAX_IMAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// 解码 PNG 为 RGBA8；灰度 / RGB / 调色板统一展开，16 位降为 8 位
+fn decode_png_rgba(png: &PngData) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new_with_limits(
+        png.as_bytes(),
+        png::Limits {
+            bytes: MAX_IMAGE_BYTES,
+        },
+    );
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let size = reader.output_buffer_size();
+    if size > MAX_IMAGE_BYTES {
+        anyhow::bail!("image too large: {size} bytes");
+    }
+    let mut buf = vec![0u8; size];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+    let (w, h) = (info.width, info.height);
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => anyhow::bail!("indexed png not expanded"),
+    };
+    Ok((w, h, rgba))
+}
+
+/// 贴图：图片拉伸到标注区域，双线性采样后按图片 alpha × opacity 合成；随标注一起旋转
+///
+/// decoded 为预先解码的 (宽, 高, RGBA8 像素)，调用方保证宽高非 0、标注区域非空
+fn apply_image(tile: &mut Tile, ann: &Annotation, (iw, ih, pixels): &(u32, u32, Vec<u8>)) {
+    let local = ann.local_bounds();
+    let opacity = ann.meta.opacity.clamp(0.0, 1.0);
+    let (sx, sy) = (*iw as f32 / local.w, *ih as f32 / local.h);
+    let put = |tile: &mut Tile, x: u32, y: u32, lx: f32, ly: f32| {
+        let p = ((lx - local.x) * sx, (ly - local.y) * sy);
+        let c = sample_base(pixels, *iw, *ih, p, SampleFilter::Bilinear);
+        let a = (c[3] as f32 * opacity).round() as u8;
+        blend_over(tile.px(x, y), c[0], c[1], c[2], a);
+    };
+    if let Some(rot) = Rotation::of(ann) {
+        rot.for_each_pixel(tile.width, tile.rows(), local, |x, y, lx, ly| {
+            put(tile, x, y, lx, ly)
+        });
+        return;
+    }
+    let (xs, ys, xe, ye) = tile.span(&local);
+    for y in ys..ye {
+        for x in xs..xe {
+            let (lx, ly) = (x as f32 + 0.5, y as f32 + 0.5);
+            if local_contains(&local, lx, ly) {
+                put(tile, x as u32, y as u32, lx, ly);
+            }
+        }
+    }
+}
+
+/// 按标注旋转变换点集（画布坐标）
+fn rotated_points(ann: &Annotation, points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    match Rotation::of(ann) {
+        Some(rot) => points.iter().map(|p| rot.forward(*p)).collect(),
+        None => points.to_vec(),
+    }
+}
+
+/// 抗锯齿扫描线填充多边形（非零环绕规则，首尾自动闭合）；顶点已是画布坐标
+///
+/// 每行在像素中心高度与边表求交，按环绕数得到内部区间，区间内的像素整像素混合；
+/// 只有边经过的像素（像素中心离边不足半像素时必在其中）单独按距离取覆盖率：
+/// 在内时为 0.5 + 到边界的距离，在外时为 0.5 - 距离（均截断到 0..=1），每个像素只混合一次。
+fn fill_polygon(tile: &mut Tile, points: &[(f32, f32)], (r, g, b, a): (u8, u8, u8, u8)) {
+    if points.len() < 3 || a == 0 {
+        return;
+    }
+    let Some(bb) = Rect::bounding(points.iter().copied()) else {
+        return;
+    };
+    let n = points.len();
+    let edges: Vec<Segment> = (0..n).map(|i| (points[i], points[(i + 1) % n])).collect();
+    let (xs, ys, xe, ye) = tile.span(&bb.inflate(1.0));
+    if xs >= xe {
+        return;
+    }
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+    let mut spans: Vec<(f32, f32)> = Vec::new();
+    let mut near: Vec<Segment> = Vec::new();
+    let mut on_edge = vec![false; (xe - xs) as usize];
+    let blend = |dst: &mut [u8], c: f32| blend_over(dst, r, g, b, (a as f32 * c).round() as u8);
+    for y in ys..ye {
+        let (top, cy) = (y as f32, y as f32 + 0.5);
+        crossings.clear();
+        near.clear();
+        on_edge.fill(false);
+        for &(p0, p1) in &edges {
+            let (lo, hi) = if p0.1 <= p1.1 { (p0, p1) } else { (p1, p0) };
+            let x_at = |v: f32| {
+                if hi.1 == lo.1 {
+                    lo.0
+                } else {
+                    lo.0 + (v.clamp(lo.1, hi.1) - lo.1) * (hi.0 - lo.0) / (hi.1 - lo.1)
+                }
+            };
+            if lo.1 <= cy && cy < hi.1 {
+                crossings.push((x_at(cy), if p1.1 > p0.1 { 1 } else { -1 }));
+            }
+            // 边落在 [top, top + 1] 内的部分横向外扩半像素，覆盖所有离它不足半像素的像素中心
+            if hi.1 < top || lo.1 > top + 1.0 {
+                continue;
+            }
+            near.push((p0, p1));
+            let (u, v) = if hi.1 == lo.1 {
+                (p0.0, p1.0)
+            } else {
+                (x_at(top), x_at(top + 1.0))
+            };
+            let from = ((u.min(v) - 1.0).ceil() as i64).max(xs);
+            let to = (u.max(v).floor() as i64 + 1).min(xe);
+            for x in from..to {
+                on_edge[(x - xs) as usize] = true;
+            }
+        }
+        crossings.sort_by(|p, q| p.0.total_cmp(&q.0));
+        spans.clear();
+        let mut winding = 0;
+        for &(x, dir) in &crossings {
+            let was_inside = winding != 0;
+            winding += dir;
+            match (was_inside, winding != 0) {
+                (false, true) => spans.push((x, x)),
+                (true, false) => spans.last_mut().unwrap().1 = x,
+                _ => {}
+            }
+        }
+        // 内部区间：像素中心落在 [起点, 终点) 内且不是边缘像素的整像素混合
+        for &(x0, x1) in &spans {
+            let from = ((x0 - 0.5).ceil() as i64).max(xs);
+            let to = ((x1 - 0.5).ceil() as i64).min(xe);
+            for x in from..to {
+                if !on_edge[(x - xs) as usize] {
+                    blend(tile.px(x as u32, y as u32), 1.0);
+                }
+            }
+        }
+        // 边缘像素：区间判断内外，到附近边的距离决定覆盖率（更远的边距离 ≥ 半像素，不影响截断结果）
+        for x in xs..xe {
+            if !on_edge[(x - xs) as usize] {
+                continue;
+            }
+            let cx = x as f32 + 0.5;
+            let d = near
+                .iter()
+                .map(|&(p, q)| distance_to_segment((cx, cy), p, q))
+                .fold(f32::INFINITY, f32::min);
+            let c = if spans.iter().any(|&(x0, x1)| x0 <= cx && cx < x1) {
+                (0.5 + d).min(1.0)
+            } else {
+                (0.5 - d).max(0.0)
+            };
+            if c > 0.0 {
+                blend(tile.px(x as u32, y as u32), c);
+            }
+        }
+    }
+}
+
+/// 线段端点对
+type Segment = ((f32, f32), (f32, f32));
+
+/// 沿折线按 dash / gap 切分出各段 dash，图案在折点处连续；跨过折点的 dash 保留折点，按折线描边
+fn dash_path(points: &[(f32, f32)], dash: f32, gap: f32) -> Vec<Vec<(f32, f32)>> {
+    let mut out = Vec::new();
+    let mut current: Vec<(f32, f32)> = Vec::new();
+    // on 表示当前处于 dash 段，left 为当前段剩余长度
+    let (mut on, mut left) = (true, dash);
+    for w in points.windows(2) {
+        let (p0, p1) = (w[0], w[1]);
+        let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            continue;
+        }
+        let at = |t: f32| (p0.0 + dx * t / len, p0.1 + dy * t / len);
+        let mut cur = 0.0;
+        while cur < len {
+            let end = (cur + left).min(len);
+            if on {
+                if current.is_empty() {
+                    current.push(at(cur));
+                }
+                current.push(at(end));
+            }
+            left -= end - cur;
+            cur = end;
+            if left <= 0.0 {
+                if on {
+                    out.push(std::mem::take(&mut current));
+                }
+                on = !on;
+                left = if on { dash } else { gap };
+            }
+        }
+    }
+    if current.len() >= 2 {
+        out.push(current);
+    }
+    out
+}
+
+/// 单位方向向量 a -> b（a、b 重合时为零向量）
+fn direction(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (dx / len, dy / len)
+    }
+}
+
+/// 线段 p -> q 两侧各扩 half 的矩形（平头线段、方头）
+fn band(p: (f32, f32), q: (f32, f32), half: f32) -> Part {
+    let (ux, uy) = direction(p, q);
+    let (nx, ny) = (-uy * half, ux * half);
+    Part::Convex(SmallVec::from_slice(&[
+        (p.0 + nx, p.1 + ny),
+        (q.0 + nx, q.1 + ny),
+        (q.0 - nx, q.1 - ny),
+        (p.0 - nx, p.1 - ny),
+    ]))
+}
+
+/// 描边样式
+#[derive(Debug, Clone, Copy)]
+struct Stroke {
+    width: f32,
+    cap: LineCap,
+    join: LineJoin,
+}
+
+impl Stroke {
+    /// 圆头 + 圆角连接：箭头、测量线、荧光笔等不可配置的描边
+    fn round(width: f32) -> Self {
+        Self {
+            width,
+            cap: LineCap::Round,
+            join: LineJoin::Round,
+        }
+    }
+
+    /// 把折线展开为描边部件追加到 out；closed 时首尾相连、没有线端
+    ///
+    /// 圆角连接且两端为圆头（或闭合）时每段就是一个胶囊形；否则每段为矩形，
+    /// 拐点外侧补圆 / 尖角 / 切角，两端按线端补圆或外延半个线宽。只有一个点时圆头画圆点、
+    /// 方头按水平方向画方块、平头不绘制。
+    fn outline(&self, path: &[(f32, f32)], closed: bool, out: &mut Vec<Part>) {
+        let half = self.width / 2.0;
+        let mut pts: Vec<(f32, f32)> = Vec::with_capacity(path.len());
+        for &p in path {
+            if pts.last() != Some(&p) {
+                pts.push(p);
+            }
+        }
+        if closed && pts.len() > 2 && pts.first() == pts.last() {
+            pts.pop();
+        }
+        let n = pts.len();
+        if n == 1 {
+            let p = pts[0];
+            match self.cap {
+                LineCap::Round => out.push(Part::Disc(p, half)),
+                LineCap::Square => out.push(band((p.0 - half, p.1), (p.0 + half, p.1), half)),
+                LineCap::Butt => {}
+            }
+            return;
+        }
+        if n < 2 {
+            return;
+        }
+        let closed = closed && n > 2;
+        let edges = if closed { n } else { n - 1 };
+        let edge = |i: usize| (pts[i], pts[(i + 1) % n]);
+        if self.join == LineJoin::Round && (closed || self.cap == LineCap::Round) {
+            out.extend((0..edges).map(|i| Part::Capsule(edge(i), half)));
+            return;
+        }
+        out.extend((0..edges).map(|i| {
+            let (p, q) = edge(i);
+            band(p, q, half)
+        }));
+        let joints = if closed { 0..n } else { 1..n - 1 };
+        for i in joints {
+            self.join_part(pts[(i + n - 1) % n], pts[i], pts[(i + 1) % n], out);
+        }
+        if closed {
+            return;
+        }
+        for (end, from) in [(pts[0], pts[1]), (pts[n - 1], pts[n - 2])] {
+            match self.cap {
+                LineCap::Butt => {}
+                LineCap::Round => out.push(Part::Disc(end, half)),
+                LineCap::Square => {
+                    let (ux, uy) = direction(from, end);
+                    out.push(band(end, (end.0 + ux * half, end.1 + uy * half), half));
+                }
+            }
+        }
+    }
+
+    /// 拐点 v 处（prev -> v -> next）外侧的连接部件
+    fn join_part(&self, prev: (f32, f32), v: (f32, f32), next: (f32, f32), out: &mut Vec<Part>) {
+        let half = self.width / 2.0;
+        if self.join == LineJoin::Round {
+            out.push(Part::Disc(v, half));
+            return;
+        }
+        let (u1, u2) = (direction(prev, v), direction(v, next));
+        let cross = u1.0 * u2.1 - u1.1 * u2.0;
+        if cross.abs() < 1e-6 {
+            // 共线：同向无缝隙；折返时 Miter / Bevel 都没有外侧
+            return;
+        }
+        // 外侧法向与转向相反
+        let s = -cross.signum() * half;
+        let a = (v.0 - u1.1 * s, v.1 + u1.0 * s);
+        let b = (v.0 - u2.1 * s, v.1 + u2.0 * s);
+        if self.join == LineJoin::Miter {
+            // 尖端沿两条外侧法向的角平分线，距离为 half / cos(θ / 2)
+            let (mx, my) = direction(v, ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0));
+            let cos = ((a.0 - v.0) * mx + (a.1 - v.1) * my) / half;
+            if cos > 0.0 && 1.0 / cos <= LineJoin::MITER_LIMIT {
+                let tip = (v.0 + mx * half / cos, v.1 + my * half / cos);
+                out.push(Part::Convex(SmallVec::from_slice(&[v, a, tip, b])));
+                return;
+            }
+        }
+        out.push(Part::Convex(SmallVec::from_slice(&[v, a, b])));
+    }
+
+    fn parts(&self, path: &[(f32, f32)], closed: bool) -> Vec<Part> {
+        let mut out = Vec::new();
+        self.outline(path, closed, &mut out);
+        out
+    }
+}
+
+/// 描边的实心部件；覆盖率由像素中心到边界的有向距离决定：边界上为 0.5，向内外各 0.5px 线性过渡
+#[derive(Debug, Clone)]
+enum Part {
+    /// 圆头线段：到线段距离不超过半径的区域
+    Capsule(Segment, f32),
+    /// 凸多边形（平头线段、方头、尖角 / 切角连接、三角形箭头），顶点顺序不限
+    Convex(SmallVec<[(f32, f32); 4]>),
+    /// 圆心与半径
+    Disc((f32, f32), f32),
+}
+
+impl Part {
+    fn area(&self) -> Option<Rect> {
+        match self {
+            Part::Capsule((p, q), r) => Rect::bounding([*p, *q]).map(|b| b.inflate(*r)),
+            Part::Convex(pts) => Rect::bounding(pts.iter().copied()),
+            Part::Disc((cx, cy), r) => Some(Rect {
+                x: cx - r,
+                y: cy - r,
+                w: 2.0 * r,
+                h: 2.0 * r,
+            }),
+        }
+    }
 
-    // 串行应用颜色到图像（避免数据竞争）
-    // 由于每个块写入不同区域，这里也可以并行，但为了简单起见先串行
-    for (bx, by, bw, bh, r, g, b, a) in block_colors {
-        for yy in by..by + bh {
-            for xx in bx..bx + bw {
-                let i = ((yy as u32 * img.width + xx as u32) * 4) as usize;
-                if i + 3 < img.pixels.len() {
-                    img.pixels[i] = r;
-                    img.pixels[i + 1] = g;
-                    img.pixels[i + 2] = b;
-                    img.pixels[i + 3] = a;
+    fn coverage(&self, p: (f32, f32)) -> f32 {
+        match self {
+            Part::Capsule((a, b), r) => (r + 0.5 - distance_to_segment(p, *a, *b)).clamp(0.0, 1.0),
+            Part::Disc(c, r) => {
+                let d = ((p.0 - c.0).powi(2) + (p.1 - c.1).powi(2)).sqrt();
+                (r + 0.5 - d).clamp(0.0, 1.0)
+            }
+            Part::Convex(pts) => {
+                let n = pts.len();
+                let area2: f32 = (0..n)
+                    .map(|i| {
+                        let (a, b) = (pts[i], pts[(i + 1) % n]);
+                        a.0 * b.1 - b.0 * a.1
+                    })
+                    .sum();
+                if n < 3 || area2.abs() < 1e-6 {
+                    return 0.0;
+                }
+                // 内部：到各边所在直线的最小距离（凸多边形内即到边界的距离）
+                let mut inside = f32::MAX;
+                for i in 0..n {
+                    let (a, b) = (pts[i], pts[(i + 1) % n]);
+                    let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+                    let len = (ex * ex + ey * ey).sqrt();
+                    if len > 0.0 {
+                        let d = area2.signum() * (ex * (p.1 - a.1) - ey * (p.0 - a.0)) / len;
+                        inside = inside.min(d);
+                    }
+                }
+                if inside >= 0.0 {
+                    return (0.5 + inside).min(1.0);
                 }
+                let ring: SmallVec<[(f32, f32); 5]> =
+                    pts.iter().chain(pts.first()).copied().collect();
+                (0.5 - distance_to_polyline(p, &ring)).max(0.0)
+            }
+        }
+    }
+}
+
+/// 抗锯齿描边：各部件覆盖率取最大值后每个像素只混合一次，半透明时重叠处不会加深
+fn stroke_aa(tile: &mut Tile, parts: &[Part], (r, g, b, a): (u8, u8, u8, u8)) {
+    stroke_coverage(tile, parts, |dst, c| {
+        let alpha = (a as f32 * c).round() as u8;
+        blend_over(dst, r, g, b, alpha);
+    });
+}
+
+/// 将部件栅格化到覆盖率缓冲（各部分取最大值），再对覆盖率非 0 的像素各调用一次 blend；
+/// 缓冲只覆盖包围盒落在本分块内的部分
+fn stroke_coverage(tile: &mut Tile, parts: &[Part], mut blend: impl FnMut(&mut [u8], f32)) {
+    let areas: Vec<Option<Rect>> = parts.iter().map(Part::area).collect();
+    let Some(bb) = Rect::bounding(areas.iter().flatten().flat_map(|r| r.corners())) else {
+        return;
+    };
+    let (x0, y0, x1, y1) = tile.span(&bb.inflate(1.0));
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+    let bw = (x1 - x0) as usize;
+    let mut coverage = vec![0f32; bw * (y1 - y0) as usize];
+    // 只遍历每个部件自身的包围盒
+    for (part, area) in parts.iter().zip(&areas) {
+        let Some(area) = area.map(|a| a.inflate(1.0)) else {
+            continue;
+        };
+        let xs = (area.x.floor() as i64).max(x0);
+        let ys = (area.y.floor() as i64).max(y0);
+        let xe = ((area.x + area.w).ceil() as i64).min(x1);
+        let ye = ((area.y + area.h).ceil() as i64).min(y1);
+        for y in ys..ye {
+            for x in xs..xe {
+                let c = &mut coverage[(y - y0) as usize * bw + (x - x0) as usize];
+                *c = c.max(part.coverage((x as f32 + 0.5, y as f32 + 0.5)));
+            }
+        }
+    }
+    for (row, line) in coverage.chunks_exact(bw).enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            if c <= 0.0 {
+                continue;
             }
+            blend(tile.px(x0 as u32 + col as u32, y0 as u32 + row as u32), c);
+        }
+    }
+}
+
+/// Freehand / Highlighter 的平滑：smoothing（0..1）对应 0~3 次 Chaikin 细分，点数超过 4096 时提前停止
+fn smooth_points(points: &[(f32, f32)], smoothing: f32) -> Vec<(f32, f32)> {
+    let passes = if smoothing <= 0.0 {
+        0
+    } else if smoothing < 0.34 {
+        1
+    } else if smoothing < 0.67 {
+        2
+    } else {
+        3
+    };
+    let mut pts = points.to_vec();
+    for _ in 0..passes {
+        pts = chaikin(&pts);
+        if pts.len() > 4096 {
+            break;
         }
     }
+    pts
 }
 
 fn chaikin(pts: &[(f32, f32)]) -> Vec<(f32, f32)> {
@@ -549,172 +2348,12 @@ impl ExportEncoder for PngEncoder {
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn draw_thick_line(
-    img: &mut Image,
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
-    th: i32,
-    (r, g, b): (u8, u8, u8),
-    a: u8,
-) {
-    // Bresenham 基础实现, 对每个像素扩展一个圆形近似的方形厚度
-    let dx = (x1 - x0).abs();
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let dy = -(y1 - y0).abs();
-    let sy = if y0 < y1 { 1 } else { -1 };
-    let mut err = dx + dy;
-    let mut x = x0;
-    let mut y = y0;
-    loop {
-        for oy in -th / 2..=th / 2 {
-            for ox in -th / 2..=th / 2 {
-                blend_fill_rect(img, x + ox, y + oy, 1, 1, r, g, b, a);
-            }
-        }
-        if x == x1 && y == y1 {
-            break;
-        }
-        let e2 = 2 * err;
-        if e2 >= dy {
-            err += dy;
-            x += sx;
-        }
-        if e2 <= dx {
-            err += dx;
-            y += sy;
-        }
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-fn draw_arrow_head(
-    img: &mut Image,
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
-    size: f32,
-    (r, g, b): (u8, u8, u8),
-    a: u8,
-) {
-    let dx = (x1 - x0) as f32;
-    let dy = (y1 - y0) as f32;
-    let len = (dx * dx + dy * dy).sqrt().max(1.0);
-    let ux = dx / len;
-    let uy = dy / len;
-    // 基础等腰三角: 尾点为终点 (x1,y1)
-    let bx = x1 as f32 - ux * size;
-    let by = y1 as f32 - uy * size;
-    let perp_x = -uy;
-    let perp_y = ux;
-    let w = size * 0.5;
-    let p1 = (x1 as f32, y1 as f32);
-    let p2 = (bx + perp_x * w, by + perp_y * w);
-    let p3 = (bx - perp_x * w, by - perp_y * w);
-    fill_triangle(img, p1, p2, p3, r, g, b, a);
-}
-
-#[allow(clippy::too_many_arguments)]
-fn fill_triangle(
-    img: &mut Image,
-    p1: (f32, f32),
-    p2: (f32, f32),
-    p3: (f32, f32),
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
-) {
-    let (x1, y1) = p1;
-    let (x2, y2) = p2;
-    let (x3, y3) = p3;
-    let min_x = x1.min(x2).min(x3).floor() as i32;
-    let max_x = x1.max(x2).max(x3).ceil() as i32;
-    let min_y = y1.min(y2).min(y3).floor() as i32;
-    let max_y = y1.max(y2).max(y3).ceil() as i32;
-    let area = (x2 - x1) * (y3 - y1) - (x3 - x1) * (y2 - y1);
-    if area.abs() < f32::EPSILON {
-        return;
-    }
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let px = x as f32 + 0.5;
-            let py = y as f32 + 0.5;
-            let w0 = (x2 - x1) * (py - y1) - (y2 - y1) * (px - x1);
-            let w1 = (x3 - x2) * (py - y2) - (y3 - y2) * (px - x2);
-            let w2 = (x1 - x3) * (py - y3) - (y1 - y3) * (px - x3);
-            if (w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0) || (w0 <= 0.0 && w1 <= 0.0 && w2 <= 0.0) {
-                blend_fill_rect(img, x, y, 1, 1, r, g, b, a);
-            }
-        }
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-fn draw_dashed_line(
-    img: &mut Image,
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
-    th: i32,
-    (r, g, b): (u8, u8, u8),
-    a: u8,
-) {
-    // 基于总长度拆分 dash(开) 与 gap(关)，dash_len = 4*th, gap_len = 2*th
-    let dx = (x1 - x0) as f32;
-    let dy = (y1 - y0) as f32;
-    let len = (dx * dx + dy * dy).sqrt();
-    if len == 0.0 {
-        return;
-    }
-    let ux = dx / len;
-    let uy = dy / len;
-    let dash_len = (4 * th).max(2) as f32;
-    let gap_len = (2 * th).max(1) as f32;
-    let mut _dist = 0.0;
-    let mut cur = 0.0;
-    while cur < len {
-        // 绘制 dash 段
-        let seg = (dash_len).min(len - cur);
-        // 将 seg 离散为若干像素段，使用插值调用 draw_thick_line 分段 (近似)
-        let steps = (seg.max(1.0)) as i32;
-        let start_x = x0 as f32 + ux * cur;
-        let start_y = y0 as f32 + uy * cur;
-        for s in 0..steps {
-            let t0 = s as f32 / steps as f32;
-            let t1 = (s + 1) as f32 / steps as f32;
-            if t0 >= 1.0 {
-                break;
-            }
-            let sx = start_x + ux * seg * t0;
-            let sy = start_y + uy * seg * t0;
-            let ex = start_x + ux * seg * t1;
-            let ey = start_y + uy * seg * t1;
-            draw_thick_line(
-                img,
-                sx.round() as i32,
-                sy.round() as i32,
-                ex.round() as i32,
-                ey.round() as i32,
-                th,
-                (r, g, b),
-                a,
-            );
-        }
-        cur += seg + gap_len;
-        _dist += seg + gap_len;
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::Utc;
-    use uuid::Uuid;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use screenshot_core::{ArrowHeadShape, ArrowHeads, MeasureMode};
+    use uuid::Uuid;
 
     fn make_rect(x: f32, y: f32, w: f32, h: f32, color: &str, opacity: f32, z: i32) -> Annotation {
         // 使用 v7 时间序列 UUID (测试场景无需稳定种子)
@@ -729,7 +2368,7 @@ mod tests {
                 rotation: 0,
                 opacity,
                 stroke_color: None,
-                fill_color: Some(color.to_string()),
+                fill_color: Some(Color::parse(color).unwrap()),
                 stroke_width: None,
                 z,
                 locked: false,
@@ -783,12 +2422,41 @@ mod tests {
         assert!(px[0] < 255 && px[2] > 0 && px[3] > 255 / 2); // blended, alpha > 127
     }
 
+    #[test]
+    fn test_color_alpha_composes_with_opacity() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(4, 4);
+        let by_color = r.render(
+            &frame,
+            &[make_rect(0.0, 0.0, 4.0, 4.0, "#0000FF80", 1.0, 0)],
+        );
+        let by_opacity = r.render(
+            &frame,
+            &[make_rect(
+                0.0,
+                0.0,
+                4.0,
+                4.0,
+                "rgb(0, 0, 255)",
+                128.0 / 255.0,
+                0,
+            )],
+        );
+        assert_eq!(by_color.pixels, by_opacity.pixels);
+        // 两者叠加：alpha 相乘
+        let both = r.render(
+            &frame,
+            &[make_rect(0.0, 0.0, 4.0, 4.0, "#0000FF80", 0.5, 0)],
+        );
+        assert_eq!(both.pixels[3], 64);
+    }
+
     #[test]
     fn test_rect_stroke() {
         let r = SimpleRenderer;
         // base transparent
         let mut rect = make_rect(1.0, 1.0, 4.0, 4.0, "#00FF00", 0.4, 0);
-        rect.meta.stroke_color = Some("#FF0000".into());
+        rect.meta.stroke_color = Some(Color::rgb(0xFF, 0x00, 0x00));
         rect.meta.stroke_width = Some(1.0);
         rect.meta.opacity = 1.0;
         let frame = dummy_frame(8, 8);
@@ -811,13 +2479,14 @@ mod tests {
         hl_meta.y = 0.0;
         hl_meta.w = 4.0;
         hl_meta.h = 4.0;
-        hl_meta.fill_color = Some("#0000FF".into());
+        hl_meta.fill_color = Some(Color::rgb(0x00, 0x00, 0xFF));
         hl_meta.opacity = 0.5;
         hl_meta.z = 1;
         let highlight = Annotation {
             meta: hl_meta,
             kind: AnnotationKind::Highlight {
                 mode: BlendMode::Screen,
+                corner_radius: 0,
             },
         };
         let frame = dummy_frame(4, 4);
@@ -841,7 +2510,7 @@ mod tests {
             h: 10.0,
             rotation: 0,
             opacity: 1.0,
-            stroke_color: Some("#00FF00".into()),
+            stroke_color: Some(Color::rgb(0x00, 0xFF, 0x00)),
             fill_color: None,
             stroke_width: Some(2.0),
             z: 0,
@@ -853,6 +2522,9 @@ mod tests {
             kind: AnnotationKind::Arrow {
                 head_size: 8,
                 line_style: screenshot_core::LineStyle::Solid,
+                controls: vec![],
+                heads: ArrowHeads::End,
+                head_shape: ArrowHeadShape::Triangle,
             },
         };
         let r = SimpleRenderer;
@@ -868,9 +2540,11 @@ mod tests {
             }
         }
         assert!(diag >= 5, "diag green pixels insufficient: {}", diag);
-        // 终点附近应更粗(箭头头部)，检查终点像素块
-        let end_idx = ((10 * 12 + 10) * 4) as usize;
+        // 终点附近应更粗(箭头头部)：尖端在 (10,10) 像素角上，检查其内侧与偏离线条的头部像素
+        let end_idx = ((9 * 12 + 9) * 4) as usize;
         assert!(img.pixels[end_idx + 1] > 150);
+        let side_idx = ((6 * 12 + 8) * 4) as usize;
+        assert!(img.pixels[side_idx + 1] > 150, "head wider than line");
     }
 
     #[test]
@@ -912,7 +2586,10 @@ mod tests {
         };
         let mosaic = Annotation {
             meta,
-            kind: AnnotationKind::Mosaic { level: 1 },
+            kind: AnnotationKind::Mosaic {
+                level: 1,
+                corner_radius: 0,
+            },
         };
         let img = r.render(&base, &[mosaic]);
         // Sample two pixels inside first block should be identical after mosaic
@@ -925,7 +2602,7 @@ mod tests {
     }
 
     #[test]
-    fn test_text_placeholder_render() {
+    fn test_text_render() {
         use chrono::Utc;
         use screenshot_core::{AnnotationKind, AnnotationMeta};
         use uuid::Uuid;
@@ -939,7 +2616,7 @@ mod tests {
             rotation: 0,
             opacity: 1.0,
             stroke_color: None,
-            fill_color: Some("#112233".into()),
+            fill_color: Some(Color::rgb(0x11, 0x22, 0x33)),
             stroke_width: None,
             z: 0,
             locked: false,
@@ -966,11 +2643,7 @@ mod tests {
                 }
             }
         }
-        assert!(
-            colored > 50,
-            "expected placeholder text blocks, got {}",
-            colored
-        );
+        assert!(colored > 50, "expected rasterized glyphs, got {}", colored);
     }
 
     #[test]
@@ -991,7 +2664,7 @@ mod tests {
             h: 0.0,
             rotation: 0,
             opacity: 1.0,
-            stroke_color: Some("#FF0000".into()),
+            stroke_color: Some(Color::rgb(0xFF, 0x00, 0x00)),
             fill_color: None,
             stroke_width: Some(2.0),
             z: 0,
@@ -1003,6 +2676,8 @@ mod tests {
             kind: AnnotationKind::Freehand {
                 points: pts.clone(),
                 smoothing: 1.0,
+                cap: LineCap::Round,
+                join: LineJoin::Round,
             },
         };
         let freehand_raw = Annotation {
@@ -1013,6 +2688,8 @@ mod tests {
             kind: AnnotationKind::Freehand {
                 points: pts.clone(),
                 smoothing: 0.0,
+                cap: LineCap::Round,
+                join: LineJoin::Round,
             },
         };
         let r = SimpleRenderer;
@@ -1045,7 +2722,7 @@ mod tests {
             h: 0.0,
             rotation: 0,
             opacity: 1.0,
-            stroke_color: Some("#00FF00".into()),
+            stroke_color: Some(Color::rgb(0x00, 0xFF, 0x00)),
             fill_color: None,
             stroke_width: Some(2.0),
             z: 0,
@@ -1061,7 +2738,7 @@ mod tests {
             h: 0.0,
             rotation: 0,
             opacity: 1.0,
-            stroke_color: Some("#00FF00".into()),
+            stroke_color: Some(Color::rgb(0x00, 0xFF, 0x00)),
             fill_color: None,
             stroke_width: Some(2.0),
             z: 0,
@@ -1073,6 +2750,9 @@ mod tests {
             kind: AnnotationKind::Arrow {
                 head_size: 8,
                 line_style: LineStyle::Solid,
+                controls: vec![],
+                heads: ArrowHeads::End,
+                head_shape: ArrowHeadShape::Triangle,
             },
         };
         let dashed = Annotation {
@@ -1080,6 +2760,9 @@ mod tests {
             kind: AnnotationKind::Arrow {
                 head_size: 8,
                 line_style: LineStyle::Dashed,
+                controls: vec![],
+                heads: ArrowHeads::End,
+                head_shape: ArrowHeadShape::Triangle,
             },
         };
         let r = SimpleRenderer;
@@ -1113,6 +2796,1430 @@ mod tests {
         );
     }
 
+    fn make_shape(x: f32, y: f32, w: f32, h: f32, kind: AnnotationKind) -> Annotation {
+        let ts = uuid::Timestamp::now(uuid::NoContext);
+        Annotation {
+            meta: screenshot_core::AnnotationMeta {
+                id: Uuid::new_v7(ts),
+                x,
+                y,
+                w,
+                h,
+                rotation: 0,
+                opacity: 1.0,
+                stroke_color: None,
+                fill_color: None,
+                stroke_width: None,
+                z: 0,
+                locked: false,
+                created_at: Utc::now(),
+            },
+            kind,
+        }
+    }
+
+    fn px(img: &Image, x: u32, y: u32) -> [u8; 4] {
+        let i = img.idx(x, y);
+        [
+            img.pixels[i],
+            img.pixels[i + 1],
+            img.pixels[i + 2],
+            img.pixels[i + 3],
+        ]
+    }
+
+    #[test]
+    fn test_ellipse_fill_and_stroke() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(24, 24);
+        let mut filled = make_shape(2.0, 2.0, 20.0, 20.0, AnnotationKind::Ellipse {});
+        filled.meta.fill_color = Some(Color::rgb(0, 0, 255));
+        filled.meta.opacity = 0.5;
+        let img = r.render(&frame, &[filled.clone()]);
+        assert_eq!(px(&img, 12, 12), [0, 0, 255, 127]);
+        // 包围盒角落不在椭圆内
+        assert_eq!(px(&img, 3, 3)[3], 0);
+        assert_eq!(px(&img, 2, 12)[2], 255, "left edge of the circle");
+
+        let mut ring = filled;
+        ring.meta.fill_color = None;
+        ring.meta.opacity = 1.0;
+        ring.meta.stroke_color = Some(Color::rgb(255, 0, 0));
+        ring.meta.stroke_width = Some(3.0);
+        let img = r.render(&frame, &[ring]);
+        assert_eq!(px(&img, 3, 12), [255, 0, 0, 255]);
+        assert_eq!(px(&img, 12, 12)[3], 0, "hollow center");
+        assert_eq!(px(&img, 12, 20), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_rotated_ellipse() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(30, 30);
+        // 宽 20 高 6 的横椭圆旋转 90° 后变成竖椭圆
+        let mut e = make_shape(5.0, 12.0, 20.0, 6.0, AnnotationKind::Ellipse {});
+        e.meta.fill_color = Some(Color::rgb(0, 255, 0));
+        e.meta.rotation = 90;
+        let img = r.render(&frame, &[e]);
+        assert_eq!(px(&img, 15, 7)[1], 255);
+        assert_eq!(px(&img, 7, 15)[3], 0);
+    }
+
+    #[test]
+    fn test_line_caps() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(40, 12);
+        let line = |cap| {
+            let mut l = make_shape(
+                10.0,
+                6.0,
+                20.0,
+                0.0,
+                AnnotationKind::Line {
+                    line_style: LineStyle::Solid,
+                    cap,
+                },
+            );
+            l.meta.stroke_color = Some(Color::rgb(255, 255, 0));
+            l.meta.stroke_width = Some(4.0);
+            l
+        };
+        let row_extent = |img: &Image| {
+            let xs: Vec<u32> = (0..40).filter(|&x| px(img, x, 6)[3] > 0).collect();
+            (xs[0], *xs.last().unwrap())
+        };
+        let butt = r.render(&frame, &[line(LineCap::Butt)]);
+        assert_eq!(row_extent(&butt), (10, 29));
+        // 线宽 4：y 覆盖 [4, 8)
+        assert_eq!(px(&butt, 20, 4)[3], 255);
+        assert_eq!(px(&butt, 20, 8)[3], 0);
+        let square = r.render(&frame, &[line(LineCap::Square)]);
+        assert_eq!(row_extent(&square), (8, 31));
+        assert_eq!(px(&square, 8, 4)[3], 255, "square corner");
+        let round = r.render(&frame, &[line(LineCap::Round)]);
+        assert_eq!(row_extent(&round), (8, 31));
+        // 圆头角上的像素只有部分覆盖
+        let corner = px(&round, 8, 4)[3];
+        assert!(
+            corner > 0 && corner < 128,
+            "round cap trims corners: {corner}"
+        );
+    }
+
+    #[test]
+    fn test_polyline_joins_and_miter_limit() {
+        let r = SimpleRenderer;
+        let frame = white_frame(30, 30);
+        let stroke = |points, join| {
+            let mut a = make_shape(
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                AnnotationKind::Polyline {
+                    points,
+                    line_style: LineStyle::Solid,
+                    start_arrow: false,
+                    end_arrow: false,
+                    head_size: 0,
+                    cap: LineCap::Butt,
+                    join,
+                },
+            );
+            a.meta.stroke_color = Some(Color::BLACK);
+            a.meta.stroke_width = Some(6.0);
+            r.render(&frame, &[a])
+        };
+        // 直角拐点：外角 (23, 23) 只有 Miter 补满，Bevel 切掉，Round 为半径 3 的圆角
+        let corner = || vec![(5.0, 20.0), (20.0, 20.0), (20.0, 5.0)];
+        let miter = stroke(corner(), LineJoin::Miter);
+        let bevel = stroke(corner(), LineJoin::Bevel);
+        let round = stroke(corner(), LineJoin::Round);
+        assert_eq!(px(&miter, 22, 22), [0, 0, 0, 255]);
+        assert_eq!(px(&bevel, 22, 22), [255, 255, 255, 255]);
+        assert_eq!(px(&round, 22, 22), [255, 255, 255, 255]);
+        assert_eq!(px(&round, 21, 21), [0, 0, 0, 255]);
+        // 平头：端点外不延伸
+        assert_eq!(px(&miter, 4, 20), [255, 255, 255, 255]);
+        // 近乎折返的尖角超过 MITER_LIMIT，退化为切角，不会伸出长刺
+        let spike = stroke(
+            vec![(5.0, 14.0), (22.0, 14.0), (5.0, 16.0)],
+            LineJoin::Miter,
+        );
+        for y in 8..22 {
+            assert_eq!(px(&spike, 27, y), [255, 255, 255, 255], "row {y}");
+        }
+    }
+
+    #[test]
+    fn test_rect_subpixel_coverage() {
+        let r = SimpleRenderer;
+        let frame = white_frame(12, 10);
+        let mut rect = make_shape(
+            2.5,
+            2.0,
+            5.0,
+            4.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        rect.meta.fill_color = Some(Color::BLACK);
+        let img = r.render(&frame, &[rect]);
+        // 左右边落在像素中间：边缘像素覆盖一半，而不是被截断到整数坐标
+        for x in [2, 7] {
+            let v = px(&img, x, 3)[0];
+            assert!(v.abs_diff(128) <= 2, "column {x}: {v}");
+        }
+        assert_eq!(px(&img, 3, 3), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 8, 3), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 4, 6), [255, 255, 255, 255]);
+
+        // 描边同样按面积覆盖：线宽 1.5 时内侧第二列只覆盖一半
+        let mut framed = make_shape(
+            2.0,
+            2.0,
+            8.0,
+            6.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        framed.meta.stroke_color = Some(Color::BLACK);
+        framed.meta.stroke_width = Some(1.5);
+        let img = r.render(&frame, &[framed]);
+        assert_eq!(px(&img, 2, 4), [0, 0, 0, 255]);
+        assert!(px(&img, 3, 4)[0].abs_diff(128) <= 2);
+        assert_eq!(px(&img, 4, 4), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_rounded_rect_fill_and_stroke() {
+        let r = SimpleRenderer;
+        let frame = white_frame(24, 24);
+        let mut filled = make_shape(
+            2.0,
+            2.0,
+            20.0,
+            20.0,
+            AnnotationKind::Rect { corner_radius: 6 },
+        );
+        filled.meta.fill_color = Some(Color::BLACK);
+        let img = r.render(&frame, &[filled.clone()]);
+        // 角点落在圆角之外，边的中点与中心完全覆盖，圆弧上的像素为部分覆盖
+        assert_eq!(px(&img, 2, 2), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 2, 12), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 12, 12), [0, 0, 0, 255]);
+        assert!((1..255).contains(&px(&img, 3, 4)[0]));
+
+        // 描边为同心圆角环：圆弧中段为线色，内部保持原图
+        filled.meta.fill_color = None;
+        filled.meta.stroke_color = Some(Color::BLACK);
+        filled.meta.stroke_width = Some(2.0);
+        let img = r.render(&frame, &[filled]);
+        assert_eq!(px(&img, 4, 4), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 2, 2), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 6, 6), [255, 255, 255, 255]);
+
+        // 半径超过短边一半时按短边一半处理（两端为半圆）
+        let pill = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: 20.0,
+            h: 10.0,
+        };
+        for y in 0..10 {
+            for x in 0..20 {
+                let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+                assert_eq!(
+                    rounded_rect_coverage(&pill, 255.0, fx, fy),
+                    rounded_rect_coverage(&pill, 5.0, fx, fy)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounded_mosaic_keeps_corners() {
+        let r = SimpleRenderer;
+        let frame = step_frame(20, 10);
+        // 第一块跨过黑白分界，平均为灰
+        let mosaic = make_shape(
+            7.0,
+            0.0,
+            12.0,
+            10.0,
+            AnnotationKind::Mosaic {
+                level: 1,
+                corner_radius: 4,
+            },
+        );
+        let img = r.render(&frame, &[mosaic]);
+        assert_eq!(px(&img, 7, 0), [0, 0, 0, 255]);
+        let gray = px(&img, 8, 5)[0];
+        assert!((1..255).contains(&gray));
+        assert_eq!(px(&img, 12, 0)[0], gray);
+    }
+
+    #[test]
+    fn test_dashed_line_single_blend() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(64, 8);
+        let mut l = make_shape(
+            0.0,
+            4.0,
+            60.0,
+            0.0,
+            AnnotationKind::Line {
+                line_style: LineStyle::Dashed,
+                cap: LineCap::Butt,
+            },
+        );
+        l.meta.stroke_color = Some(Color::rgb(0, 0, 0));
+        l.meta.stroke_width = Some(2.0);
+        l.meta.opacity = 0.5;
+        let img = r.render(&frame, &[l]);
+        // dash 8px / gap 4px；半透明像素只混合一次
+        let row: Vec<u8> = (0..64).map(|x| px(&img, x, 4)[3]).collect();
+        assert!(row[0..8].iter().all(|&a| a == 127));
+        assert!(row[8..12].iter().all(|&a| a == 0));
+        assert!(row[12..20].iter().all(|&a| a == 127));
+    }
+
+    #[test]
+    fn test_step_marker() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(40, 30);
+        // 包围盒 40x30：圆直径 30，居中于 (20, 15)
+        let mut m = make_shape(
+            0.0,
+            0.0,
+            40.0,
+            30.0,
+            AnnotationKind::StepMarker { number: 7 },
+        );
+        let img = r.render(&frame, &[m.clone()]);
+        assert_eq!(px(&img, 7, 15), [0xE5, 0x39, 0x35, 255], "default fill");
+        assert_eq!(px(&img, 2, 15)[3], 0, "outside the circle");
+        assert_eq!(px(&img, 20, 1)[3], 255);
+        let count = |img: &Image, c: [u8; 4]| {
+            (0..30)
+                .flat_map(|y| (0..40).map(move |x| (x, y)))
+                .filter(|&(x, y)| px(img, x, y) == c)
+                .count()
+        };
+        assert!(count(&img, [255, 255, 255, 255]) > 0, "white digit");
+
+        // 浅色底自动改用黑色数字；多位数字仍在圆内
+        m.meta.fill_color = Some(Color::rgb(0xFF, 0xEE, 0x00));
+        m.kind = AnnotationKind::StepMarker { number: 12 };
+        let img = r.render(&frame, &[m]);
+        assert_eq!(count(&img, [255, 255, 255, 255]), 0);
+        let dark: Vec<(u32, u32)> = (0..30)
+            .flat_map(|y| (0..40).map(move |x| (x, y)))
+            .filter(|&(x, y)| px(&img, x, y) == [0, 0, 0, 255])
+            .collect();
+        assert!(!dark.is_empty());
+        assert!(dark.iter().all(|&(x, _)| (6..34).contains(&x)));
+    }
+
+    /// 左半黑、右半白的不透明底图
+    fn step_frame(w: u32, h: u32) -> Frame {
+        let mut buf = Vec::with_capacity((w * h * 4) as usize);
+        for _ in 0..h {
+            for x in 0..w {
+                let v = if x < w / 2 { 0 } else { 255 };
+                buf.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        Frame {
+            width: w,
+            height: h,
+            pixel_format: PixelFormat::Rgba8,
+            bytes: std::sync::Arc::from(buf.into_boxed_slice()),
+        }
+    }
+
+    #[test]
+    fn test_gaussian_kernel() {
+        let k = gaussian_kernel(4);
+        assert_eq!(k.len(), 13);
+        assert!((k.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert_eq!(k[0], k[12]);
+        assert!(k[6] > k[5] && k[5] > k[4]);
+    }
+
+    #[test]
+    fn test_blur_clamps_at_image_borders() {
+        let r = SimpleRenderer;
+        let frame = step_frame(40, 12);
+        let blur = make_shape(0.0, 0.0, 40.0, 12.0, AnnotationKind::Blur { radius: 6 });
+        let img = r.render(&frame, &[blur]);
+        for y in [0, 5, 11] {
+            // 边缘采样夹到边界像素：四角与上下边不会被拉暗或变透明
+            assert_eq!(px(&img, 0, y), [0, 0, 0, 255]);
+            assert_eq!(px(&img, 39, y), [255, 255, 255, 255]);
+            // 黑白交界两侧对称过渡
+            let (a, b) = (px(&img, 19, y)[0] as i32, px(&img, 20, y)[0] as i32);
+            assert!(0 < a && a < 128 && 128 < b && b < 255, "{a} {b}");
+            assert!((a + b - 255).abs() <= 1);
+            assert_eq!(px(&img, 19, y), px(&img, 19, 5), "uniform along y");
+        }
+    }
+
+    #[test]
+    fn test_blur_samples_base_pixels() {
+        let r = SimpleRenderer;
+        let frame = step_frame(40, 12);
+        // 低层的红色矩形不参与模糊采样
+        let mut red = make_shape(
+            10.0,
+            0.0,
+            20.0,
+            12.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        red.meta.fill_color = Some(Color::rgb(255, 0, 0));
+        let mut blur = make_shape(16.0, 2.0, 8.0, 8.0, AnnotationKind::Blur { radius: 4 });
+        blur.meta.z = 1;
+        let img = r.render(&frame, &[red, blur.clone()]);
+        let only_blur = r.render(&frame, &[blur.clone()]);
+        assert_eq!(px(&img, 18, 5), px(&only_blur, 18, 5));
+        assert_eq!(px(&img, 12, 5), [255, 0, 0, 255], "outside the blur region");
+
+        blur.kind = AnnotationKind::Blur { radius: 0 };
+        assert_eq!(
+            r.render(&frame, &[blur]).pixels,
+            r.render(&frame, &[]).pixels
+        );
+    }
+
+    #[test]
+    fn test_rotated_blur() {
+        let r = SimpleRenderer;
+        let frame = step_frame(40, 40);
+        let mut blur = make_shape(10.0, 10.0, 20.0, 20.0, AnnotationKind::Blur { radius: 6 });
+        blur.meta.rotation = 45;
+        let img = r.render(&frame, &[blur]);
+        // 菱形中心在黑白交界，被模糊；包围盒角落在菱形外保持原样
+        assert!((1..255).contains(&px(&img, 20, 20)[0]));
+        assert!(px(&img, 21, 7)[0] < 255, "inside, near the top vertex");
+        assert_eq!(px(&img, 24, 5)[0], 255, "outside, next to the top vertex");
+        assert_eq!(px(&img, 11, 11), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 28, 11), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_redact_ignores_opacity_and_covers_partial_pixels() {
+        let r = SimpleRenderer;
+        let frame = step_frame(20, 10);
+        let mut red = make_shape(2.5, 2.5, 5.0, 5.0, AnnotationKind::Redact {});
+        red.meta.opacity = 0.0;
+        red.meta.fill_color = Some(Color::rgba(0x12, 0x34, 0x56, 0));
+        let img = r.render(&frame, &[red]);
+        // 覆盖 [2.5, 7.5) 相交的全部像素：2..8
+        for (x, y) in [(2, 2), (7, 7), (5, 5)] {
+            assert_eq!(px(&img, x, y), [0x12, 0x34, 0x56, 255]);
+        }
+        assert_eq!(px(&img, 1, 5), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 8, 5), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_redact_applies_before_sampling() {
+        let r = SimpleRenderer;
+        let frame = step_frame(40, 20);
+        let red = make_shape(14.0, 4.0, 12.0, 12.0, AnnotationKind::Redact {});
+        // 高层的模糊与马赛克跨过涂黑区域
+        let mut blur = make_shape(6.0, 0.0, 28.0, 10.0, AnnotationKind::Blur { radius: 6 });
+        blur.meta.z = 1;
+        let mut mosaic = make_shape(
+            6.0,
+            10.0,
+            28.0,
+            10.0,
+            AnnotationKind::Mosaic {
+                level: 1,
+                corner_radius: 0,
+            },
+        );
+        mosaic.meta.z = 1;
+        let mut rotated = make_shape(28.0, 2.0, 8.0, 8.0, AnnotationKind::Redact {});
+        rotated.meta.rotation = 30;
+        let anns = [red, blur, mosaic, rotated];
+
+        // 把原图中被涂掉的像素换成别的值，渲染结果必须完全一致
+        let img = r.render(&frame, &anns);
+        let redacted = r.render(&frame, &[anns[0].clone(), anns[3].clone()]);
+        let mut scrambled = frame.bytes.to_vec();
+        for y in 0..20u32 {
+            for x in 0..40u32 {
+                let covered = (14..26).contains(&x) && (4..16).contains(&y);
+                // 右半原本为白色，变黑即被旋转涂黑区域覆盖
+                if covered || x >= 20 && px(&redacted, x, y) == [0, 0, 0, 255] {
+                    let i = ((y * 40 + x) * 4) as usize;
+                    scrambled[i..i + 4].copy_from_slice(&[200, 10, 90, 255]);
+                }
+            }
+        }
+        let frame2 = Frame {
+            bytes: std::sync::Arc::from(scrambled.into_boxed_slice()),
+            ..frame
+        };
+        assert_eq!(r.render(&frame2, &anns).pixels, img.pixels);
+    }
+
+    fn white_frame(w: u32, h: u32) -> Frame {
+        Frame {
+            width: w,
+            height: h,
+            pixel_format: PixelFormat::Rgba8,
+            bytes: std::sync::Arc::from(vec![255u8; (w * h * 4) as usize].into_boxed_slice()),
+        }
+    }
+
+    fn spotlight(x: f32, y: f32, w: f32, h: f32, shape: SpotlightShape, feather: u8) -> Annotation {
+        let mut s = make_shape(x, y, w, h, AnnotationKind::Spotlight { shape, feather });
+        s.meta.opacity = 0.5;
+        s
+    }
+
+    #[test]
+    fn test_spotlight_dims_outside() {
+        let r = SimpleRenderer;
+        let frame = white_frame(40, 30);
+        let img = r.render(
+            &frame,
+            &[spotlight(10.0, 10.0, 20.0, 10.0, SpotlightShape::Rect, 0)],
+        );
+        assert_eq!(px(&img, 10, 10), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 29, 19), [255, 255, 255, 255]);
+        let dim = px(&img, 5, 5);
+        assert!(dim[0] < 140 && dim[0] > 110, "{dim:?}");
+        assert_eq!(px(&img, 35, 25), dim);
+
+        // 椭圆亮区：包围盒角落被压暗
+        let img = r.render(
+            &frame,
+            &[spotlight(
+                10.0,
+                10.0,
+                20.0,
+                10.0,
+                SpotlightShape::Ellipse,
+                0,
+            )],
+        );
+        assert_eq!(px(&img, 20, 15), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 10, 10), dim);
+
+        // 旋转 90° 后亮区变为竖条
+        let mut s = spotlight(10.0, 10.0, 20.0, 10.0, SpotlightShape::Rect, 0);
+        s.meta.rotation = 90;
+        let img = r.render(&frame, &[s]);
+        assert_eq!(px(&img, 20, 6), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 12, 15), dim);
+    }
+
+    #[test]
+    fn test_spotlights_union_without_compounding() {
+        let r = SimpleRenderer;
+        let frame = white_frame(60, 20);
+        let single = r.render(
+            &frame,
+            &[spotlight(5.0, 5.0, 10.0, 10.0, SpotlightShape::Rect, 0)],
+        );
+        let both = r.render(
+            &frame,
+            &[
+                spotlight(5.0, 5.0, 10.0, 10.0, SpotlightShape::Rect, 0),
+                spotlight(30.0, 5.0, 20.0, 10.0, SpotlightShape::Ellipse, 0),
+            ],
+        );
+        // 两个亮区都保持原样，外部只压暗一次
+        assert_eq!(px(&both, 8, 8), [255, 255, 255, 255]);
+        assert_eq!(px(&both, 40, 10), [255, 255, 255, 255]);
+        assert_eq!(px(&both, 22, 2), px(&single, 22, 2));
+        assert_ne!(px(&single, 40, 10), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_spotlight_feather_and_layering() {
+        let r = SimpleRenderer;
+        let frame = white_frame(60, 20);
+        let mut arrow_like = make_shape(
+            40.0,
+            0.0,
+            10.0,
+            10.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        arrow_like.meta.fill_color = Some(Color::rgb(255, 0, 0));
+        arrow_like.meta.z = 1;
+        let img = r.render(
+            &frame,
+            &[
+                spotlight(10.0, 0.0, 10.0, 20.0, SpotlightShape::Rect, 10),
+                arrow_like,
+            ],
+        );
+        let level = |x: u32| px(&img, x, 10)[0];
+        assert_eq!(level(19), 255);
+        // 亮区外 10px 内线性过渡，之后为完全压暗
+        assert!(
+            level(20) > level(24) && level(24) > level(28),
+            "monotonic falloff"
+        );
+        assert_eq!(level(30), level(35));
+        assert!(level(25) > level(35) && level(25) < 255);
+        // z 更高的标注画在压暗层之上
+        assert_eq!(px(&img, 45, 5), [255, 0, 0, 255]);
+    }
+
+    /// 红色通道为 x * 4 的横向渐变
+    fn ramp_frame(w: u32, h: u32) -> Frame {
+        let mut buf = Vec::with_capacity((w * h * 4) as usize);
+        for _ in 0..h {
+            for x in 0..w {
+                buf.extend_from_slice(&[(x * 4) as u8, 0, 0, 255]);
+            }
+        }
+        Frame {
+            width: w,
+            height: h,
+            pixel_format: PixelFormat::Rgba8,
+            bytes: std::sync::Arc::from(buf.into_boxed_slice()),
+        }
+    }
+
+    fn magnifier(shape: MagnifierShape, filter: SampleFilter) -> Annotation {
+        make_shape(
+            30.0,
+            0.0,
+            20.0,
+            20.0,
+            AnnotationKind::Magnifier {
+                source: (8.0, 8.0, 4.0, 4.0),
+                zoom: 4.0,
+                shape,
+                filter,
+            },
+        )
+    }
+
+    #[test]
+    fn test_magnifier_sampling() {
+        let r = SimpleRenderer;
+        let frame = ramp_frame(60, 20);
+        let mut nearest = magnifier(MagnifierShape::Rect, SampleFilter::Nearest);
+        nearest.meta.stroke_width = Some(0.0);
+        let img = r.render(&frame, &[nearest.clone()]);
+        // 插图中心对齐 source 中心 (10, 10)，每个底图像素放大为 4 像素宽
+        assert_eq!(px(&img, 36, 10)[0], 36);
+        assert_eq!(px(&img, 39, 10)[0], 36);
+        assert_eq!(px(&img, 40, 10)[0], 40);
+        assert_eq!(px(&img, 43, 10)[0], 40);
+        assert_eq!(px(&img, 44, 10)[0], 44);
+        // 插图外保持原样
+        assert_eq!(px(&img, 29, 10)[0], 116);
+
+        let mut bilinear = nearest.clone();
+        bilinear.kind = AnnotationKind::Magnifier {
+            source: (8.0, 8.0, 4.0, 4.0),
+            zoom: 4.0,
+            shape: MagnifierShape::Rect,
+            filter: SampleFilter::Bilinear,
+        };
+        let img = r.render(&frame, &[bilinear]);
+        // 采样点 10.125 落在像素 9、10 中心之间：36 * 0.375 + 40 * 0.625
+        assert_eq!(px(&img, 40, 10)[0], 39);
+        let row: Vec<u8> = (36..44).map(|x| px(&img, x, 10)[0]).collect();
+        assert!(row.windows(2).all(|w| w[0] < w[1]), "{row:?}");
+
+        // 插图叠在 source 上时仍从原始底图采样
+        let mut overlapping = nearest;
+        overlapping.meta.x = 0.0;
+        let img = r.render(&frame, &[overlapping]);
+        assert_eq!(px(&img, 10, 10)[0], 40);
+    }
+
+    #[test]
+    fn test_magnifier_border_and_shape() {
+        let r = SimpleRenderer;
+        let frame = ramp_frame(60, 20);
+        let img = r.render(
+            &frame,
+            &[magnifier(MagnifierShape::Circle, SampleFilter::Nearest)],
+        );
+        // 缺省白色 2px 边框，圆外的角落不受影响
+        assert_eq!(px(&img, 30, 10), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 31, 1), [124, 0, 0, 255]);
+        assert_eq!(px(&img, 33, 10)[0], 32);
+        // 圆形边框外缘按覆盖率混合
+        let clear = r.render(
+            &dummy_frame(60, 20),
+            &[magnifier(MagnifierShape::Circle, SampleFilter::Nearest)],
+        );
+        assert!(clear
+            .pixels
+            .chunks_exact(4)
+            .any(|p| (1..255).contains(&p[3])));
+
+        let mut rect = magnifier(MagnifierShape::Rect, SampleFilter::Nearest);
+        rect.meta.stroke_color = Some(Color::rgb(0, 0, 255));
+        rect.meta.stroke_width = Some(1.0);
+        let img = r.render(&frame, &[rect.clone()]);
+        assert_eq!(px(&img, 31, 0), [0, 0, 255, 255]);
+        assert_eq!(px(&img, 31, 1)[2], 0);
+
+        // 旋转只改变外形，放大内容保持正向
+        rect.meta.rotation = 45;
+        let img = r.render(&frame, &[rect]);
+        assert_eq!(px(&img, 31, 1), [124, 0, 0, 255]);
+        assert_eq!(px(&img, 40, 10)[0], 40);
+        assert_eq!(px(&img, 40, 13)[0], 40);
+    }
+
+    /// 2x2 贴图：红、绿 / 蓝、全透明
+    fn sticker_png() -> PngData {
+        let mut img = Image::new(2, 2);
+        img.pixels.copy_from_slice(&[
+            255, 0, 0, 255, 0, 255, 0, 255, //
+            0, 0, 255, 255, 0, 0, 0, 0,
+        ]);
+        PngData::new(PngEncoder.encode_png(&img).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_image_sticker_scales_and_composites() {
+        let r = SimpleRenderer;
+        let frame = white_frame(20, 10);
+        let png = sticker_png();
+        let sticker = |x: f32| {
+            make_shape(
+                x,
+                0.0,
+                8.0,
+                8.0,
+                AnnotationKind::Image {
+                    source: ImageSource::Embedded { png: png.clone() },
+                },
+            )
+        };
+        let mut faded = sticker(10.0);
+        faded.meta.opacity = 0.5;
+        let img = r.render(&frame, &[sticker(0.0), faded]);
+        // 每个贴图像素放大为 4x4，透明部分露出底图
+        assert_eq!(px(&img, 1, 1), [255, 0, 0, 255]);
+        assert_eq!(px(&img, 6, 1), [0, 255, 0, 255]);
+        assert_eq!(px(&img, 1, 6), [0, 0, 255, 255]);
+        assert_eq!(px(&img, 6, 6), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 9, 1), [255, 255, 255, 255]);
+        // opacity 与图片 alpha 相乘
+        let half = px(&img, 11, 1);
+        assert!((126..=129).contains(&half[1]) && half[0] == 255, "{half:?}");
+
+        // 未解析的资源引用与损坏数据不绘制
+        let mut missing = sticker(0.0);
+        missing.kind = AnnotationKind::Image {
+            source: ImageSource::Asset { id: Uuid::now_v7() },
+        };
+        let mut broken = sticker(10.0);
+        let mut bytes = png.as_bytes().to_vec();
+        bytes.truncate(20);
+        broken.kind = AnnotationKind::Image {
+            source: ImageSource::Embedded {
+                png: PngData::new(bytes).unwrap(),
+            },
+        };
+        let img = r.render(&frame, &[missing, broken]);
+        assert!(img.pixels.iter().all(|&v| v == 255));
+    }
+
+    #[test]
+    fn test_polygon_fill() {
+        let r = SimpleRenderer;
+        let frame = white_frame(30, 30);
+        let l_shape = vec![
+            (2.0, 2.0),
+            (22.0, 2.0),
+            (22.0, 12.0),
+            (12.0, 12.0),
+            (12.0, 22.0),
+            (2.0, 22.0),
+        ];
+        let mut poly = make_shape(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Polygon {
+                points: l_shape,
+                join: LineJoin::Round,
+            },
+        );
+        poly.meta.fill_color = Some(Color::BLACK);
+        let img = r.render(&frame, &[poly]);
+        // 边落在整数坐标上时像素要么全覆盖要么不覆盖，凹口保持原样
+        assert_eq!(px(&img, 7, 17), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 17, 17), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 21, 3), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 22, 3), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 1, 3), [255, 255, 255, 255]);
+
+        // 自相交的五角星：非零环绕规则下中心五边形也被填充
+        let star = vec![
+            (15.0, 2.0),
+            (23.0, 27.0),
+            (2.0, 11.0),
+            (28.0, 11.0),
+            (7.0, 27.0),
+        ];
+        let mut poly = make_shape(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Polygon {
+                points: star,
+                join: LineJoin::Round,
+            },
+        );
+        poly.meta.fill_color = Some(Color::BLACK);
+        let img = r.render(&frame, &[poly]);
+        assert_eq!(px(&img, 15, 16), [0, 0, 0, 255]);
+        assert_eq!(px(&img, 15, 26), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_polygon_scanline_matches_per_pixel_reference() {
+        use screenshot_core::point_in_polygon;
+        // 逐像素参考：对包围盒内每个像素做内外判断并取到全部边的距离
+        let reference = |img: &mut Image, points: &[(f32, f32)]| {
+            let ring: Vec<_> = points.iter().chain(points.first()).copied().collect();
+            for y in 0..img.height {
+                for x in 0..img.width {
+                    let p = (x as f32 + 0.5, y as f32 + 0.5);
+                    let d = distance_to_polyline(p, &ring);
+                    let c = if point_in_polygon(p, points) {
+                        (0.5 + d).min(1.0)
+                    } else {
+                        (0.5 - d).max(0.0)
+                    };
+                    if c > 0.0 {
+                        let i = img.idx(x, y);
+                        blend_over(
+                            &mut img.pixels[i..i + 4],
+                            20,
+                            40,
+                            200,
+                            (200.0 * c).round() as u8,
+                        );
+                    }
+                }
+            }
+        };
+        let shapes: [&[(f32, f32)]; 4] = [
+            // 亚像素坐标的凹多边形，含水平边
+            &[
+                (2.3, 2.7),
+                (30.6, 2.7),
+                (30.6, 14.2),
+                (14.4, 14.2),
+                (14.4, 33.9),
+                (2.3, 33.9),
+            ],
+            // 自相交五角星（非零环绕）
+            &[
+                (20.2, 1.5),
+                (31.1, 36.4),
+                (2.2, 14.1),
+                (37.8, 14.1),
+                (8.9, 36.4),
+            ],
+            // 细长三角形：整行都是边缘像素
+            &[(1.0, 1.0), (39.0, 6.5), (1.0, 3.2)],
+            // 顶点超出画布
+            &[(-10.0, 20.0), (20.0, -8.0), (50.0, 20.0), (20.0, 48.0)],
+        ];
+        for points in shapes {
+            let mut expected = Image::new(40, 40);
+            expected.pixels.fill(255);
+            let mut actual = Image::new(40, 40);
+            actual.pixels.fill(255);
+            reference(&mut expected, points);
+            fill_polygon(&mut actual.tile(), points, (20, 40, 200, 200));
+            assert!(actual.pixels == expected.pixels, "{points:?}");
+        }
+    }
+
+    #[test]
+    fn test_polygon_stroke_is_antialiased() {
+        let r = SimpleRenderer;
+        let frame = white_frame(30, 30);
+        let square = vec![(5.0, 5.25), (25.0, 5.25), (25.0, 25.0), (5.0, 25.0)];
+        let mut poly = make_shape(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Polygon {
+                points: square,
+                join: LineJoin::Round,
+            },
+        );
+        poly.meta.stroke_color = Some(Color::BLACK);
+        poly.meta.stroke_width = Some(2.0);
+        let img = r.render(&frame, &[poly]);
+        // 线宽 2 以 y = 5.25 为中心：覆盖率 = 1.5 - 像素中心距离，描边不填充内部
+        assert_eq!(px(&img, 15, 5), [0, 0, 0, 255]);
+        for (y, expect) in [(4, 64), (6, 191)] {
+            let v = px(&img, 15, y)[0];
+            assert!(v.abs_diff(expect) <= 2, "row {y}: {v}");
+        }
+        assert_eq!(px(&img, 15, 3), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 15, 15), [255, 255, 255, 255]);
+    }
+
+    fn polyline(points: Vec<(f32, f32)>, line_style: LineStyle, end_arrow: bool) -> Annotation {
+        let mut a = make_shape(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Polyline {
+                points,
+                line_style,
+                start_arrow: false,
+                end_arrow,
+                head_size: 8,
+                cap: LineCap::Round,
+                join: LineJoin::Round,
+            },
+        );
+        a.meta.stroke_color = Some(Color::BLACK);
+        a.meta.stroke_width = Some(2.0);
+        a
+    }
+
+    #[test]
+    fn test_polyline_arrows_dashes_and_overlap() {
+        let r = SimpleRenderer;
+        let frame = white_frame(40, 40);
+        let plain = r.render(
+            &frame,
+            &[polyline(
+                vec![(5.0, 20.0), (25.0, 20.0)],
+                LineStyle::Solid,
+                false,
+            )],
+        );
+        let arrow = r.render(
+            &frame,
+            &[polyline(
+                vec![(5.0, 20.0), (25.0, 20.0)],
+                LineStyle::Solid,
+                true,
+            )],
+        );
+        assert_eq!(px(&plain, 19, 21), [255, 255, 255, 255]);
+        assert_eq!(px(&arrow, 19, 21), [0, 0, 0, 255], "end arrow head");
+        assert_eq!(px(&arrow, 7, 22), [255, 255, 255, 255], "no start arrow");
+
+        // 半透明折线折返重叠处不会加深
+        let mut there = polyline(vec![(5.0, 10.0), (25.0, 10.0)], LineStyle::Solid, false);
+        there.meta.opacity = 0.5;
+        let mut back = there.clone();
+        back.kind = AnnotationKind::Polyline {
+            points: vec![(5.0, 10.0), (25.0, 10.0), (5.0, 10.0)],
+            line_style: LineStyle::Solid,
+            start_arrow: false,
+            end_arrow: false,
+            head_size: 8,
+            cap: LineCap::Round,
+            join: LineJoin::Round,
+        };
+        let once = r.render(&frame, &[there]);
+        let twice = r.render(&frame, &[back]);
+        assert_eq!(px(&once, 15, 10), px(&twice, 15, 10));
+        assert_ne!(px(&once, 15, 10), [0, 0, 0, 255]);
+
+        // 虚线：dash = 8、gap = 4（线宽 2）
+        let dashed = r.render(
+            &frame,
+            &[polyline(
+                vec![(2.0, 30.0), (38.0, 30.0)],
+                LineStyle::Dashed,
+                false,
+            )],
+        );
+        assert_eq!(px(&dashed, 5, 30), [0, 0, 0, 255]);
+        assert_eq!(px(&dashed, 12, 30), [255, 255, 255, 255]);
+        assert_eq!(px(&dashed, 17, 30), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_dash_path_continues_across_vertices() {
+        let dashes = dash_path(&[(0.0, 0.0), (6.0, 0.0), (6.0, 10.0)], 8.0, 4.0);
+        // 第一段 dash 跨过折点：0..6 + 折点后 2，保留折点以便按连接样式描边
+        assert_eq!(dashes[0], vec![(0.0, 0.0), (6.0, 0.0), (6.0, 2.0)]);
+        assert_eq!(dashes[1], vec![(6.0, 6.0), (6.0, 10.0)]);
+        assert_eq!(dashes.len(), 2);
+    }
+
+    fn arrow(
+        from: (f32, f32),
+        to: (f32, f32),
+        controls: Vec<(f32, f32)>,
+        line_style: LineStyle,
+        heads: ArrowHeads,
+        head_shape: ArrowHeadShape,
+    ) -> Annotation {
+        let mut a = make_shape(
+            from.0,
+            from.1,
+            to.0 - from.0,
+            to.1 - from.1,
+            AnnotationKind::Arrow {
+                head_size: 8,
+                line_style,
+                controls,
+                heads,
+                head_shape,
+            },
+        );
+        a.meta.stroke_color = Some(Color::BLACK);
+        a.meta.stroke_width = Some(2.0);
+        a
+    }
+
+    #[test]
+    fn test_curved_arrow_with_both_heads() {
+        let r = SimpleRenderer;
+        let frame = white_frame(64, 40);
+        // 二次曲线 (4,30) -> (60,30)，控制点 (32,-2)：曲线顶点为 (32,14)
+        let curve = |line_style, head_shape| {
+            arrow(
+                (4.0, 30.0),
+                (60.0, 30.0),
+                vec![(32.0, -2.0)],
+                line_style,
+                ArrowHeads::Both,
+                head_shape,
+            )
+        };
+        let black = |img: &Image| img.pixels.chunks_exact(4).filter(|p| p[0] < 128).count();
+
+        let discs = r.render(&frame, &[curve(LineStyle::Solid, ArrowHeadShape::Circle)]);
+        assert_eq!(px(&discs, 32, 14), [0, 0, 0, 255], "curve apex");
+        assert_eq!(px(&discs, 32, 29), [255, 255, 255, 255], "chord");
+        assert_eq!(px(&discs, 1, 30), [0, 0, 0, 255], "start disc");
+        assert_eq!(px(&discs, 62, 31), [0, 0, 0, 255], "end disc");
+
+        // 虚线只作用于线条，两端三角头部完整且沿曲线切线方向
+        let solid = r.render(&frame, &[curve(LineStyle::Solid, ArrowHeadShape::Triangle)]);
+        let dashed = r.render(
+            &frame,
+            &[curve(LineStyle::Dashed, ArrowHeadShape::Triangle)],
+        );
+        for img in [&solid, &dashed] {
+            assert_eq!(px(img, 7, 26), [0, 0, 0, 255], "start head");
+            assert_eq!(px(img, 56, 26), [0, 0, 0, 255], "end head");
+        }
+        assert!(black(&dashed) < black(&solid) * 9 / 10);
+        assert!(black(&dashed) > black(&solid) / 2);
+    }
+
+    #[test]
+    fn test_arrow_head_shapes() {
+        let r = SimpleRenderer;
+        let frame = white_frame(48, 16);
+        let straight = |head_shape| {
+            let a = arrow(
+                (4.0, 8.0),
+                (40.0, 8.0),
+                vec![],
+                LineStyle::Solid,
+                ArrowHeads::End,
+                head_shape,
+            );
+            r.render(&frame, &[a])
+        };
+        // 三角形头部处线条被裁到底边，尖端外没有线帽
+        let tri = straight(ArrowHeadShape::Triangle);
+        assert_eq!(px(&tri, 40, 8), [255, 255, 255, 255]);
+        assert_eq!(px(&tri, 35, 8), [0, 0, 0, 255]);
+        // V 形只描两翼，线条延伸到尖端
+        let open = straight(ArrowHeadShape::OpenV);
+        assert_ne!(px(&open, 40, 8), [255, 255, 255, 255]);
+        assert_eq!(px(&open, 35, 10), [0, 0, 0, 255], "wing");
+        assert_eq!(px(&tri, 34, 6), [0, 0, 0, 255]);
+        assert_ne!(px(&open, 34, 6), [0, 0, 0, 255], "open inside");
+        // 横线垂直于线条
+        let bar = straight(ArrowHeadShape::Bar);
+        assert_eq!(px(&bar, 39, 5), [0, 0, 0, 255]);
+        assert_eq!(px(&bar, 39, 11), [0, 0, 0, 255]);
+        assert_eq!(px(&bar, 36, 5), [255, 255, 255, 255]);
+    }
+
+    fn highlighter(points: Vec<(f32, f32)>, opacity: f32) -> Annotation {
+        let mut a = make_shape(
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            AnnotationKind::Highlighter {
+                points,
+                smoothing: 0.0,
+            },
+        );
+        a.meta.stroke_color = Some(Color::rgb(200, 100, 50));
+        a.meta.opacity = opacity;
+        a
+    }
+
+    #[test]
+    fn test_highlighter_multiplies_once_per_stroke() {
+        let r = SimpleRenderer;
+        let frame = white_frame(40, 24);
+        // 缺省线宽 16：y = 10.5 上下各 8px
+        let once = r.render(&frame, &[highlighter(vec![(5.0, 10.5), (35.0, 10.5)], 1.0)]);
+        assert_eq!(px(&once, 20, 3), [200, 100, 50, 255]);
+        assert_eq!(px(&once, 20, 1), [255, 255, 255, 255]);
+
+        // 暗色内容经 Multiply 保持可读，不被颜色覆盖
+        let mut ink = make_shape(
+            18.0,
+            8.0,
+            4.0,
+            4.0,
+            AnnotationKind::Rect { corner_radius: 0 },
+        );
+        ink.meta.fill_color = Some(Color::BLACK);
+        let mut stroke = highlighter(vec![(5.0, 10.5), (35.0, 10.5)], 1.0);
+        stroke.meta.z = 1;
+        let over_ink = r.render(&frame, &[ink, stroke]);
+        assert_eq!(px(&over_ink, 20, 10), [0, 0, 0, 255]);
+
+        // 半透明时同一笔折返重叠不加深，两笔交叠才加深
+        let single = r.render(&frame, &[highlighter(vec![(5.0, 10.5), (35.0, 10.5)], 0.5)]);
+        let back = r.render(
+            &frame,
+            &[highlighter(
+                vec![(5.0, 10.5), (35.0, 10.5), (5.0, 10.5)],
+                0.5,
+            )],
+        );
+        assert_eq!(px(&single, 20, 10), px(&back, 20, 10));
+        assert_ne!(px(&single, 20, 10), [200, 100, 50, 255], "semi-transparent");
+        let two = r.render(
+            &frame,
+            &[
+                highlighter(vec![(5.0, 10.5), (35.0, 10.5)], 0.5),
+                highlighter(vec![(20.5, 0.0), (20.5, 24.0)], 0.5),
+            ],
+        );
+        assert!(px(&two, 20, 10)[1] < px(&single, 20, 10)[1]);
+    }
+
+    #[test]
+    fn test_measure_render() {
+        let r = SimpleRenderer;
+        let frame = white_frame(120, 60);
+        let color = [0xFF, 0x2D, 0x55, 255];
+        let white = [255, 255, 255, 255];
+        let measure = |x, y, w, h, mode| make_shape(x, y, w, h, AnnotationKind::Measure { mode });
+        let count = |img: &Image, c: [u8; 4]| {
+            (0..60)
+                .flat_map(|y| (0..120).map(move |x| (x, y)))
+                .filter(|&(x, y)| px(img, x, y) == c)
+                .count()
+        };
+
+        // 像素中心上的水平尺寸线 (10.5,20.5) -> (110.5,20.5)，标签 "100px" 居中压线
+        let line = r.render(
+            &frame,
+            &[measure(10.5, 20.5, 100.0, 0.0, MeasureMode::Line)],
+        );
+        assert_eq!(px(&line, 10, 17), color, "tick");
+        assert_eq!(px(&line, 110, 23), color, "tick");
+        assert_eq!(px(&line, 30, 20), color, "dimension line");
+        assert_eq!(px(&line, 30, 18), white);
+        assert_eq!(px(&line, 42, 13), color, "label background");
+        assert!(px(&line, 46, 19)[1] > 200, "label ink");
+        assert_eq!(px(&line, 60, 10), white, "above the label");
+
+        // 截图 scale 为 2 时附加逻辑点，标签更长
+        let retina = r.render_scaled(
+            &frame,
+            &[measure(10.5, 20.5, 100.0, 0.0, MeasureMode::Line)],
+            2.0,
+        );
+        assert_eq!(px(&retina, 30, 15), color, "wider label");
+        assert!(count(&retina, color) > count(&line, color));
+
+        // Rect 模式：上边与左边各一条尺寸线，对边不画
+        let rect = r.render(
+            &frame,
+            &[measure(20.5, 20.5, 60.0, 30.0, MeasureMode::Rect)],
+        );
+        assert_eq!(px(&rect, 20, 47), color, "height line");
+        assert_eq!(px(&rect, 24, 20), color, "width line");
+        assert_eq!(px(&rect, 80, 40), white, "right edge");
+        assert_eq!(px(&rect, 60, 50), white, "bottom edge");
+    }
+
+    #[test]
+    fn test_inset_triangle() {
+        // 直角边 4、3 的三角形内切圆半径为 1、内心 (1, 1)；各边内移 0.5 后直角顶点到 (0.5, 0.5)
+        let t = [(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)];
+        let inner = inset_triangle(t, 0.5).unwrap();
+        assert!((inner[0].0 - 0.5).abs() < 1e-5 && (inner[0].1 - 0.5).abs() < 1e-5);
+        assert!(
+            (triangle_area2(inner) - 3.0).abs() < 1e-4,
+            "quarter of the area"
+        );
+        assert!(inset_triangle(t, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_callout_render() {
+        let r = SimpleRenderer;
+        let frame = dummy_frame(60, 50);
+        let mut c = make_shape(
+            0.0,
+            0.0,
+            60.0,
+            30.0,
+            AnnotationKind::Callout {
+                content: "ab cd".into(),
+                font_size: 10,
+                anchor: (20.0, 48.0),
+                corner_radius: 6,
+            },
+        );
+        c.meta.stroke_color = Some(Color::rgb(255, 0, 0));
+        c.meta.stroke_width = Some(2.0);
+        let img = r.render(&frame, &[c.clone()]);
+        assert_eq!(px(&img, 0, 0)[3], 0, "rounded corner");
+        assert!(
+            (0..6)
+                .flat_map(|y| (0..6).map(move |x| (x, y)))
+                .any(|(x, y)| (1..255).contains(&px(&img, x, y)[3])),
+            "anti-aliased border on the corner"
+        );
+        assert_eq!(px(&img, 30, 0), [255, 0, 0, 255], "top border");
+        assert_eq!(px(&img, 30, 4), [255, 255, 255, 255], "default white fill");
+        // 尾巴与框体相接处没有分隔线，尾巴两侧有描边
+        assert_eq!(px(&img, 20, 29), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 20, 40), [255, 255, 255, 255]);
+        assert_eq!(px(&img, 13, 33), [255, 0, 0, 255]);
+        assert_eq!(px(&img, 40, 40)[3], 0, "outside the tail");
+        // 白底黑字：抗锯齿字形，两个单词一行放下，在内缩描边 + 半个字号的文字区域内水平居中
+        let ink = |img: &Image| -> Vec<(u32, u32)> {
+            (0..50)
+                .flat_map(|y| (0..60).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    let p = px(img, x, y);
+                    p[3] == 255 && p[0] == p[1] && p[1] == p[2] && p[0] < 255
+                })
+                .collect()
+        };
+        let pts = ink(&img);
+        assert!(
+            pts.iter().any(|&(x, y)| px(&img, x, y)[0] < 64),
+            "dark stems"
+        );
+        assert!(
+            pts.iter().any(|&(x, y)| px(&img, x, y)[0] > 64),
+            "anti-aliased"
+        );
+        assert!(pts
+            .iter()
+            .all(|&(x, y)| (7..53).contains(&x) && (7..23).contains(&y)));
+        let (left, right) = (pts.iter().map(|p| p.0).min(), pts.iter().map(|p| p.0).max());
+        let mid = (left.unwrap() + right.unwrap()) as f32 / 2.0;
+        assert!((mid - 30.0).abs() <= 2.0, "centered at {mid}");
+
+        // 窄框按实测字宽折行；高度只够一行时多余的行被裁掉
+        c.meta.w = 30.0;
+        let ink_rows = |img: &Image| {
+            let mut rows: Vec<u32> = ink(img).into_iter().map(|p| p.1).collect();
+            rows.dedup();
+            rows.len()
+        };
+        let one = ink_rows(&r.render(&frame, &[c.clone()]));
+        c.meta.h = 40.0;
+        c.meta.stroke_width = None;
+        let two = ink_rows(&r.render(&frame, &[c]));
+        assert!(one >= 5 && two > one * 3 / 2, "{one} vs {two} rows");
+    }
+
+    /// 横纵双向渐变的底图，让采样类标注在分块边界两侧都有可区分的像素
+    fn gradient_frame(w: u32, h: u32) -> Frame {
+        let mut buf = Vec::with_capacity((w * h * 4) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                buf.extend_from_slice(&[(x * 3) as u8, (y * 5) as u8, ((x + y) * 2) as u8, 255]);
+            }
+        }
+        Frame {
+            width: w,
+            height: h,
+            pixel_format: PixelFormat::Rgba8,
+            bytes: std::sync::Arc::from(buf.into_boxed_slice()),
+        }
+    }
+
+    /// 各类标注跨越多行，部分带旋转
+    fn tiling_scene() -> Vec<Annotation> {
+        let rotated = |mut a: Annotation, rotation: u16| {
+            a.meta.rotation = rotation;
+            a
+        };
+        let filled = |mut a: Annotation, color: Color, opacity: f32| {
+            a.meta.fill_color = Some(color);
+            a.meta.stroke_color = Some(Color::BLACK);
+            a.meta.stroke_width = Some(1.5);
+            a.meta.opacity = opacity;
+            a
+        };
+        let mut text = make_shape(
+            4.0,
+            50.0,
+            60.0,
+            20.0,
+            AnnotationKind::Text {
+                content: "Tile".into(),
+                font_family: "system".into(),
+                font_size: 14,
+            },
+        );
+        text.meta.fill_color = Some(Color::rgb(0, 0, 255));
+        let callout = make_shape(
+            70.0,
+            44.0,
+            40.0,
+            20.0,
+            AnnotationKind::Callout {
+                content: "ab".into(),
+                font_size: 10,
+                anchor: (90.0, 76.0),
+                corner_radius: 4,
+            },
+        );
+        let mut step = make_shape(
+            40.0,
+            70.0,
+            16.0,
+            16.0,
+            AnnotationKind::StepMarker { number: 3 },
+        );
+        step.meta.stroke_color = Some(Color::WHITE);
+        step.meta.stroke_width = Some(1.0);
+        let mut anns = vec![
+            filled(
+                make_shape(
+                    5.5,
+                    3.25,
+                    30.0,
+                    20.5,
+                    AnnotationKind::Rect { corner_radius: 5 },
+                ),
+                Color::rgb(255, 0, 0),
+                0.6,
+            ),
+            filled(
+                rotated(
+                    make_shape(40.0, 4.0, 28.0, 18.0, AnnotationKind::Ellipse {}),
+                    30,
+                ),
+                Color::rgb(0, 200, 0),
+                0.8,
+            ),
+            make_shape(
+                80.0,
+                2.0,
+                33.0,
+                27.0,
+                AnnotationKind::Mosaic {
+                    level: 1,
+                    corner_radius: 4,
+                },
+            ),
+            rotated(
+                make_shape(
+                    120.0,
+                    4.0,
+                    30.0,
+                    24.0,
+                    AnnotationKind::Mosaic {
+                        level: 1,
+                        corner_radius: 0,
+                    },
+                ),
+                45,
+            ),
+            make_shape(4.0, 28.0, 30.0, 17.0, AnnotationKind::Blur { radius: 4 }),
+            rotated(
+                make_shape(40.0, 28.0, 30.0, 14.0, AnnotationKind::Blur { radius: 3 }),
+                20,
+            ),
+            rotated(
+                make_shape(120.0, 40.0, 24.0, 9.0, AnnotationKind::Redact {}),
+                15,
+            ),
+            magnifier(MagnifierShape::Circle, SampleFilter::Bilinear),
+            spotlight(100.0, 60.0, 40.0, 30.0, SpotlightShape::Ellipse, 6),
+            highlighter(vec![(10.0, 90.0), (60.0, 100.0), (20.0, 110.0)], 0.7),
+            polyline(
+                vec![(70.0, 90.0), (110.0, 118.0), (150.0, 92.0)],
+                LineStyle::Dashed,
+                true,
+            ),
+            make_shape(
+                10.0,
+                80.0,
+                100.0,
+                0.0,
+                AnnotationKind::Measure {
+                    mode: MeasureMode::Line,
+                },
+            ),
+            text,
+            callout,
+            step,
+        ];
+        for (z, a) in anns.iter_mut().enumerate() {
+            a.meta.z = z as i32;
+        }
+        anns
+    }
+
+    #[test]
+    fn test_tiled_render_matches_single_tile() {
+        let frame = gradient_frame(160, 120);
+        let anns = tiling_scene();
+        let whole = render_tiled(&frame, &anns, 1.0, u32::MAX);
+        // 每行一个分块：任何跨块的标注都会被切开，结果仍须逐像素相同
+        for tile_pixels in [1, 160 * 7, TILE_PIXELS] {
+            let tiled = render_tiled(&frame, &anns, 1.0, tile_pixels);
+            assert!(
+                tiled.pixels == whole.pixels,
+                "tile_pixels = {tile_pixels} differs from single tile"
+            );
+        }
+        assert_eq!(SimpleRenderer.render(&frame, &anns).pixels, whole.pixels);
+    }
+
+    #[test]
+    fn test_samples_base_only_for_sampling_kinds() {
+        let anns = tiling_scene();
+        let sampling: Vec<bool> = anns.iter().map(samples_base).collect();
+        assert_eq!(sampling.iter().filter(|&&s| s).count(), 5);
+        // 没有采样类标注时不拷贝底图，渲染结果不受影响
+        let frame = gradient_frame(160, 120);
+        let plain: Vec<Annotation> = anns.into_iter().filter(|a| !samples_base(a)).collect();
+        let img = SimpleRenderer.render(&frame, &plain);
+        assert_eq!(img.pixels, render_tiled(&frame, &plain, 1.0, 1).pixels);
+    }
+
     #[test]
     fn test_jpeg_encode() {
         let mut img = Image::new(16, 16);
@@ -0,0 +1,723 @@
+//! 文字栅格化：fontdue 字形 + 内置默认字体，font_family 从系统字体目录解析，缺字时回退到 CJK 系统字体，
+//! 系统中没有 CJK 字体时使用内置的 Unifont 点阵
+use fontdue::{Font, FontSettings, Metrics, OutlineBounds};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// 内置默认字体 DejaVu Sans（拉丁 / 希腊 / 西里尔字母），许可见 assets/fonts/LICENSE-DejaVu.txt
+const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+
+/// 内置 CJK 点阵：GNU Unifont 的 CJK 标点、假名、统一汉字与全角字符子集，许可见 assets/fonts/LICENSE-Unifont.txt
+///
+/// 按码位升序的定长记录：码位（3 字节小端）、点阵宽度（8 或 16）、16 行 × 2 字节（高位在左，半角只用首字节）
+const UNIFONT_CJK: &[u8] = include_bytes!("../assets/fonts/unifont-cjk.bin");
+
+/// UNIFONT_CJK 单条记录的字节数
+const UNIFONT_RECORD: usize = 36;
+
+/// Unifont 点阵高 16 点，其中基线以下 2 点
+const UNIFONT_HEIGHT: f32 = 16.0;
+const UNIFONT_DESCENT: f32 = 2.0;
+
+/// 点阵字形在字形缓存键中使用的字体 id（加载的字体从 0 递增，不会用到）
+const UNIFONT_ID: usize = usize::MAX;
+
+/// 这些 font_family 直接使用内置字体，不扫描系统字体
+const DEFAULT_FAMILIES: [&str; 4] = ["", "system", "default", "sansserif"];
+
+/// 主字体与内置字体都缺字时依次尝试的系统 CJK 字体
+const CJK_FALLBACKS: [&str; 10] = [
+    "Noto Sans CJK SC",
+    "Noto Sans SC",
+    "Source Han Sans SC",
+    "PingFang SC",
+    "Hiragino Sans GB",
+    "Microsoft YaHei",
+    "WenQuanYi Micro Hei",
+    "WenQuanYi Zen Hei",
+    "Droid Sans Fallback",
+    "SimSun",
+];
+
+/// 字形缓存条目上限，超过后整体清空
+const GLYPH_CACHE_LIMIT: usize = 4096;
+
+/// 扫描系统字体目录的最大递归深度
+const MAX_SCAN_DEPTH: usize = 8;
+
+/// 已加载的字体；id 在进程内唯一，作为字形缓存键的一部分
+struct LoadedFont {
+    id: usize,
+    font: Font,
+}
+
+/// 系统字体目录中的一个 face（集合文件可包含多个）
+#[derive(Debug, Clone)]
+struct FaceInfo {
+    /// 规范化后的族名与全名
+    names: Vec<String>,
+    path: PathBuf,
+    index: u32,
+}
+
+struct FontDb {
+    default: Arc<LoadedFont>,
+    /// 系统字体索引，首次需要时扫描
+    system: OnceLock<Vec<FaceInfo>>,
+    /// 按规范化族名缓存的解析结果；None 表示未找到或加载失败，不再重试
+    families: Mutex<HashMap<String, Option<Arc<LoadedFont>>>>,
+    next_id: AtomicUsize,
+}
+
+impl FontDb {
+    fn get() -> &'static FontDb {
+        static DB: OnceLock<FontDb> = OnceLock::new();
+        DB.get_or_init(|| {
+            let font = Font::from_bytes(DEFAULT_FONT, FontSettings::default())
+                .expect("bundled font is valid");
+            FontDb {
+                default: Arc::new(LoadedFont { id: 0, font }),
+                system: OnceLock::new(),
+                families: Mutex::new(HashMap::new()),
+                next_id: AtomicUsize::new(1),
+            }
+        })
+    }
+
+    /// 按族名解析字体：默认族名返回内置字体，其余在系统字体中查找
+    ///
+    /// 扫描字体目录与读取字体文件都在 families 锁外进行：首次扫描只阻塞同样要查系统字体的线程，
+    /// 已缓存的族名不受影响；并发解析同一族名时以先写入的结果为准
+    fn resolve(&self, family: &str) -> Option<Arc<LoadedFont>> {
+        let key = normalize_family(family);
+        if DEFAULT_FAMILIES.contains(&key.as_str()) {
+            return Some(self.default.clone());
+        }
+        let cached = self.families().get(&key).cloned();
+        if let Some(hit) = cached {
+            return hit;
+        }
+        let system = self
+            .system
+            .get_or_init(|| scan_font_dirs(&system_font_dirs()));
+        let loaded = system
+            .iter()
+            .filter(|f| f.names.contains(&key))
+            .find_map(|f| load_face(&f.path, f.index))
+            .map(|font| {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                Arc::new(LoadedFont { id, font })
+            });
+        if loaded.is_none() {
+            tracing::debug!(family, "font family not found, using fallback");
+        }
+        self.families().entry(key).or_insert(loaded).clone()
+    }
+
+    fn families(&self) -> std::sync::MutexGuard<'_, HashMap<String, Option<Arc<LoadedFont>>>> {
+        self.families.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 选择绘制 ch 的字形来源：主字体 → 内置字体 → CJK 系统字体 → 内置 Unifont 点阵；
+    /// 都没有时用主字体的缺字框
+    fn face_for(&self, primary: &Arc<LoadedFont>, ch: char) -> Face {
+        if primary.font.has_glyph(ch) {
+            return Face::Outline(primary.clone());
+        }
+        if self.default.font.has_glyph(ch) {
+            return Face::Outline(self.default.clone());
+        }
+        if let Some(f) = CJK_FALLBACKS
+            .iter()
+            .filter_map(|family| self.resolve(family))
+            .find(|f| f.font.has_glyph(ch))
+        {
+            return Face::Outline(f);
+        }
+        if unifont_record(ch).is_some() {
+            return Face::Unifont;
+        }
+        Face::Outline(primary.clone())
+    }
+}
+
+/// 单个字符的字形来源
+enum Face {
+    Outline(Arc<LoadedFont>),
+    /// 内置 Unifont CJK 点阵
+    Unifont,
+}
+
+/// 在 UNIFONT_CJK 中二分查找 ch 的记录
+fn unifont_record(ch: char) -> Option<&'static [u8; UNIFONT_RECORD]> {
+    let (records, _) = UNIFONT_CJK.as_chunks::<UNIFONT_RECORD>();
+    records
+        .binary_search_by_key(&(ch as u32), |r| u32::from_le_bytes([r[0], r[1], r[2], 0]))
+        .ok()
+        .map(|i| &records[i])
+}
+
+/// 把 Unifont 点阵缩放到字号 size（点阵高 16 点对应 size 像素）：每个输出像素按其覆盖的点阵面积取覆盖率
+fn rasterize_unifont(record: &[u8; UNIFONT_RECORD], size: f32) -> (Metrics, Vec<u8>) {
+    let dots = record[3] as usize;
+    let rows = &record[4..];
+    let on = |x: usize, y: usize| rows[y * 2 + x / 8] & (0x80 >> (x % 8)) != 0;
+    let s = size / UNIFONT_HEIGHT;
+    let (src_w, src_h) = (dots as f32, UNIFONT_HEIGHT);
+    let (width, height) = ((src_w * s).ceil() as usize, (src_h * s).ceil() as usize);
+    let mut coverage = vec![0u8; width * height];
+    for oy in 0..height {
+        let (y0, y1) = (oy as f32 / s, ((oy + 1) as f32 / s).min(src_h));
+        for ox in 0..width {
+            let (x0, x1) = (ox as f32 / s, ((ox + 1) as f32 / s).min(src_w));
+            let mut area = 0.0;
+            for sy in y0 as usize..(y1.ceil() as usize) {
+                let h = y1.min(sy as f32 + 1.0) - y0.max(sy as f32);
+                for sx in (x0 as usize..(x1.ceil() as usize)).filter(|&sx| on(sx, sy)) {
+                    area += h * (x1.min(sx as f32 + 1.0) - x0.max(sx as f32));
+                }
+            }
+            coverage[oy * width + ox] = (area * s * s * 255.0).round().min(255.0) as u8;
+        }
+    }
+    let metrics = Metrics {
+        xmin: 0,
+        ymin: -(UNIFONT_DESCENT * s).round() as i32,
+        width,
+        height,
+        advance_width: src_w * s,
+        advance_height: 0.0,
+        bounds: OutlineBounds {
+            xmin: 0.0,
+            ymin: -UNIFONT_DESCENT * s,
+            width: src_w * s,
+            height: src_h * s,
+        },
+    };
+    (metrics, coverage)
+}
+
+/// 族名规范化：小写并去掉空格、连字符与下划线（"Noto Sans-CJK" 与 "notosanscjk" 视为相同）
+fn normalize_family(family: &str) -> String {
+    family
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 各平台的系统字体目录（含用户字体目录）
+fn system_font_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "macos") {
+        dirs.extend(["/System/Library/Fonts", "/Library/Fonts"].map(PathBuf::from));
+        dirs.extend(home.map(|h| h.join("Library/Fonts")));
+    } else if cfg!(windows) {
+        if let Some(windir) = std::env::var_os("WINDIR") {
+            dirs.push(PathBuf::from(windir).join("Fonts"));
+        }
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft/Windows/Fonts"));
+        }
+    } else {
+        dirs.extend(["/usr/share/fonts", "/usr/local/share/fonts"].map(PathBuf::from));
+        if let Some(h) = home {
+            dirs.push(h.join(".local/share/fonts"));
+            dirs.push(h.join(".fonts"));
+        }
+    }
+    dirs
+}
+
+/// 递归扫描目录中的 ttf / otf / ttc / otc，读取 name 表中的族名与全名；无法解析的文件跳过
+fn scan_font_dirs(dirs: &[PathBuf]) -> Vec<FaceInfo> {
+    fn walk(dir: &Path, depth: usize, out: &mut Vec<FaceInfo>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth < MAX_SCAN_DEPTH {
+                    walk(&path, depth + 1, out);
+                }
+                continue;
+            }
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_ascii_lowercase);
+            if !matches!(ext.as_deref(), Some("ttf" | "otf" | "ttc" | "otc")) {
+                continue;
+            }
+            let Ok(data) = std::fs::read(&path) else {
+                continue;
+            };
+            let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+            for index in 0..count {
+                let Ok(face) = ttf_parser::Face::parse(&data, index) else {
+                    continue;
+                };
+                let mut names: Vec<String> = face
+                    .names()
+                    .into_iter()
+                    .filter(|n| {
+                        matches!(
+                            n.name_id,
+                            ttf_parser::name_id::FAMILY
+                                | ttf_parser::name_id::FULL_NAME
+                                | ttf_parser::name_id::TYPOGRAPHIC_FAMILY
+                        )
+                    })
+                    .filter_map(|n| n.to_string())
+                    .map(|n| normalize_family(&n))
+                    .collect();
+                names.sort();
+                names.dedup();
+                if !names.is_empty() {
+                    out.push(FaceInfo {
+                        names,
+                        path: path.clone(),
+                        index,
+                    });
+                }
+            }
+        }
+    }
+    let mut out = Vec::new();
+    for dir in dirs {
+        walk(dir, 0, &mut out);
+    }
+    out
+}
+
+fn load_face(path: &Path, index: u32) -> Option<Font> {
+    let data = std::fs::read(path).ok()?;
+    let settings = FontSettings {
+        collection_index: index,
+        ..FontSettings::default()
+    };
+    Font::from_bytes(data, settings)
+        .map_err(|e| tracing::debug!(path = %path.display(), error = e, "failed to load font"))
+        .ok()
+}
+
+/// 栅格化后的字形：覆盖率位图（自上而下逐行）与度量
+struct Glyph {
+    metrics: Metrics,
+    coverage: Vec<u8>,
+}
+
+/// 字形缓存键：(字体 id, 字号的位表示, 字符)
+type GlyphKey = (usize, u32, char);
+
+fn glyph(face: &Face, size: f32, ch: char) -> Arc<Glyph> {
+    static CACHE: OnceLock<Mutex<HashMap<GlyphKey, Arc<Glyph>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    let id = match face {
+        Face::Outline(font) => font.id,
+        Face::Unifont => UNIFONT_ID,
+    };
+    let key = (id, size.to_bits(), ch);
+    if let Some(g) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+        return g.clone();
+    }
+    // 栅格化在锁外进行，并发渲染时同一字形最多重复计算一次
+    let (metrics, coverage) = match face {
+        Face::Outline(font) => font.font.rasterize(ch, size),
+        Face::Unifont => match unifont_record(ch) {
+            Some(record) => rasterize_unifont(record, size),
+            None => (Metrics::default(), Vec::new()),
+        },
+    };
+    let g = Arc::new(Glyph { metrics, coverage });
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= GLYPH_CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(key, g.clone());
+    g
+}
+
+/// 排版后的整块文字覆盖率位图，(0, 0) 为第一行行框左上角
+pub struct TextBitmap {
+    pub width: usize,
+    pub height: usize,
+    /// 每像素覆盖率 0..=255，行优先
+    pub coverage: Vec<u8>,
+}
+
+impl TextBitmap {
+    pub fn at(&self, x: usize, y: usize) -> u8 {
+        if x < self.width && y < self.height {
+            self.coverage[y * self.width + x]
+        } else {
+            0
+        }
+    }
+
+    /// 有墨迹（覆盖率非 0）的像素包围盒 (x, y, 宽, 高)；没有墨迹时返回 None
+    pub fn ink_bounds(&self) -> Option<(usize, usize, usize, usize)> {
+        let inked = |i: &usize| self.coverage[*i] > 0;
+        let first = (0..self.coverage.len()).find(inked)?;
+        let last = (0..self.coverage.len()).rfind(inked)?;
+        let (x0, x1) = (0..self.height)
+            .flat_map(|y| {
+                let row = &self.coverage[y * self.width..(y + 1) * self.width];
+                let l = row.iter().position(|&c| c > 0)?;
+                let r = row.iter().rposition(|&c| c > 0)?;
+                Some((l, r))
+            })
+            .fold((usize::MAX, 0), |(a, b), (l, r)| (a.min(l), b.max(r)));
+        let (y0, y1) = (first / self.width, last / self.width);
+        Some((x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+    }
+}
+
+/// 按 font_family / font_size 排版文字：按 '\n' 分行（不自动折行），行高取主字体的行度量，
+/// 制表符按 4 个空格宽度处理；内容为空或字号无效时返回 None
+pub fn rasterize_text(content: &str, font_family: &str, font_size: f32) -> Option<TextBitmap> {
+    if content.is_empty() {
+        return None;
+    }
+    let layout = Layout::new(font_family, font_size)?;
+    let lines: Vec<&str> = content.split('\n').collect();
+    Some(layout.rasterize(&lines, false))
+}
+
+/// 在 max_width × max_height 的文字框内排版：按实测字宽折行（优先在空白处断开，连续文字超过一行时
+/// 按字符断开，保留显式换行），放不下的行被裁掉，各行在位图内水平居中；位图宽度取最宽的一行。
+/// 没有可见文字、字号无效或一行也放不下时返回 None
+pub fn rasterize_block(
+    content: &str,
+    font_family: &str,
+    font_size: f32,
+    max_width: f32,
+    max_height: f32,
+) -> Option<TextBitmap> {
+    if content.trim().is_empty() || max_width <= 0.0 {
+        return None;
+    }
+    let layout = Layout::new(font_family, font_size)?;
+    let mut lines = layout.wrap(content, max_width);
+    let max_lines = ((max_height - layout.ascent + layout.descent) / layout.line_h).floor() + 1.0;
+    if max_lines < 1.0 {
+        return None;
+    }
+    lines.truncate(max_lines as usize);
+    if lines.iter().all(|l| l.is_empty()) {
+        return None;
+    }
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    Some(layout.rasterize(&lines, true))
+}
+
+/// 一次排版用到的主字体与行度量
+struct Layout {
+    db: &'static FontDb,
+    primary: Arc<LoadedFont>,
+    size: f32,
+    ascent: f32,
+    /// 下行高度（负值）
+    descent: f32,
+    line_h: f32,
+    space: f32,
+}
+
+impl Layout {
+    fn new(font_family: &str, size: f32) -> Option<Self> {
+        if !size.is_finite() || size <= 0.0 {
+            return None;
+        }
+        let db = FontDb::get();
+        let primary = db
+            .resolve(font_family)
+            .unwrap_or_else(|| db.default.clone());
+        let (ascent, line_h) = match primary.font.horizontal_line_metrics(size) {
+            Some(lm) => (lm.ascent, lm.new_line_size),
+            None => (size * 0.8, size * 1.25),
+        };
+        let descent = descent_or(&primary, size);
+        let space = glyph(&Face::Outline(primary.clone()), size, ' ')
+            .metrics
+            .advance_width;
+        Some(Layout {
+            db,
+            primary,
+            size,
+            ascent,
+            descent,
+            line_h,
+            space,
+        })
+    }
+
+    fn glyph(&self, ch: char) -> Arc<Glyph> {
+        glyph(&self.db.face_for(&self.primary, ch), self.size, ch)
+    }
+
+    /// 字符的步进宽度：制表符按 4 个空格，其他控制字符不占宽度
+    fn advance(&self, ch: char) -> f32 {
+        match ch {
+            '\t' => self.space * 4.0,
+            c if c.is_control() => 0.0,
+            c => self.glyph(c).metrics.advance_width,
+        }
+    }
+
+    /// 按步进宽度折行：单词之间用一个空格连接，行首的字符即使超宽也保留
+    fn wrap(&self, content: &str, max_width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in content.split('\n') {
+            let (mut line, mut line_w) = (String::new(), 0.0);
+            for word in paragraph.split_whitespace() {
+                let word_w: f32 = word.chars().map(|c| self.advance(c)).sum();
+                if !line.is_empty() {
+                    if line_w + self.space + word_w <= max_width {
+                        line.push(' ');
+                        line.push_str(word);
+                        line_w += self.space + word_w;
+                        continue;
+                    }
+                    lines.push(std::mem::take(&mut line));
+                    line_w = 0.0;
+                }
+                for ch in word.chars() {
+                    let w = self.advance(ch);
+                    if !line.is_empty() && line_w + w > max_width {
+                        lines.push(std::mem::take(&mut line));
+                        line_w = 0.0;
+                    }
+                    line.push(ch);
+                    line_w += w;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// 把已分好的行合成到一张位图；center 为真时各行按步进宽度在位图内水平居中
+    fn rasterize(&self, lines: &[&str], center: bool) -> TextBitmap {
+        let widths: Vec<f32> = lines
+            .iter()
+            .map(|l| l.chars().map(|c| self.advance(c)).sum())
+            .collect();
+        let block_w = widths.iter().copied().fold(0.0, f32::max);
+
+        // 先定位所有字形（位图左上角），再合成到一张位图
+        let mut placed = Vec::new();
+        let mut width = 0.0f32;
+        for (row, line) in lines.iter().enumerate() {
+            let baseline = self.ascent + row as f32 * self.line_h;
+            let mut pen = if center {
+                ((block_w - widths[row]) / 2.0).round()
+            } else {
+                0.0
+            };
+            for ch in line.chars() {
+                match ch {
+                    '\t' => pen += self.space * 4.0,
+                    c if c.is_control() => {}
+                    c => {
+                        let g = self.glyph(c);
+                        let m = g.metrics;
+                        let x = pen.round() as i64 + m.xmin as i64;
+                        let y = (baseline.round() as i64) - m.ymin as i64 - m.height as i64;
+                        width = width.max(x as f32 + m.width as f32);
+                        pen += m.advance_width;
+                        placed.push((x, y, g));
+                    }
+                }
+                width = width.max(pen);
+            }
+        }
+        let width = width.ceil().max(1.0) as usize;
+        let height = (self.line_h * (lines.len() - 1) as f32 + (self.ascent - self.descent))
+            .ceil()
+            .max(1.0) as usize;
+        let mut coverage = vec![0u8; width * height];
+        for (x0, y0, g) in placed {
+            for gy in 0..g.metrics.height {
+                let y = y0 + gy as i64;
+                if y < 0 || y >= height as i64 {
+                    continue;
+                }
+                for gx in 0..g.metrics.width {
+                    let x = x0 + gx as i64;
+                    if x < 0 || x >= width as i64 {
+                        continue;
+                    }
+                    let dst = &mut coverage[y as usize * width + x as usize];
+                    *dst = (*dst).max(g.coverage[gy * g.metrics.width + gx]);
+                }
+            }
+        }
+        TextBitmap {
+            width,
+            height,
+            coverage,
+        }
+    }
+}
+
+/// 主字体的下行高度（负值）；没有行度量时按字号 0.2 估算
+fn descent_or(font: &LoadedFont, size: f32) -> f32 {
+    font.font
+        .horizontal_line_metrics(size)
+        .map_or(-size * 0.2, |lm| lm.descent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inked(b: &TextBitmap) -> usize {
+        b.coverage.iter().filter(|&&c| c > 0).count()
+    }
+
+    #[test]
+    fn glyphs_are_antialiased() {
+        let b = rasterize_text("Hi", "system", 16.0).unwrap();
+        assert!(b.width > 8 && b.height >= 16, "{}x{}", b.width, b.height);
+        assert!(b.coverage.contains(&255));
+        assert!(
+            b.coverage.iter().any(|&c| c > 0 && c < 255),
+            "partial coverage on edges"
+        );
+        let (x, y, w, h) = b.ink_bounds().unwrap();
+        assert!(w > 8 && x + w <= b.width && y + h <= b.height);
+        assert!(rasterize_text(" ", "system", 16.0)
+            .unwrap()
+            .ink_bounds()
+            .is_none());
+        assert!(rasterize_text("", "system", 16.0).is_none());
+        assert!(rasterize_text("a", "system", 0.0).is_none());
+    }
+
+    #[test]
+    fn multi_line_and_proportional_width() {
+        let one = rasterize_text("ab", "system", 20.0).unwrap();
+        let two = rasterize_text("ab\nab", "system", 20.0).unwrap();
+        assert_eq!(one.width, two.width);
+        assert!(two.height > one.height * 3 / 2);
+        let narrow = rasterize_text("iiii", "system", 20.0).unwrap();
+        let wide = rasterize_text("WWWW", "system", 20.0).unwrap();
+        assert!(narrow.width * 2 < wide.width);
+    }
+
+    #[test]
+    fn unknown_family_and_missing_glyphs_fall_back() {
+        let a = rasterize_text("Hello", "system", 14.0).unwrap();
+        let b = rasterize_text("Hello", "No Such Font Family", 14.0).unwrap();
+        assert_eq!(
+            (a.width, a.height, &a.coverage),
+            (b.width, b.height, &b.coverage)
+        );
+        // 没有任何字体覆盖的字符以主字体的缺字框占位
+        let tofu = rasterize_text("\u{10FFFD}", "system", 14.0).unwrap();
+        assert!(inked(&tofu) > 0);
+    }
+
+    #[test]
+    fn cjk_renders_real_glyphs() {
+        // 系统有 CJK 字体时用系统字体，否则用内置点阵；两种情况下不同汉字都不是缺字框
+        let tofu = rasterize_text("\u{10FFFD}", "system", 16.0).unwrap();
+        let zhong = rasterize_text("中", "system", 16.0).unwrap();
+        let wen = rasterize_text("文", "system", 16.0).unwrap();
+        assert!(zhong.width >= 12 && inked(&zhong) > 0);
+        assert_ne!(zhong.coverage, wen.coverage);
+        for g in [&zhong, &wen] {
+            assert_ne!((g.width, &g.coverage), (tofu.width, &tofu.coverage));
+        }
+        let mixed = rasterize_text("Hi 中文", "No Such Font Family", 16.0).unwrap();
+        assert!(mixed.width > zhong.width * 2);
+    }
+
+    #[test]
+    fn unifont_bitmaps_scale_by_area() {
+        let zhong = unifont_record('中').unwrap();
+        assert_eq!(zhong[3], 16);
+        assert_eq!(unifont_record('ｱ').unwrap()[3], 8, "halfwidth katakana");
+        assert!(unifont_record('A').is_none());
+        // 16px 时逐点对应：“中”的竖笔在第 7 列贯穿全高，覆盖率只有 0 与 255
+        let (m, cov) = rasterize_unifont(zhong, 16.0);
+        assert_eq!(
+            (m.width, m.height, m.ymin, m.advance_width),
+            (16, 16, -2, 16.0)
+        );
+        assert!(cov.iter().all(|&c| c == 0 || c == 255));
+        assert!((0..16).all(|y| cov[y * 16 + 7] == 255));
+        // 8px 时每个像素覆盖 2x2 个点：竖笔只占左半，覆盖率为一半
+        let (m, cov) = rasterize_unifont(zhong, 8.0);
+        assert_eq!((m.width, m.height, m.ymin), (8, 8, -1));
+        assert_eq!(cov[3], 128);
+    }
+
+    #[test]
+    fn block_wraps_by_measured_width() {
+        let layout = Layout::new("system", 20.0).unwrap();
+        let width = |s: &str| -> f32 { s.chars().map(|c| layout.advance(c)).sum() };
+        let fits = width("click the");
+        assert_eq!(
+            layout.wrap("click the button", fits),
+            ["click the", "button"]
+        );
+        // 窄字母比宽字母一行放得多
+        let three_w = width("WWW");
+        assert_eq!(layout.wrap("iiiiii", three_w), ["iiiiii"]);
+        assert_eq!(
+            layout.wrap("WWWWWW", three_w),
+            ["WWW", "WWW"],
+            "long words break per char"
+        );
+        assert_eq!(layout.wrap("中文字", width("中文")), ["中文", "字"]);
+        assert_eq!(layout.wrap("a\n\nb", 100.0), ["a", "", "b"]);
+
+        // 行数受高度限制；各行在位图中水平居中
+        let one = rasterize_block("ab cd", "system", 20.0, width("ab"), 24.0).unwrap();
+        let two = rasterize_block("ab cd", "system", 20.0, width("ab"), 60.0).unwrap();
+        assert!(two.height > one.height * 3 / 2);
+        let wide = rasterize_block("WWWW i", "system", 20.0, width("WWWW"), 60.0).unwrap();
+        let cols = |b: &TextBitmap, rows: std::ops::Range<usize>| {
+            (0..b.width)
+                .filter(|&x| rows.clone().any(|y| b.at(x, y) > 0))
+                .collect::<Vec<_>>()
+        };
+        let i_cols = cols(&wide, wide.height / 2..wide.height);
+        let mid = (i_cols[0] + i_cols[i_cols.len() - 1]) as f32 / 2.0;
+        assert!(
+            (mid - wide.width as f32 / 2.0).abs() <= 2.0,
+            "{mid} of {}",
+            wide.width
+        );
+        assert!(rasterize_block("   ", "system", 20.0, 100.0, 100.0).is_none());
+        assert!(rasterize_block("ab", "system", 20.0, 100.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn glyph_cache_reuses_rasterized_glyphs() {
+        let face = Face::Outline(FontDb::get().default.clone());
+        let first = glyph(&face, 13.0, 'Q');
+        assert!(Arc::ptr_eq(&first, &glyph(&face, 13.0, 'Q')));
+        assert!(!Arc::ptr_eq(&first, &glyph(&face, 14.0, 'Q')));
+        let bitmap = glyph(&Face::Unifont, 13.0, '中');
+        assert!(Arc::ptr_eq(&bitmap, &glyph(&Face::Unifont, 13.0, '中')));
+    }
+
+    #[test]
+    fn scans_font_families_from_directories() {
+        let dir = std::env::temp_dir().join(format!("renderer-fonts-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("nested/Bundled.TTF"), DEFAULT_FONT).unwrap();
+        std::fs::write(dir.join("broken.ttf"), b"not a font").unwrap();
+        let faces = scan_font_dirs(std::slice::from_ref(&dir));
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(faces.len(), 1);
+        assert!(faces[0].names.contains(&normalize_family("DejaVu Sans")));
+        assert_eq!(normalize_family("Noto Sans-CJK_SC"), "notosanscjksc");
+    }
+}
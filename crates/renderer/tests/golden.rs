@@ -0,0 +1,490 @@
+//! 渲染金样测试：与 tests/golden/*.png 逐像素比对。
+//! 渲染逻辑有意变更时，使用 `UPDATE_GOLDEN=1 cargo test -p renderer --test golden` 重新生成。
+use chrono::{TimeZone, Utc};
+use renderer::{ExportEncoder, Image, PngEncoder, Renderer, SimpleRenderer};
+use screenshot_core::{
+    Annotation, AnnotationKind, AnnotationMeta, ArrowHeadShape, ArrowHeads, BlendMode, Color,
+    Frame, LineCap, LineJoin, LineStyle, MagnifierShape, MeasureMode, PixelFormat, SampleFilter,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const W: u32 = 160;
+const H: u32 = 120;
+
+/// 确定性的渐变 + 棋盘格底图，便于观察马赛克与混合效果
+fn background() -> Frame {
+    let mut buf = Vec::with_capacity((W * H * 4) as usize);
+    for y in 0..H {
+        for x in 0..W {
+            let check = if (x / 8 + y / 8) % 2 == 0 { 40 } else { 0 };
+            buf.extend_from_slice(&[
+                (x * 255 / W) as u8,
+                (y * 255 / H) as u8,
+                (128 + check) as u8,
+                255,
+            ]);
+        }
+    }
+    Frame {
+        width: W,
+        height: H,
+        pixel_format: PixelFormat::Rgba8,
+        bytes: Arc::from(buf.into_boxed_slice()),
+    }
+}
+
+fn meta(n: u128, x: f32, y: f32, w: f32, h: f32, rotation: u16) -> AnnotationMeta {
+    AnnotationMeta {
+        id: Uuid::from_u128(n),
+        x,
+        y,
+        w,
+        h,
+        rotation,
+        opacity: 1.0,
+        stroke_color: None,
+        fill_color: None,
+        stroke_width: None,
+        z: n as i32,
+        locked: false,
+        created_at: Utc.timestamp_millis_opt(0).unwrap(),
+    }
+}
+
+/// 每种标注各放一个，统一旋转 rotation 度
+fn scene(rotation: u16) -> Vec<Annotation> {
+    let mut rect = meta(1, 10.0, 12.0, 40.0, 20.0, rotation);
+    rect.fill_color = Some(Color::rgb(0x33, 0x66, 0xFF));
+    rect.stroke_color = Some(Color::rgb(0xFF, 0xFF, 0xFF));
+    rect.stroke_width = Some(2.0);
+    rect.opacity = 0.8;
+
+    let mut highlight = meta(2, 62.0, 10.0, 40.0, 16.0, rotation);
+    highlight.fill_color = Some(Color::rgb(0xFF, 0xEE, 0x00));
+    highlight.opacity = 0.6;
+
+    let mosaic = meta(3, 112.0, 8.0, 36.0, 28.0, rotation);
+
+    let mut text = meta(4, 12.0, 60.0, 48.0, 12.0, rotation);
+    text.fill_color = Some(Color::rgb(0xFF, 0x33, 0x66));
+
+    let mut freehand = meta(5, 0.0, 0.0, 0.0, 0.0, rotation);
+    freehand.stroke_color = Some(Color::rgb(0x00, 0xFF, 0x88));
+    freehand.stroke_width = Some(2.0);
+
+    let mut arrow = meta(6, 112.0, 70.0, 36.0, 24.0, rotation);
+    arrow.stroke_color = Some(Color::rgb(0xFF, 0x88, 0x00));
+    arrow.stroke_width = Some(2.0);
+
+    vec![
+        Annotation {
+            meta: rect,
+            kind: AnnotationKind::Rect { corner_radius: 0 },
+        },
+        Annotation {
+            meta: highlight,
+            kind: AnnotationKind::Highlight {
+                mode: BlendMode::Multiply,
+                corner_radius: 0,
+            },
+        },
+        Annotation {
+            meta: mosaic,
+            kind: AnnotationKind::Mosaic {
+                level: 1,
+                corner_radius: 0,
+            },
+        },
+        Annotation {
+            meta: text,
+            kind: AnnotationKind::Text {
+                content: "Hey".into(),
+                font_family: "system".into(),
+                font_size: 12,
+            },
+        },
+        Annotation {
+            meta: freehand,
+            kind: AnnotationKind::Freehand {
+                points: vec![(66.0, 60.0), (76.0, 90.0), (86.0, 62.0), (100.0, 96.0)],
+                smoothing: 0.5,
+                cap: LineCap::Round,
+                join: LineJoin::Round,
+            },
+        },
+        Annotation {
+            meta: arrow,
+            kind: AnnotationKind::Arrow {
+                head_size: 8,
+                line_style: LineStyle::Solid,
+                controls: vec![],
+                heads: ArrowHeads::End,
+                head_shape: ArrowHeadShape::Triangle,
+            },
+        },
+    ]
+}
+
+/// 椭圆与直线（三种线端、实线 / 虚线）
+fn shapes(rotation: u16) -> Vec<Annotation> {
+    let mut ellipse = meta(1, 10.0, 10.0, 60.0, 36.0, rotation);
+    ellipse.fill_color = Some(Color::rgba(0x33, 0x66, 0xFF, 0xA0));
+    ellipse.stroke_color = Some(Color::WHITE);
+    ellipse.stroke_width = Some(3.0);
+
+    let mut ring = meta(2, 90.0, 10.0, 50.0, 50.0, rotation);
+    ring.stroke_color = Some(Color::rgb(0xFF, 0x33, 0x66));
+    ring.stroke_width = Some(4.0);
+    ring.opacity = 0.7;
+
+    let line = |n: u128, y: f32, line_style, cap| {
+        let mut m = meta(n, 16.0, y, 120.0, 8.0, rotation);
+        m.stroke_color = Some(Color::rgb(0xFF, 0xEE, 0x00));
+        m.stroke_width = Some(6.0);
+        Annotation {
+            meta: m,
+            kind: AnnotationKind::Line { line_style, cap },
+        }
+    };
+
+    vec![
+        Annotation {
+            meta: ellipse,
+            kind: AnnotationKind::Ellipse {},
+        },
+        Annotation {
+            meta: ring,
+            kind: AnnotationKind::Ellipse {},
+        },
+        line(3, 64.0, LineStyle::Solid, LineCap::Butt),
+        line(4, 80.0, LineStyle::Solid, LineCap::Round),
+        line(5, 96.0, LineStyle::Dashed, LineCap::Square),
+    ]
+}
+
+/// 线端 × 连接组合的粗折线、尖角多边形，以及落在亚像素坐标上的细线与矩形
+fn strokes(rotation: u16) -> Vec<Annotation> {
+    let zigzag = |n: u128, y: f32, cap, join| {
+        let mut m = meta(n, 0.0, 0.0, 0.0, 0.0, rotation);
+        m.stroke_color = Some(Color::rgb(0xFF, 0xEE, 0x00));
+        m.stroke_width = Some(7.0);
+        m.opacity = 0.8;
+        Annotation {
+            meta: m,
+            kind: AnnotationKind::Polyline {
+                points: vec![(14.0, y + 18.0), (34.0, y), (54.0, y + 18.0)],
+                line_style: LineStyle::Solid,
+                start_arrow: false,
+                end_arrow: false,
+                head_size: 0,
+                cap,
+                join,
+            },
+        }
+    };
+
+    let mut star = meta(4, 0.0, 0.0, 0.0, 0.0, rotation);
+    star.fill_color = Some(Color::rgba(0x33, 0x66, 0xFF, 0xA0));
+    star.stroke_color = Some(Color::WHITE);
+    star.stroke_width = Some(3.0);
+
+    let hairline = |n: u128, y: f32| {
+        let mut m = meta(n, 80.0, y, 70.0, 3.3, rotation);
+        m.stroke_color = Some(Color::WHITE);
+        m.stroke_width = Some(1.0);
+        Annotation {
+            meta: m,
+            kind: AnnotationKind::Line {
+                line_style: LineStyle::Solid,
+                cap: LineCap::Butt,
+            },
+        }
+    };
+
+    let mut rect = meta(7, 82.4, 84.6, 60.5, 24.3, rotation);
+    rect.fill_color = Some(Color::rgba(0xFF, 0x33, 0x66, 0x80));
+    rect.stroke_color = Some(Color::WHITE);
+    rect.stroke_width = Some(1.5);
+
+    vec![
+        zigzag(1, 8.0, LineCap::Butt, LineJoin::Miter),
+        zigzag(2, 44.0, LineCap::Square, LineJoin::Bevel),
+        zigzag(3, 80.0, LineCap::Round, LineJoin::Round),
+        Annotation {
+            meta: star,
+            kind: AnnotationKind::Polygon {
+                points: vec![
+                    (115.0, 6.0),
+                    (124.0, 40.0),
+                    (90.0, 18.0),
+                    (140.0, 18.0),
+                    (106.0, 40.0),
+                ],
+                join: LineJoin::Miter,
+            },
+        },
+        hairline(5, 52.25),
+        hairline(6, 64.5),
+        Annotation {
+            meta: rect,
+            kind: AnnotationKind::Rect { corner_radius: 0 },
+        },
+    ]
+}
+
+/// 圆角矩形（填充 + 描边、仅描边）、圆角高亮与圆角马赛克
+fn rounded(rotation: u16) -> Vec<Annotation> {
+    let mut card = meta(1, 10.0, 10.0, 64.0, 40.0, rotation);
+    card.fill_color = Some(Color::rgba(0x33, 0x66, 0xFF, 0xC0));
+    card.stroke_color = Some(Color::WHITE);
+    card.stroke_width = Some(3.0);
+
+    let mut pill = meta(2, 90.0, 16.0, 60.0, 20.0, rotation);
+    pill.stroke_color = Some(Color::rgb(0xFF, 0xEE, 0x00));
+    pill.stroke_width = Some(2.0);
+
+    let mut highlight = meta(3, 12.0, 68.0, 60.0, 24.0, rotation);
+    highlight.fill_color = Some(Color::rgba(0xFF, 0xEE, 0x00, 0xA0));
+
+    let mosaic = meta(4, 92.0, 60.0, 56.0, 48.0, rotation);
+
+    vec![
+        Annotation {
+            meta: card,
+            kind: AnnotationKind::Rect { corner_radius: 12 },
+        },
+        // 半径超过短边一半，按胶囊形状绘制
+        Annotation {
+            meta: pill,
+            kind: AnnotationKind::Rect { corner_radius: 255 },
+        },
+        Annotation {
+            meta: highlight,
+            kind: AnnotationKind::Highlight {
+                mode: BlendMode::Multiply,
+                corner_radius: 8,
+            },
+        },
+        Annotation {
+            meta: mosaic,
+            kind: AnnotationKind::Mosaic {
+                level: 2,
+                corner_radius: 16,
+            },
+        },
+    ]
+}
+
+/// 两个 Callout：带描边、尾巴朝下；深色底、尾巴朝右且文字需要折行
+fn callouts(rotation: u16) -> Vec<Annotation> {
+    let mut light = meta(1, 8.0, 8.0, 84.0, 40.0, rotation);
+    light.stroke_color = Some(Color::rgb(0x22, 0x22, 0x22));
+    light.stroke_width = Some(2.0);
+
+    let mut dark = meta(2, 20.0, 62.0, 76.0, 50.0, rotation);
+    dark.fill_color = Some(Color::rgba(0x1E, 0x3A, 0x8A, 0xE0));
+
+    vec![
+        Annotation {
+            meta: light,
+            kind: AnnotationKind::Callout {
+                content: "Click here".into(),
+                font_size: 8,
+                anchor: (40.0, 64.0),
+                corner_radius: 8,
+            },
+        },
+        Annotation {
+            meta: dark,
+            kind: AnnotationKind::Callout {
+                content: "then drag the handle".into(),
+                font_size: 7,
+                anchor: (140.0, 90.0),
+                corner_radius: 4,
+            },
+        },
+    ]
+}
+
+/// 步骤编号（一位 / 两位数）、测量标签与圆形放大镜边框：文字与边框都走覆盖率混合
+fn markers(rotation: u16) -> Vec<Annotation> {
+    let mut light = meta(2, 44.0, 8.0, 28.0, 28.0, rotation);
+    light.fill_color = Some(Color::rgb(0xFF, 0xEE, 0x00));
+    let mut magnifier = meta(5, 96.0, 56.0, 52.0, 52.0, rotation);
+    magnifier.stroke_width = Some(3.0);
+
+    vec![
+        Annotation {
+            meta: meta(1, 8.0, 8.0, 28.0, 28.0, rotation),
+            kind: AnnotationKind::StepMarker { number: 3 },
+        },
+        Annotation {
+            meta: light,
+            kind: AnnotationKind::StepMarker { number: 12 },
+        },
+        Annotation {
+            meta: meta(3, 90.5, 20.5, 60.0, 0.0, rotation),
+            kind: AnnotationKind::Measure {
+                mode: MeasureMode::Line,
+            },
+        },
+        Annotation {
+            meta: meta(4, 12.5, 52.5, 64.0, 48.0, rotation),
+            kind: AnnotationKind::Measure {
+                mode: MeasureMode::Rect,
+            },
+        },
+        Annotation {
+            meta: magnifier,
+            kind: AnnotationKind::Magnifier {
+                source: (20.0, 20.0, 16.0, 16.0),
+                zoom: 3.0,
+                shape: MagnifierShape::Circle,
+                filter: SampleFilter::Bilinear,
+            },
+        },
+    ]
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+fn decode_png(bytes: &[u8]) -> Image {
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    pixels.truncate(info.buffer_size());
+    Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    }
+}
+
+fn assert_golden(name: &str, img: &Image) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, PngEncoder.encode_png(img).unwrap()).unwrap();
+        return;
+    }
+    let expected = decode_png(&std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "missing golden {} ({e}); run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    }));
+    assert_eq!(
+        (expected.width, expected.height),
+        (img.width, img.height),
+        "{name}: size mismatch"
+    );
+    let diff = expected
+        .pixels
+        .chunks_exact(4)
+        .zip(img.pixels.chunks_exact(4))
+        .filter(|(a, b)| a != b)
+        .count();
+    if diff > 0 {
+        let actual = path.with_file_name(format!("{name}.actual.png"));
+        std::fs::write(&actual, PngEncoder.encode_png(img).unwrap()).unwrap();
+        panic!(
+            "{name}: {diff} pixels differ from golden, actual written to {}",
+            actual.display()
+        );
+    }
+}
+
+fn render_rotation(rotation: u16) -> Image {
+    SimpleRenderer.render(&background(), &scene(rotation))
+}
+
+#[test]
+fn golden_rotation_0() {
+    assert_golden("rotation_0", &render_rotation(0));
+}
+
+#[test]
+fn golden_rotation_45() {
+    assert_golden("rotation_45", &render_rotation(45));
+}
+
+#[test]
+fn golden_rotation_90() {
+    assert_golden("rotation_90", &render_rotation(90));
+}
+
+#[test]
+fn golden_rotation_180() {
+    assert_golden("rotation_180", &render_rotation(180));
+}
+
+#[test]
+fn full_turn_matches_unrotated() {
+    assert_eq!(render_rotation(360).pixels, render_rotation(0).pixels);
+}
+
+#[test]
+fn golden_ellipse_and_line() {
+    assert_golden(
+        "shapes_0",
+        &SimpleRenderer.render(&background(), &shapes(0)),
+    );
+    assert_golden(
+        "shapes_30",
+        &SimpleRenderer.render(&background(), &shapes(30)),
+    );
+}
+
+#[test]
+fn golden_strokes() {
+    assert_golden(
+        "strokes_0",
+        &SimpleRenderer.render(&background(), &strokes(0)),
+    );
+    assert_golden(
+        "strokes_30",
+        &SimpleRenderer.render(&background(), &strokes(30)),
+    );
+}
+
+#[test]
+fn golden_rounded() {
+    assert_golden(
+        "rounded_0",
+        &SimpleRenderer.render(&background(), &rounded(0)),
+    );
+    assert_golden(
+        "rounded_30",
+        &SimpleRenderer.render(&background(), &rounded(30)),
+    );
+}
+
+#[test]
+fn golden_callout() {
+    assert_golden(
+        "callout_0",
+        &SimpleRenderer.render(&background(), &callouts(0)),
+    );
+    assert_golden(
+        "callout_30",
+        &SimpleRenderer.render(&background(), &callouts(30)),
+    );
+}
+
+#[test]
+fn golden_markers() {
+    assert_golden(
+        "markers_0",
+        &SimpleRenderer.render(&background(), &markers(0)),
+    );
+    assert_golden(
+        "markers_30",
+        &SimpleRenderer.render(&background(), &markers(30)),
+    );
+}
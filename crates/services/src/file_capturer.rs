@@ -0,0 +1,203 @@
+use crate::Capturer;
+use anyhow::Context;
+use chrono::Utc;
+use screenshot_core::{Frame, FrameSet, PixelFormat, Screenshot};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// 虚拟桌面图像中单个显示器所占的矩形（像素坐标，相对图像左上角）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonitorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 从 PNG/JPEG 文件加载截图的捕获器，用于流水线脚本与确定性测试
+///
+/// - 未设置布局：`FrameSet.primary` 与 `all[0]` 均为整张图片
+/// - 设置布局：图片视为虚拟桌面，`primary` 为整张图片，`all` 按布局切分出每个显示器的帧
+pub struct FileCapturer {
+    path: PathBuf,
+    layout: Option<Vec<MonitorRect>>,
+    scale: f32,
+}
+
+impl FileCapturer {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            layout: None,
+            scale: 1.0,
+        }
+    }
+
+    /// 按显示器布局切分虚拟桌面图片
+    pub fn with_layout(mut self, layout: Vec<MonitorRect>) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// 设置 `Screenshot.scale`（模拟 HiDPI 捕获）
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+impl Capturer for FileCapturer {
+    fn capture_full(&self) -> anyhow::Result<Screenshot> {
+        load_screenshot(&self.path, self.layout.as_deref(), self.scale)
+    }
+}
+
+/// 目录序列捕获器：按文件名顺序每次返回下一张 PNG/JPEG
+pub struct DirCapturer {
+    files: Vec<PathBuf>,
+    cursor: AtomicUsize,
+    layout: Option<Vec<MonitorRect>>,
+    scale: f32,
+    cycle: bool,
+}
+
+impl DirCapturer {
+    /// 扫描目录下的 `.png` / `.jpg` / `.jpeg` 文件（不递归），按文件名排序
+    pub fn new<P: AsRef<Path>>(dir: P) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let mut files = Vec::new();
+        for entry in
+            std::fs::read_dir(dir).with_context(|| format!("read dir {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_file() && is_supported_image(&path) {
+                files.push(path);
+            }
+        }
+        if files.is_empty() {
+            anyhow::bail!("no png/jpeg files in {}", dir.display());
+        }
+        files.sort();
+        Ok(Self {
+            files,
+            cursor: AtomicUsize::new(0),
+            layout: None,
+            scale: 1.0,
+            cycle: false,
+        })
+    }
+
+    pub fn with_layout(mut self, layout: Vec<MonitorRect>) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// 序列结束后从头循环；默认结束后返回错误
+    pub fn with_cycle(mut self, cycle: bool) -> Self {
+        self.cycle = cycle;
+        self
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// 剩余未返回的文件数（循环模式下恒为文件总数）
+    pub fn remaining(&self) -> usize {
+        if self.cycle {
+            return self.files.len();
+        }
+        self.files
+            .len()
+            .saturating_sub(self.cursor.load(Ordering::Relaxed))
+    }
+}
+
+impl Capturer for DirCapturer {
+    fn capture_full(&self) -> anyhow::Result<Screenshot> {
+        let i = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let path = if self.cycle {
+            &self.files[i % self.files.len()]
+        } else {
+            self.files
+                .get(i)
+                .ok_or_else(|| anyhow::anyhow!("image sequence exhausted"))?
+        };
+        load_screenshot(path, self.layout.as_deref(), self.scale)
+    }
+}
+
+fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg"))
+        .unwrap_or(false)
+}
+
+fn load_screenshot(
+    path: &Path,
+    layout: Option<&[MonitorRect]>,
+    scale: f32,
+) -> anyhow::Result<Screenshot> {
+    let img = image::open(path)
+        .with_context(|| format!("load image {}", path.display()))?
+        .to_rgba8();
+    let (width, height) = img.dimensions();
+    let primary = Frame {
+        width,
+        height,
+        pixel_format: PixelFormat::Rgba8,
+        bytes: Arc::from(img.into_raw().into_boxed_slice()),
+    };
+    let all = match layout {
+        None | Some([]) => vec![primary.clone()],
+        Some(rects) => rects
+            .iter()
+            .map(|r| split_frame(&primary, r))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    };
+    Ok(Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet { primary, all }),
+        scale,
+        created_at: Utc::now(),
+    })
+}
+
+/// 从虚拟桌面帧中切出一个显示器；越界视为布局描述错误
+fn split_frame(frame: &Frame, r: &MonitorRect) -> anyhow::Result<Frame> {
+    let fits = r.width > 0
+        && r.height > 0
+        && r.x.checked_add(r.width).is_some_and(|x2| x2 <= frame.width)
+        && r.y
+            .checked_add(r.height)
+            .is_some_and(|y2| y2 <= frame.height);
+    if !fits {
+        anyhow::bail!(
+            "monitor rect {:?} outside image {}x{}",
+            r,
+            frame.width,
+            frame.height
+        );
+    }
+    let row_len = (r.width * 4) as usize;
+    let mut bytes = Vec::with_capacity(row_len * r.height as usize);
+    for row in r.y..r.y + r.height {
+        let start = ((row * frame.width + r.x) * 4) as usize;
+        bytes.extend_from_slice(&frame.bytes[start..start + row_len]);
+    }
+    Ok(Frame {
+        width: r.width,
+        height: r.height,
+        pixel_format: frame.pixel_format,
+        bytes: Arc::from(bytes.into_boxed_slice()),
+    })
+}
@@ -4,14 +4,21 @@ use infra::{metrics, start_timer};
 use parking_lot::Mutex;
 use renderer::{ExportEncoder, PngEncoder, Renderer, SimpleRenderer};
 use screenshot_core::{
-    naming, undo, Annotation, HistoryItem, Result as CoreResult, Screenshot, UndoContext, UndoStack,
+    naming, Annotation, AnnotationKind, HistoryItem, ImageAssets, Rect, Result as CoreResult,
+    Screenshot, SpatialIndex, UndoCommand, UndoOp, UndoStack,
 };
+use std::collections::HashSet;
 use std::fs::{create_dir_all, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use uuid::Uuid;
 
+mod file_capturer;
+mod project;
+pub use file_capturer::*;
+pub use project::*;
+
 // 缩略图生成常量
 const THUMBNAIL_MAX_SIZE: u32 = 240; // 缩略图最长边像素数
 
@@ -49,55 +56,200 @@ impl<C: Capturer> CaptureService<C> {
 }
 
 pub struct AnnotationService {
-    pub annotations: Vec<Annotation>,
+    /// 只能经由 add / update / execute 等方法修改，保证空间索引同步；读取用 `list()`
+    annotations: Vec<Annotation>,
     pub undo: UndoStack,
+    /// 贴图资源库：Image 标注的 Asset 引用在此解析，随 `save_session` 保存
+    pub assets: ImageAssets,
+    /// 空间索引，随 add/update/undo/redo 同步
+    index: SpatialIndex,
 }
 impl AnnotationService {
     pub fn new() -> Self {
         Self {
             annotations: Vec::new(),
             undo: UndoStack::new(100),
+            assets: ImageAssets::new(),
+            index: SpatialIndex::new(),
+        }
+    }
+
+    /// 以已有标注（例如从项目文件加载）初始化，撤销栈为空
+    pub fn from_annotations(annotations: Vec<Annotation>) -> Self {
+        let index = SpatialIndex::from_annotations(&annotations);
+        Self {
+            annotations,
+            index,
+            ..Self::new()
+        }
+    }
+
+    pub fn index(&self) -> &SpatialIndex {
+        &self.index
+    }
+
+    fn sync_index(&mut self, ids: &[Uuid]) {
+        for id in ids {
+            match self.annotations.iter().find(|a| a.meta.id == *id) {
+                Some(a) => self.index.upsert(a),
+                None => {
+                    self.index.remove(*id);
+                }
+            }
         }
     }
 
-    pub fn add(&mut self, ann: Annotation) {
-        let ctx = UndoContext {
-            annotations: self.annotations.clone(),
+    /// 点命中的最上层标注（考虑 z 序、旋转、描边宽度；tolerance 为额外容差像素）
+    pub fn hit_test(&self, x: f32, y: f32, tolerance: f32) -> Option<&Annotation> {
+        let candidates: HashSet<Uuid> = self
+            .index
+            .query_point(x, y, tolerance)
+            .into_iter()
+            .collect();
+        self.annotations
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| candidates.contains(&a.meta.id) && a.hit_test(x, y, tolerance))
+            .max_by_key(|(i, a)| (a.meta.z, *i))
+            .map(|(_, a)| a)
+    }
+
+    /// 与矩形（框选）相交的标注，按渲染顺序（z 升序）返回
+    pub fn query_rect(&self, rect: &Rect) -> Vec<&Annotation> {
+        let candidates: HashSet<Uuid> = self.index.query_rect(rect).into_iter().collect();
+        let mut out: Vec<&Annotation> = self
+            .annotations
+            .iter()
+            .filter(|a| candidates.contains(&a.meta.id) && a.intersects_rect(rect))
+            .collect();
+        out.sort_by_key(|a| a.meta.z);
+        out
+    }
+
+    /// 应用命令并记录到撤销栈
+    pub fn execute(&mut self, command: UndoCommand, merge_key: Option<&str>) {
+        command.apply(&mut self.annotations);
+        self.sync_index(&command.affected_ids());
+        let mut op = UndoOp::new(command);
+        op.merge_key = merge_key.map(|s| s.to_string());
+        self.undo.push(op);
+    }
+
+    /// 添加注解
+    ///
+    /// 步骤标记（`StepMarker`）编号为 0 时自动取下一个编号；指定编号时插入到该位置，
+    /// 其后的标记依次后移。编号调整与添加合并为一次撤销。
+    pub fn add(&mut self, mut ann: Annotation) {
+        let index = self.annotations.len();
+        let mut renumber = Vec::new();
+        if let AnnotationKind::StepMarker { number } = &mut ann.kind {
+            let mut order = self.step_order();
+            let pos = match *number {
+                0 => order.len(),
+                n => (n as usize - 1).min(order.len()),
+            };
+            *number = pos as u32 + 1;
+            order.insert(pos, ann.meta.id);
+            renumber = self.renumber_steps(&order);
+        }
+        let add = UndoCommand::Add {
+            index,
+            annotation: ann,
         };
-        self.annotations.push(ann.clone());
-        self.undo.push(undo::UndoOp {
-            apply: Box::new(move |c: &mut UndoContext| {
-                c.annotations.push(ann.clone());
-            }),
-            revert: Box::new(move |c: &mut UndoContext| {
-                c.annotations = ctx.annotations.clone();
-            }),
-            merge_key: None,
-        });
+        self.execute(with_renumber(add, renumber), None);
+    }
+
+    /// 删除注解，返回被删除的注解；删除步骤标记时其后的编号依次前移
+    pub fn remove(&mut self, id: Uuid) -> Option<Annotation> {
+        let index = self.annotations.iter().position(|a| a.meta.id == id)?;
+        let annotation = self.annotations[index].clone();
+        let renumber = if is_step_marker(&annotation) {
+            let order: Vec<Uuid> = self.step_order().into_iter().filter(|i| *i != id).collect();
+            self.renumber_steps(&order)
+        } else {
+            Vec::new()
+        };
+        let remove = UndoCommand::Remove {
+            index,
+            annotation: annotation.clone(),
+        };
+        self.execute(with_renumber(remove, renumber), None);
+        Some(annotation)
+    }
+
+    /// 将步骤标记移动到第 `number` 位（从 1 开始，超出范围时取最后一位），其余标记顺延
+    pub fn move_step(&mut self, id: Uuid, number: u32) -> bool {
+        let mut order = self.step_order();
+        let Some(pos) = order.iter().position(|i| *i == id) else {
+            return false;
+        };
+        order.remove(pos);
+        let target = (number.max(1) as usize - 1).min(order.len());
+        order.insert(target, id);
+        let updates = self.renumber_steps(&order);
+        if updates.is_empty() {
+            return false;
+        }
+        self.execute(UndoCommand::Batch { commands: updates }, None);
+        true
+    }
+
+    /// 步骤标记的当前顺序：按编号升序，编号相同按列表顺序
+    fn step_order(&self) -> Vec<Uuid> {
+        let mut steps: Vec<(u32, usize, Uuid)> = self
+            .annotations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, a)| match a.kind {
+                AnnotationKind::StepMarker { number } => Some((number, i, a.meta.id)),
+                _ => None,
+            })
+            .collect();
+        steps.sort_unstable();
+        steps.into_iter().map(|(_, _, id)| id).collect()
+    }
+
+    /// 按 order 将标记依次编号为 1..=n，返回编号有变化的标记的 Update 命令
+    fn renumber_steps(&self, order: &[Uuid]) -> Vec<UndoCommand> {
+        order
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| {
+                let before = self.annotations.iter().find(|a| a.meta.id == *id)?;
+                let number = i as u32 + 1;
+                match before.kind {
+                    AnnotationKind::StepMarker { number: n } if n != number => {
+                        let mut after = before.clone();
+                        after.kind = AnnotationKind::StepMarker { number };
+                        Some(UndoCommand::Update {
+                            before: Box::new(before.clone()),
+                            after: Box::new(after),
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
     }
 
     pub fn undo(&mut self) -> bool {
-        let mut ctx = UndoContext {
-            annotations: self.annotations.clone(),
+        let ids = match self.undo.undo_ops().last() {
+            Some(op) => op.command.affected_ids(),
+            None => return false,
         };
-        if self.undo.undo(&mut ctx) {
-            self.annotations = ctx.annotations;
-            true
-        } else {
-            false
-        }
+        let done = self.undo.undo(&mut self.annotations);
+        self.sync_index(&ids);
+        done
     }
 
     pub fn redo(&mut self) -> bool {
-        let mut ctx = UndoContext {
-            annotations: self.annotations.clone(),
+        let ids = match self.undo.redo_ops().last() {
+            Some(op) => op.command.affected_ids(),
+            None => return false,
         };
-        if self.undo.redo(&mut ctx) {
-            self.annotations = ctx.annotations;
-            true
-        } else {
-            false
-        }
+        let done = self.undo.redo(&mut self.annotations);
+        self.sync_index(&ids);
+        done
     }
 
     /// 根据 id 修改注解：
@@ -113,85 +265,125 @@ impl AnnotationService {
             Some(i) => i,
             None => return,
         };
-        let before = self.annotations[idx].clone();
-        let changed = {
-            let ann = &mut self.annotations[idx];
-            f(ann)
-        };
-        if !changed {
+        let mut after = self.annotations[idx].clone();
+        if !f(&mut after) {
             return;
         }
-        let after = self.annotations[idx].clone();
-        self.undo.push(undo::UndoOp {
-            apply: Box::new(move |c: &mut UndoContext| {
-                if let Some(a) = c
-                    .annotations
-                    .iter_mut()
-                    .find(|a| a.meta.id == after.meta.id)
-                {
-                    *a = after.clone();
-                }
-            }),
-            revert: Box::new(move |c: &mut UndoContext| {
-                if let Some(a) = c
-                    .annotations
-                    .iter_mut()
-                    .find(|a| a.meta.id == before.meta.id)
-                {
-                    *a = before.clone();
-                }
-            }),
+        let before = std::mem::replace(&mut self.annotations[idx], after.clone());
+        self.index.upsert(&after);
+        let op = UndoOp {
+            command: UndoCommand::Update {
+                before: Box::new(before),
+                after: Box::new(after),
+            },
             merge_key: merge_key.map(|s| s.to_string()),
+        };
+        self.undo.push(op);
+    }
+
+    /// 移动 Polygon / Polyline 的第 index 个顶点到 to（与 points 同一坐标系，未旋转时即画布坐标）；
+    /// 拖拽时传入 merge_key，连续移动合并为一次撤销。id 不存在、类型不符或越界时返回 false
+    pub fn move_vertex(
+        &mut self,
+        id: Uuid,
+        index: usize,
+        to: (f32, f32),
+        merge_key: Option<&str>,
+    ) -> bool {
+        self.edit_vertices(id, merge_key, |points, _| match points.get_mut(index) {
+            Some(p) if *p != to => {
+                *p = to;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// 在 index 处插入顶点（index 等于顶点数时追加到末尾）
+    pub fn insert_vertex(&mut self, id: Uuid, index: usize, at: (f32, f32)) -> bool {
+        self.edit_vertices(id, None, |points, _| {
+            if index > points.len() {
+                return false;
+            }
+            points.insert(index, at);
+            true
+        })
+    }
+
+    /// 删除顶点；删除后会少于下限（Polygon 3 个、Polyline 2 个）时不执行
+    pub fn remove_vertex(&mut self, id: Uuid, index: usize) -> bool {
+        self.edit_vertices(id, None, |points, min| {
+            if index >= points.len() || points.len() <= min {
+                return false;
+            }
+            points.remove(index);
+            true
+        })
+    }
+
+    /// 经 `update` 修改顶点，f 收到顶点列表与顶点数下限，返回是否有修改
+    fn edit_vertices(
+        &mut self,
+        id: Uuid,
+        merge_key: Option<&str>,
+        f: impl FnOnce(&mut Vec<(f32, f32)>, usize) -> bool,
+    ) -> bool {
+        let mut changed = false;
+        self.update(id, merge_key, |a| {
+            let min = a.min_vertices();
+            changed = a.vertices_mut().is_some_and(|points| f(points, min));
+            changed
         });
+        changed
     }
 
     /// 上移一层 (z++ 简单实现)
     pub fn move_up(&mut self, id: Uuid) {
-        if let Some(idx) = self.annotations.iter().position(|a| a.meta.id == id) {
-            let before = self.annotations[idx].meta.z;
-            self.annotations[idx].meta.z += 1;
-            let after = self.annotations[idx].meta.z;
-            self.undo.push(undo::UndoOp {
-                apply: Box::new(move |c: &mut UndoContext| {
-                    if let Some(a) = c.annotations.iter_mut().find(|a| a.meta.id == id) {
-                        a.meta.z = after;
-                    }
-                }),
-                revert: Box::new(move |c: &mut UndoContext| {
-                    if let Some(a) = c.annotations.iter_mut().find(|a| a.meta.id == id) {
-                        a.meta.z = before;
-                    }
-                }),
-                merge_key: None,
-            });
-        }
+        self.shift_z(id, 1);
     }
 
     /// 下移一层 (z--)
     pub fn move_down(&mut self, id: Uuid) {
-        if let Some(idx) = self.annotations.iter().position(|a| a.meta.id == id) {
-            let before = self.annotations[idx].meta.z;
-            self.annotations[idx].meta.z -= 1;
-            let after = self.annotations[idx].meta.z;
-            self.undo.push(undo::UndoOp {
-                apply: Box::new(move |c: &mut UndoContext| {
-                    if let Some(a) = c.annotations.iter_mut().find(|a| a.meta.id == id) {
-                        a.meta.z = after;
-                    }
-                }),
-                revert: Box::new(move |c: &mut UndoContext| {
-                    if let Some(a) = c.annotations.iter_mut().find(|a| a.meta.id == id) {
-                        a.meta.z = before;
-                    }
-                }),
-                merge_key: None,
-            });
+        self.shift_z(id, -1);
+    }
+
+    fn shift_z(&mut self, id: Uuid, delta: i32) {
+        if let Some(a) = self.annotations.iter().find(|a| a.meta.id == id) {
+            let before_z = a.meta.z;
+            self.execute(
+                UndoCommand::Reorder {
+                    id,
+                    before_z,
+                    after_z: before_z + delta,
+                },
+                None,
+            );
         }
     }
 
     pub fn list(&self) -> &[Annotation] {
         &self.annotations
     }
+
+    /// 供导出 / 渲染使用的标注列表：贴图的资源引用替换为内嵌数据
+    pub fn resolved(&self) -> Vec<Annotation> {
+        self.assets.embed(&self.annotations)
+    }
+}
+
+fn is_step_marker(a: &Annotation) -> bool {
+    matches!(a.kind, AnnotationKind::StepMarker { .. })
+}
+
+/// 主命令与编号调整合并为一个 Batch；无需调整时保持单条命令
+fn with_renumber(command: UndoCommand, renumber: Vec<UndoCommand>) -> UndoCommand {
+    if renumber.is_empty() {
+        return command;
+    }
+    let mut commands = Vec::with_capacity(renumber.len() + 1);
+    commands.push(command);
+    commands.extend(renumber);
+    UndoCommand::Batch { commands }
 }
 
 impl Default for AnnotationService {
@@ -248,7 +440,9 @@ impl<CP: Clipboard> ExportService<CP> {
     ) -> anyhow::Result<Vec<u8>> {
         let _t = start_timer("render_png_us", &[100, 500, 1_000, 5_000, 20_000, 100_000]);
         let frame = &screenshot.raw.primary;
-        let img = self.renderer.render(frame, annotations);
+        let img = self
+            .renderer
+            .render_scaled(frame, annotations, screenshot.scale);
         let r = self.encoder.encode_png(&img);
         if r.is_ok() {
             metrics::counter("render_png_ok").inc();
@@ -362,7 +556,9 @@ impl<CP: Clipboard> ExportService<CP> {
     ) -> anyhow::Result<Vec<u8>> {
         let frame = &screenshot.raw.primary;
         let _t = start_timer("render_jpeg_us", &[100, 500, 1_000, 5_000, 20_000, 100_000]);
-        let img = self.renderer.render(frame, annotations);
+        let img = self
+            .renderer
+            .render_scaled(frame, annotations, screenshot.scale);
         let r = self.encoder.encode_jpeg(&img, quality);
         if r.is_ok() {
             metrics::counter("render_jpeg_ok").inc();
@@ -398,30 +594,35 @@ impl<CP: Clipboard> ExportService<CP> {
 
     /// 生成缩略图（静态方法，可在异步任务中使用）
     fn generate_thumbnail_static(png_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
-        let img = image::load_from_memory(png_bytes)?;
-        let (w, h) = img.dimensions();
-        let max_side = THUMBNAIL_MAX_SIZE;
-        let scale = (max_side as f32 / w.max(h) as f32).min(1.0);
-        let nw = (w as f32 * scale).round() as u32;
-        let nh = (h as f32 * scale).round() as u32;
-        let resized = if scale < 1.0 {
-            img.resize_exact(nw, nh, image::imageops::FilterType::Triangle)
-        } else {
-            img
-        };
-        let mut out = Vec::new();
-        {
-            let mut enc = png::Encoder::new(&mut out, resized.width(), resized.height());
-            enc.set_color(png::ColorType::Rgba);
-            enc.set_depth(png::BitDepth::Eight);
-            let mut writer = enc.write_header()?;
-            let rgba = resized.to_rgba8();
-            writer.write_image_data(&rgba)?;
-        }
-        Ok(out)
+        png_thumbnail(png_bytes)
     }
 }
 
+/// 将 PNG 缩放为最长边不超过 `THUMBNAIL_MAX_SIZE` 的缩略图 PNG
+fn png_thumbnail(png_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let img = image::load_from_memory(png_bytes)?;
+    let (w, h) = img.dimensions();
+    let max_side = THUMBNAIL_MAX_SIZE;
+    let scale = (max_side as f32 / w.max(h) as f32).min(1.0);
+    let nw = (w as f32 * scale).round() as u32;
+    let nh = (h as f32 * scale).round() as u32;
+    let resized = if scale < 1.0 {
+        img.resize_exact(nw, nh, image::imageops::FilterType::Triangle)
+    } else {
+        img
+    };
+    let mut out = Vec::new();
+    {
+        let mut enc = png::Encoder::new(&mut out, resized.width(), resized.height());
+        enc.set_color(png::ColorType::Rgba);
+        enc.set_depth(png::BitDepth::Eight);
+        let mut writer = enc.write_header()?;
+        let rgba = resized.to_rgba8();
+        writer.write_image_data(&rgba)?;
+    }
+    Ok(out)
+}
+
 pub struct HistoryService {
     items: Vec<HistoryItem>,
     capacity: usize,
@@ -463,7 +664,7 @@ impl HistoryService {
         }
         // 裁剪
         if self.items.len() > self.capacity {
-            self.items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            self.items.sort_by_key(|b| std::cmp::Reverse(b.created_at));
             self.items.truncate(self.capacity);
         }
         Ok(())
@@ -504,7 +705,7 @@ impl HistoryService {
 
             // 裁剪
             if items.len() > capacity {
-                items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+                items.sort_by_key(|b| std::cmp::Reverse(b.created_at));
                 items.truncate(capacity);
             }
 
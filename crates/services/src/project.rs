@@ -0,0 +1,400 @@
+use crate::{png_thumbnail, AnnotationService};
+use anyhow::Context;
+use chrono::Utc;
+use renderer::{ExportEncoder, Image, PngEncoder, Renderer, SimpleRenderer};
+use screenshot_core::{
+    Annotation, AnnotationDocument, Frame, FrameSet, HistoryDocument, ImageAssets, PixelFormat,
+    PngData, ProjectAssetEntry, ProjectFrameEntry, ProjectManifest, Screenshot, UndoStack,
+    PROJECT_FORMAT_VERSION,
+};
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// 项目文件推荐扩展名
+pub const PROJECT_EXTENSION: &str = "vsproj";
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const ANNOTATIONS_ENTRY: &str = "annotations.json";
+const THUMBNAIL_ENTRY: &str = "thumbnail.png";
+const HISTORY_ENTRY: &str = "history.json";
+const PRIMARY_FRAME_ENTRY: &str = "frames/primary.png";
+/// 单个条目解压后的上限，防止伪造的 zip 头部触发超大分配
+const MAX_ENTRY_BYTES: u64 = 512 << 20;
+
+/// 可重新打开编辑的项目：原始帧 + 未拍平的标注 + 元数据
+///
+/// 容器为 zip：
+/// - `manifest.json`：`ProjectManifest`（含版本号）
+/// - `annotations.json`：`AnnotationDocument`（带 schema 版本，读取时自动迁移）
+/// - `frames/*.png`：无损保存的原始帧
+/// - `thumbnail.png`：带标注的渲染缩略图
+/// - `history.json`：可选的 `HistoryDocument`（`save_session` 写入）
+/// - `assets/*.png`：贴图资源库中的图片（`save_session` 写入），内嵌贴图随 `annotations.json` 以 base64 保存
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub manifest: ProjectManifest,
+    pub screenshot: Screenshot,
+    pub annotations: Vec<Annotation>,
+    pub thumbnail: Option<Vec<u8>>,
+    pub undo: Option<UndoStack>,
+    pub assets: ImageAssets,
+}
+
+impl Project {
+    /// 恢复编辑会话；若项目保存了撤销历史，可继续 undo / redo
+    pub fn into_annotation_service(self) -> AnnotationService {
+        let mut svc = AnnotationService::from_annotations(self.annotations);
+        if let Some(undo) = self.undo {
+            svc.undo = undo;
+        }
+        svc.assets = self.assets;
+        svc
+    }
+}
+
+/// 保存项目到文件（先写临时文件再重命名，避免写到一半留下损坏文件）
+pub fn save_project<P: AsRef<Path>>(
+    path: P,
+    screenshot: &Screenshot,
+    annotations: &[Annotation],
+    title: Option<&str>,
+) -> anyhow::Result<ProjectManifest> {
+    save_to_file(
+        path.as_ref(),
+        screenshot,
+        annotations,
+        None,
+        &ImageAssets::new(),
+        title,
+    )
+}
+
+/// 保存编辑会话：标注 + 撤销历史 + 贴图资源库，重新打开后可继续撤销
+pub fn save_session<P: AsRef<Path>>(
+    path: P,
+    screenshot: &Screenshot,
+    session: &AnnotationService,
+    title: Option<&str>,
+) -> anyhow::Result<ProjectManifest> {
+    save_to_file(
+        path.as_ref(),
+        screenshot,
+        session.list(),
+        Some(&session.undo),
+        &session.assets,
+        title,
+    )
+}
+
+fn save_to_file(
+    path: &Path,
+    screenshot: &Screenshot,
+    annotations: &[Annotation],
+    undo: Option<&UndoStack>,
+    assets: &ImageAssets,
+    title: Option<&str>,
+) -> anyhow::Result<ProjectManifest> {
+    let dir = match path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            dir
+        }
+        None => Path::new("."),
+    };
+    // 同目录下的唯一临时文件：写完再原子替换；失败时随 drop 删除
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("create project in {}", dir.display()))?;
+    let manifest = write_container(
+        std::io::BufWriter::new(tmp.as_file_mut()),
+        screenshot,
+        annotations,
+        undo,
+        assets,
+        title,
+    )?;
+    tmp.persist(path)
+        .with_context(|| format!("save project {}", path.display()))?;
+    Ok(manifest)
+}
+
+/// 从文件加载项目
+pub fn load_project<P: AsRef<Path>>(path: P) -> anyhow::Result<Project> {
+    let path = path.as_ref();
+    let file =
+        std::fs::File::open(path).with_context(|| format!("open project {}", path.display()))?;
+    read_project(std::io::BufReader::new(file))
+}
+
+/// 将项目写入任意可 seek 的输出
+pub fn write_project<W: Write + Seek>(
+    writer: W,
+    screenshot: &Screenshot,
+    annotations: &[Annotation],
+    title: Option<&str>,
+) -> anyhow::Result<ProjectManifest> {
+    write_container(
+        writer,
+        screenshot,
+        annotations,
+        None,
+        &ImageAssets::new(),
+        title,
+    )
+}
+
+fn write_container<W: Write + Seek>(
+    writer: W,
+    screenshot: &Screenshot,
+    annotations: &[Annotation],
+    undo: Option<&UndoStack>,
+    assets: &ImageAssets,
+    title: Option<&str>,
+) -> anyhow::Result<ProjectManifest> {
+    let mut zip = ZipWriter::new(writer);
+    // PNG 本身已压缩，帧与缩略图直接存储；JSON 使用 deflate
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let primary = &screenshot.raw.primary;
+    zip.start_file(PRIMARY_FRAME_ENTRY, stored)?;
+    zip.write_all(&encode_frame(primary)?)?;
+    let primary_entry = frame_entry(PRIMARY_FRAME_ENTRY, primary);
+
+    let mut frames = Vec::with_capacity(screenshot.raw.all.len());
+    for (i, f) in screenshot.raw.all.iter().enumerate() {
+        // 单显示器捕获时 all[0] 通常与 primary 共享像素，避免重复存储
+        if Arc::ptr_eq(&f.bytes, &primary.bytes) {
+            frames.push(primary_entry.clone());
+            continue;
+        }
+        let name = format!("frames/{}.png", i);
+        zip.start_file(name.as_str(), stored)?;
+        zip.write_all(&encode_frame(f)?)?;
+        frames.push(frame_entry(&name, f));
+    }
+
+    let thumbnail = match render_thumbnail(primary, &assets.embed(annotations), screenshot.scale) {
+        Ok(bytes) => {
+            zip.start_file(THUMBNAIL_ENTRY, stored)?;
+            zip.write_all(&bytes)?;
+            Some(THUMBNAIL_ENTRY.to_string())
+        }
+        Err(e) => {
+            tracing::warn!("project thumbnail failed: {e:#}");
+            None
+        }
+    };
+
+    zip.start_file(ANNOTATIONS_ENTRY, deflated)?;
+    AnnotationDocument::write(&mut zip, annotations)?;
+
+    let history = match undo {
+        Some(stack) => {
+            zip.start_file(HISTORY_ENTRY, deflated)?;
+            HistoryDocument::write(&mut zip, stack)?;
+            Some(HISTORY_ENTRY.to_string())
+        }
+        None => None,
+    };
+
+    let mut asset_entries = Vec::with_capacity(assets.len());
+    for (id, png) in assets.iter() {
+        let name = format!("assets/{}.png", id);
+        zip.start_file(name.as_str(), stored)?;
+        zip.write_all(png.as_bytes())?;
+        asset_entries.push(ProjectAssetEntry { id, path: name });
+    }
+
+    let manifest = ProjectManifest {
+        version: PROJECT_FORMAT_VERSION,
+        screenshot_id: screenshot.id,
+        scale: screenshot.scale,
+        captured_at: screenshot.created_at,
+        saved_at: Utc::now(),
+        primary: primary_entry,
+        frames,
+        title: title.map(|t| t.to_string()),
+        thumbnail,
+        history,
+        assets: asset_entries,
+    };
+    zip.start_file(MANIFEST_ENTRY, deflated)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)?;
+    zip.finish()?.flush()?;
+    Ok(manifest)
+}
+
+/// 从任意可 seek 的输入读取项目
+pub fn read_project<R: Read + Seek>(reader: R) -> anyhow::Result<Project> {
+    let mut zip = ZipArchive::new(reader).context("not a project container")?;
+    let manifest: ProjectManifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_ENTRY)?)
+        .context("invalid project manifest")?;
+    manifest.check_version()?;
+
+    let annotations: Vec<Annotation> = match zip.by_name(ANNOTATIONS_ENTRY) {
+        Ok(entry) => {
+            let buf = read_limited(entry)?;
+            AnnotationDocument::from_json(&buf)
+                .context("invalid project annotations")?
+                .annotations
+        }
+        Err(zip::result::ZipError::FileNotFound) => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    // 同一条目可能被多个帧引用（primary 与 all[0]），共享解码结果
+    let mut decoded: HashMap<String, Frame> = HashMap::new();
+    let mut load = |entry: &ProjectFrameEntry| -> anyhow::Result<Frame> {
+        if let Some(f) = decoded.get(&entry.path) {
+            return Ok(f.clone());
+        }
+        let f = decode_frame(&read_entry(&mut zip, &entry.path)?, entry)?;
+        decoded.insert(entry.path.clone(), f.clone());
+        Ok(f)
+    };
+    let primary = load(&manifest.primary)?;
+    let mut all = manifest
+        .frames
+        .iter()
+        .map(&mut load)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if all.is_empty() {
+        all.push(primary.clone());
+    }
+
+    let thumbnail = match &manifest.thumbnail {
+        Some(name) => read_entry(&mut zip, name).ok(),
+        None => None,
+    };
+
+    let undo = match &manifest.history {
+        Some(name) => Some(
+            HistoryDocument::from_json(&read_entry(&mut zip, name)?)
+                .context("invalid project history")?
+                .history,
+        ),
+        None => None,
+    };
+
+    let mut assets = ImageAssets::new();
+    for entry in &manifest.assets {
+        let png = PngData::new(read_entry(&mut zip, &entry.path)?)
+            .with_context(|| format!("invalid project asset {}", entry.path))?;
+        assets.insert_with_id(entry.id, png);
+    }
+
+    let screenshot = Screenshot {
+        id: manifest.screenshot_id,
+        raw: Arc::new(FrameSet { primary, all }),
+        scale: manifest.scale,
+        created_at: manifest.captured_at,
+    };
+    Ok(Project {
+        manifest,
+        screenshot,
+        annotations,
+        thumbnail,
+        undo,
+        assets,
+    })
+}
+
+fn read_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let entry = zip
+        .by_name(name)
+        .with_context(|| format!("project entry {} missing", name))?;
+    read_limited(entry)
+}
+
+/// 读取整个条目；头部声明的大小不可信，预分配与实际读取都受 `MAX_ENTRY_BYTES` 约束
+fn read_limited(entry: zip::read::ZipFile<'_>) -> anyhow::Result<Vec<u8>> {
+    let name = entry.name().to_string();
+    anyhow::ensure!(
+        entry.size() <= MAX_ENTRY_BYTES,
+        "project entry {} too large ({} bytes)",
+        name,
+        entry.size()
+    );
+    let mut buf = Vec::with_capacity(entry.size() as usize);
+    entry.take(MAX_ENTRY_BYTES + 1).read_to_end(&mut buf)?;
+    anyhow::ensure!(
+        buf.len() as u64 <= MAX_ENTRY_BYTES,
+        "project entry {} too large",
+        name
+    );
+    Ok(buf)
+}
+
+fn frame_entry(path: &str, frame: &Frame) -> ProjectFrameEntry {
+    ProjectFrameEntry {
+        path: path.to_string(),
+        width: frame.width,
+        height: frame.height,
+        pixel_format: frame.pixel_format,
+    }
+}
+
+/// 帧统一以 RGBA PNG 存储
+fn encode_frame(frame: &Frame) -> anyhow::Result<Vec<u8>> {
+    let expected = frame.width as usize * frame.height as usize * 4;
+    if frame.bytes.len() < expected {
+        anyhow::bail!(
+            "frame buffer too small: {} < {}",
+            frame.bytes.len(),
+            expected
+        );
+    }
+    let mut pixels = frame.bytes[..expected].to_vec();
+    if frame.pixel_format == PixelFormat::Bgra8 {
+        swap_red_blue(&mut pixels);
+    }
+    PngEncoder.encode_png(&Image {
+        width: frame.width,
+        height: frame.height,
+        pixels,
+    })
+}
+
+fn decode_frame(png_bytes: &[u8], entry: &ProjectFrameEntry) -> anyhow::Result<Frame> {
+    let img = image::load_from_memory(png_bytes)
+        .with_context(|| format!("decode frame {}", entry.path))?
+        .to_rgba8();
+    if img.dimensions() != (entry.width, entry.height) {
+        anyhow::bail!(
+            "frame {} is {:?}, manifest says {}x{}",
+            entry.path,
+            img.dimensions(),
+            entry.width,
+            entry.height
+        );
+    }
+    let mut pixels = img.into_raw();
+    if entry.pixel_format == PixelFormat::Bgra8 {
+        swap_red_blue(&mut pixels);
+    }
+    Ok(Frame {
+        width: entry.width,
+        height: entry.height,
+        pixel_format: entry.pixel_format,
+        bytes: Arc::from(pixels.into_boxed_slice()),
+    })
+}
+
+fn swap_red_blue(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+}
+
+fn render_thumbnail(
+    frame: &Frame,
+    annotations: &[Annotation],
+    scale: f32,
+) -> anyhow::Result<Vec<u8>> {
+    let img = SimpleRenderer.render_scaled(frame, annotations, scale);
+    png_thumbnail(&PngEncoder.encode_png(&img)?)
+}
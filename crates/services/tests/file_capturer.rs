@@ -0,0 +1,148 @@
+use chrono::Utc;
+use screenshot_core::{Annotation, AnnotationKind, AnnotationMeta, Color};
+use services::{
+    AnnotationService, CaptureService, Capturer, DirCapturer, ExportService, FileCapturer,
+    MonitorRect, StubClipboard,
+};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// 左半红、右半蓝的 8x4 测试图
+fn write_split_png(path: &Path) {
+    let img = image::RgbaImage::from_fn(8, 4, |x, _| {
+        if x < 4 {
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            image::Rgba([0, 0, 255, 255])
+        }
+    });
+    img.save(path).unwrap();
+}
+
+fn write_solid(path: &Path, w: u32, h: u32, v: u8) {
+    image::RgbaImage::from_pixel(w, h, image::Rgba([v, v, v, 255]))
+        .save(path)
+        .unwrap();
+}
+
+#[test]
+fn file_capturer_loads_png_into_frames() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("shot.png");
+    write_split_png(&path);
+    let shot = FileCapturer::new(&path)
+        .with_scale(2.0)
+        .capture_full()
+        .unwrap();
+    let f = &shot.raw.primary;
+    assert_eq!((f.width, f.height), (8, 4));
+    assert_eq!(f.bytes.len(), 8 * 4 * 4);
+    assert_eq!(&f.bytes[0..4], &[255, 0, 0, 255]);
+    assert_eq!(shot.raw.all.len(), 1);
+    assert_eq!(shot.scale, 2.0);
+}
+
+#[test]
+fn file_capturer_splits_virtual_desktop_by_layout() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("desktop.png");
+    write_split_png(&path);
+    let layout: Vec<MonitorRect> = serde_json::from_str(
+        r#"[{"x":0,"y":0,"width":4,"height":4},{"x":4,"y":1,"width":4,"height":3}]"#,
+    )
+    .unwrap();
+    let shot = FileCapturer::new(&path)
+        .with_layout(layout)
+        .capture_full()
+        .unwrap();
+    assert_eq!(shot.raw.primary.width, 8, "primary keeps whole desktop");
+    let all = &shot.raw.all;
+    assert_eq!(all.len(), 2);
+    assert_eq!((all[0].width, all[0].height), (4, 4));
+    assert!(all[0].bytes.chunks_exact(4).all(|p| p == [255, 0, 0, 255]));
+    assert_eq!((all[1].width, all[1].height), (4, 3));
+    assert!(all[1].bytes.chunks_exact(4).all(|p| p == [0, 0, 255, 255]));
+}
+
+#[test]
+fn file_capturer_rejects_layout_outside_image() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("desktop.png");
+    write_split_png(&path);
+    let cap = FileCapturer::new(&path).with_layout(vec![MonitorRect {
+        x: 6,
+        y: 0,
+        width: 4,
+        height: 4,
+    }]);
+    assert!(cap.capture_full().is_err());
+    assert!(FileCapturer::new(tmp.path().join("missing.png"))
+        .capture_full()
+        .is_err());
+}
+
+#[test]
+fn dir_capturer_returns_files_in_name_order() {
+    let tmp = tempfile::tempdir().unwrap();
+    write_solid(&tmp.path().join("002.png"), 2, 2, 20);
+    write_solid(&tmp.path().join("001.png"), 2, 2, 10);
+    image::RgbImage::from_pixel(3, 3, image::Rgb([200, 200, 200]))
+        .save(tmp.path().join("003.jpg"))
+        .unwrap();
+    std::fs::write(tmp.path().join("notes.txt"), b"ignored").unwrap();
+
+    let cap = DirCapturer::new(tmp.path()).unwrap();
+    assert_eq!(cap.files().len(), 3);
+    assert_eq!(cap.capture_full().unwrap().raw.primary.bytes[0], 10);
+    assert_eq!(cap.capture_full().unwrap().raw.primary.bytes[0], 20);
+    let jpeg = cap.capture_full().unwrap();
+    assert_eq!(jpeg.raw.primary.width, 3);
+    assert!(jpeg.raw.primary.bytes[0] > 190, "jpeg decoded to rgba");
+    assert_eq!(cap.remaining(), 0);
+    assert!(cap.capture_full().is_err(), "sequence exhausted");
+
+    let cycling = DirCapturer::new(tmp.path()).unwrap().with_cycle(true);
+    for _ in 0..4 {
+        cycling.capture_full().unwrap();
+    }
+    assert_eq!(cycling.capture_full().unwrap().raw.primary.bytes[0], 20);
+}
+
+#[test]
+fn headless_capture_annotate_export_pipeline() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("input.png");
+    write_solid(&path, 16, 16, 128);
+
+    let capture = CaptureService::new(Arc::new(FileCapturer::new(&path)));
+    let shot = capture.capture_full().unwrap();
+
+    let mut annotations = AnnotationService::new();
+    annotations.add(Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x: 0.0,
+            y: 0.0,
+            w: 4.0,
+            h: 4.0,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: Some(Color::rgb(0x00, 0xFF, 0x00)),
+            stroke_width: None,
+            z: 0,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind: AnnotationKind::Rect { corner_radius: 0 },
+    });
+
+    let out = tmp.path().join("out.png");
+    ExportService::new(Arc::new(StubClipboard))
+        .export_png_to_file(&shot, annotations.list(), &out)
+        .unwrap();
+    let exported = image::open(&out).unwrap().to_rgba8();
+    assert_eq!(exported.get_pixel(1, 1).0, [0, 255, 0, 255]);
+    assert_eq!(exported.get_pixel(10, 10).0, [128, 128, 128, 255]);
+}
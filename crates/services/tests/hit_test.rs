@@ -0,0 +1,216 @@
+use chrono::Utc;
+use screenshot_core::{
+    Annotation, AnnotationKind, AnnotationMeta, ArrowHeadShape, ArrowHeads, Color, LineCap,
+    LineJoin, LineStyle, Rect,
+};
+use services::AnnotationService;
+use uuid::Uuid;
+
+fn filled_rect(x: f32, y: f32, w: f32, h: f32, z: i32) -> Annotation {
+    Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x,
+            y,
+            w,
+            h,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: Some(Color::rgb(0xFF, 0x00, 0x00)),
+            stroke_width: None,
+            z,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind: AnnotationKind::Rect { corner_radius: 0 },
+    }
+}
+
+fn ids(list: Vec<&Annotation>) -> Vec<Uuid> {
+    list.into_iter().map(|a| a.meta.id).collect()
+}
+
+#[test]
+fn hit_test_picks_topmost() {
+    let mut svc = AnnotationService::new();
+    let low = filled_rect(0.0, 0.0, 50.0, 50.0, 0);
+    let high = filled_rect(25.0, 25.0, 50.0, 50.0, 1);
+    let (low_id, high_id) = (low.meta.id, high.meta.id);
+    svc.add(low);
+    svc.add(high);
+
+    assert_eq!(svc.hit_test(10.0, 10.0, 0.0).unwrap().meta.id, low_id);
+    assert_eq!(svc.hit_test(30.0, 30.0, 0.0).unwrap().meta.id, high_id);
+    assert!(svc.hit_test(90.0, 90.0, 0.0).is_none());
+    assert!(svc.hit_test(77.0, 77.0, 3.0).is_some(), "tolerance");
+
+    svc.move_down(high_id);
+    svc.move_down(high_id);
+    assert_eq!(svc.hit_test(30.0, 30.0, 0.0).unwrap().meta.id, low_id);
+}
+
+#[test]
+fn index_stays_in_sync_with_edits_and_undo() {
+    let mut svc = AnnotationService::new();
+    let a = filled_rect(0.0, 0.0, 10.0, 10.0, 0);
+    let id = a.meta.id;
+    svc.add(a);
+    let marquee = Rect {
+        x: 200.0,
+        y: 200.0,
+        w: 50.0,
+        h: 50.0,
+    };
+    assert!(svc.query_rect(&marquee).is_empty());
+
+    // 拖拽到框选区域内
+    for step in 1..=4 {
+        svc.update(id, Some("drag"), |a| {
+            a.meta.x = 55.0 * step as f32;
+            a.meta.y = 55.0 * step as f32;
+            true
+        });
+    }
+    assert_eq!(ids(svc.query_rect(&marquee)), vec![id]);
+    assert!(svc.hit_test(5.0, 5.0, 0.0).is_none());
+
+    assert!(svc.undo());
+    assert!(svc.query_rect(&marquee).is_empty());
+    assert_eq!(svc.hit_test(5.0, 5.0, 0.0).unwrap().meta.id, id);
+
+    assert!(svc.redo());
+    assert_eq!(ids(svc.query_rect(&marquee)), vec![id]);
+
+    assert!(svc.remove(id).is_some());
+    assert!(svc.query_rect(&marquee).is_empty());
+    assert!(svc.index().is_empty());
+    assert!(svc.undo());
+    assert_eq!(ids(svc.query_rect(&marquee)), vec![id]);
+
+    assert!(svc.undo());
+    assert!(svc.undo(), "undo add");
+    assert!(svc.index().is_empty());
+}
+
+#[test]
+fn rotated_annotations_in_marquee() {
+    let mut a = filled_rect(0.0, 45.0, 100.0, 10.0, 0);
+    a.meta.rotation = 90;
+    let id = a.meta.id;
+    let mut svc = AnnotationService::from_annotations(vec![a]);
+    // 旋转后为竖条：横向的薄框选不与其相交的部分
+    let left = Rect {
+        x: 0.0,
+        y: 45.0,
+        w: 20.0,
+        h: 10.0,
+    };
+    assert!(svc.query_rect(&left).is_empty());
+    let column = Rect {
+        x: 48.0,
+        y: 0.0,
+        w: 4.0,
+        h: 4.0,
+    };
+    assert_eq!(ids(svc.query_rect(&column)), vec![id]);
+
+    svc.update(id, None, |a| {
+        a.meta.rotation = 0;
+        true
+    });
+    assert_eq!(ids(svc.query_rect(&left)), vec![id]);
+}
+
+#[test]
+fn arrow_head_hit_off_the_shaft() {
+    // 水平箭头（h = 0）：线宽 2 的包围盒只有 y ∈ [49, 51]，头部命中范围为轴线两侧 head_size / 2
+    let mut arrow = filled_rect(10.0, 50.0, 80.0, 0.0, 0);
+    arrow.meta.fill_color = None;
+    arrow.meta.stroke_width = Some(2.0);
+    arrow.kind = AnnotationKind::Arrow {
+        head_size: 10,
+        line_style: LineStyle::Solid,
+        controls: vec![],
+        heads: ArrowHeads::End,
+        head_shape: ArrowHeadShape::Triangle,
+    };
+    let id = arrow.meta.id;
+    let mut svc = AnnotationService::new();
+    svc.add(arrow);
+
+    // 头部上方 3 ~ 5px、尖端之外 3px：精确测试命中，空间索引粗筛也不能漏掉
+    for (x, y) in [(85.0, 47.0), (85.0, 45.0), (88.0, 46.0), (93.0, 48.0)] {
+        assert!(svc.list()[0].hit_test(x, y, 0.0), "({x}, {y})");
+        assert_eq!(
+            svc.hit_test(x, y, 0.0).map(|a| a.meta.id),
+            Some(id),
+            "({x}, {y})"
+        );
+    }
+    // 轴线上方超出头部范围、轴线中段上方都不命中
+    assert!(svc.hit_test(85.0, 44.0, 0.0).is_none());
+    assert!(svc.hit_test(40.0, 46.0, 0.0).is_none());
+}
+
+#[test]
+fn polyline_head_hit_off_the_line() {
+    let mut line = filled_rect(0.0, 0.0, 0.0, 0.0, 0);
+    line.meta.fill_color = None;
+    line.meta.stroke_width = Some(2.0);
+    line.kind = AnnotationKind::Polyline {
+        points: vec![(10.0, 50.0), (50.0, 50.0), (90.0, 50.0)],
+        line_style: LineStyle::Solid,
+        start_arrow: false,
+        end_arrow: true,
+        head_size: 10,
+        cap: LineCap::Butt,
+        join: LineJoin::Miter,
+    };
+    let id = line.meta.id;
+    let mut svc = AnnotationService::new();
+    svc.add(line);
+
+    // 终点箭头三角形向两侧伸出 5px，超出点集包围盒
+    let (x, y) = (82.0, 46.0);
+    assert!(svc.list()[0].hit_test(x, y, 0.0));
+    assert_eq!(svc.hit_test(x, y, 0.0).map(|a| a.meta.id), Some(id));
+    assert_eq!(
+        ids(svc.query_rect(&Rect {
+            x: 80.0,
+            y: 45.0,
+            w: 4.0,
+            h: 2.0,
+        })),
+        vec![id]
+    );
+}
+
+#[test]
+fn arrow_start_circle_head_hit_and_marquee() {
+    // 起点圆形头部（半径 5）伸出线段起点之外
+    let mut arrow = filled_rect(20.0, 50.0, 60.0, 0.0, 0);
+    arrow.meta.fill_color = None;
+    arrow.meta.stroke_width = Some(2.0);
+    arrow.kind = AnnotationKind::Arrow {
+        head_size: 10,
+        line_style: LineStyle::Solid,
+        controls: vec![],
+        heads: ArrowHeads::Start,
+        head_shape: ArrowHeadShape::Circle,
+    };
+    let id = arrow.meta.id;
+    let mut svc = AnnotationService::new();
+    svc.add(arrow);
+
+    assert!(svc.list()[0].bounds().contains(15.0, 50.0));
+    assert_eq!(svc.hit_test(16.0, 50.0, 0.0).map(|a| a.meta.id), Some(id));
+    // 只框住圆形头部的左半边
+    let marquee = Rect {
+        x: 10.0,
+        y: 46.0,
+        w: 7.0,
+        h: 8.0,
+    };
+    assert_eq!(ids(svc.query_rect(&marquee)), vec![id]);
+}
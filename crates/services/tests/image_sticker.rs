@@ -0,0 +1,156 @@
+use chrono::Utc;
+use renderer::{ExportEncoder, Image, PngEncoder};
+use screenshot_core::{
+    Annotation, AnnotationKind, AnnotationMeta, Frame, FrameSet, ImageSource, PixelFormat, PngData,
+    Screenshot, PROJECT_FORMAT_VERSION,
+};
+use services::{
+    load_project, read_project, save_session, AnnotationService, ExportService, StubClipboard,
+};
+use std::io::{Cursor, Write};
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn screenshot(w: u32, h: u32) -> Screenshot {
+    let frame = Frame {
+        width: w,
+        height: h,
+        pixel_format: PixelFormat::Rgba8,
+        bytes: Arc::from(vec![255u8; (w * h * 4) as usize].into_boxed_slice()),
+    };
+    Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet {
+            primary: frame.clone(),
+            all: vec![frame],
+        }),
+        scale: 1.0,
+        created_at: Utc::now(),
+    }
+}
+
+/// 单色不透明 1x1 PNG
+fn solid_png(rgb: [u8; 3]) -> PngData {
+    let mut img = Image::new(1, 1);
+    img.pixels.copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+    PngData::new(PngEncoder.encode_png(&img).unwrap()).unwrap()
+}
+
+fn sticker(x: f32, source: ImageSource) -> Annotation {
+    Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x,
+            y: 0.0,
+            w: 4.0,
+            h: 4.0,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: None,
+            stroke_width: None,
+            z: 0,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind: AnnotationKind::Image { source },
+    }
+}
+
+fn pixel(png: &[u8], x: u32, y: u32) -> [u8; 4] {
+    image::load_from_memory(png)
+        .unwrap()
+        .to_rgba8()
+        .get_pixel(x, y)
+        .0
+}
+
+#[test]
+fn stickers_survive_project_round_trip() {
+    let tmp = tempfile::tempdir().unwrap();
+    let shot = screenshot(12, 4);
+    let mut svc = AnnotationService::new();
+    let stamp = solid_png([255, 0, 0]);
+    let id = svc.assets.insert(stamp.clone());
+    svc.add(sticker(0.0, ImageSource::Asset { id }));
+    svc.add(sticker(
+        4.0,
+        ImageSource::Embedded {
+            png: solid_png([0, 0, 255]),
+        },
+    ));
+
+    let path = tmp.path().join("stickers.vsproj");
+    let manifest = save_session(&path, &shot, &svc, None).unwrap();
+    assert_eq!(manifest.assets.len(), 1);
+    assert_eq!(manifest.assets[0].id, id);
+
+    let restored = load_project(&path).unwrap().into_annotation_service();
+    assert_eq!(restored.assets.get(id), Some(&stamp));
+    match &restored.list()[1].kind {
+        AnnotationKind::Image {
+            source: ImageSource::Embedded { png },
+        } => assert_eq!(png, &solid_png([0, 0, 255])),
+        k => panic!("unexpected kind {k:?}"),
+    }
+
+    // 资源引用经 resolved() 解析后才会绘制
+    let export = ExportService::new(Arc::new(StubClipboard));
+    let png = export
+        .render_png_bytes(&shot, &restored.resolved())
+        .unwrap();
+    assert_eq!(pixel(&png, 1, 1), [255, 0, 0, 255]);
+    assert_eq!(pixel(&png, 5, 1), [0, 0, 255, 255]);
+    assert_eq!(pixel(&png, 9, 1), [255, 255, 255, 255]);
+    let unresolved = export.render_png_bytes(&shot, restored.list()).unwrap();
+    assert_eq!(pixel(&unresolved, 1, 1), [255, 255, 255, 255]);
+}
+
+#[test]
+fn embedded_images_are_base64_in_annotation_json() {
+    let a = sticker(
+        0.0,
+        ImageSource::Embedded {
+            png: solid_png([0, 255, 0]),
+        },
+    );
+    let v = serde_json::to_value(&a).unwrap();
+    assert_eq!(v["kind"]["type"], "image");
+    assert_eq!(v["kind"]["source"]["kind"], "embedded");
+    let data = v["kind"]["source"]["png"].as_str().unwrap();
+    assert!(data.starts_with("iVBORw0KGg"), "{data}");
+
+    // 非 PNG 内容在反序列化时被拒绝
+    let mut bad = v.clone();
+    bad["kind"]["source"]["png"] = "R0lGODlhAQABAAAAACw=".into();
+    assert!(serde_json::from_value::<Annotation>(bad).is_err());
+}
+
+#[test]
+fn corrupt_project_assets_are_rejected() {
+    let frame = PngEncoder.encode_png(&Image::new(2, 2)).unwrap();
+    let mut buf = Cursor::new(Vec::new());
+    {
+        let mut zip = zip::ZipWriter::new(&mut buf);
+        let opts = zip::write::SimpleFileOptions::default();
+        zip.start_file("frames/primary.png", opts).unwrap();
+        zip.write_all(&frame).unwrap();
+        zip.start_file("assets/logo.png", opts).unwrap();
+        zip.write_all(b"GIF89a not a png").unwrap();
+        zip.start_file("manifest.json", opts).unwrap();
+        let manifest = serde_json::json!({
+            "version": PROJECT_FORMAT_VERSION,
+            "screenshot_id": Uuid::now_v7(),
+            "scale": 1.0,
+            "captured_at": 0,
+            "saved_at": 0,
+            "primary": {"path": "frames/primary.png", "width": 2, "height": 2, "pixel_format": "Rgba8"},
+            "assets": [{"id": Uuid::now_v7(), "path": "assets/logo.png"}],
+        });
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+    buf.set_position(0);
+    let err = read_project(buf).unwrap_err();
+    assert!(format!("{err:#}").contains("assets/logo.png"), "{err:#}");
+}
@@ -2,7 +2,8 @@ use chrono::Utc;
 use infra::metrics;
 use parking_lot::Mutex;
 use screenshot_core::{
-    Annotation, AnnotationKind, AnnotationMeta, Frame, FrameSet, PixelFormat, Screenshot,
+    Annotation, AnnotationKind, AnnotationMeta, Frame, FrameSet, MeasureMode, PixelFormat,
+    Screenshot,
 };
 use services::{gen_file_name, AnnotationService, ExportService, HistoryService, StubClipboard};
 use std::sync::Arc;
@@ -99,6 +100,41 @@ fn test_annotation_undo_layer_move() {
     assert_eq!(svc.list()[0].meta.z, 1, "redo should reapply z change");
 }
 
+#[test]
+fn test_measure_label_follows_screenshot_scale() {
+    // 标注不保存缩放：同一个 Measure 在 2x 截图上导出时标签附加逻辑点，渲染结果不同
+    let measure = Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x: 10.5,
+            y: 20.5,
+            w: 100.0,
+            h: 0.0,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: None,
+            stroke_width: None,
+            z: 0,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind: AnnotationKind::Measure {
+            mode: MeasureMode::Line,
+        },
+    };
+    let export = ExportService::new(Arc::new(StubClipboard));
+    let shot = make_mock_screenshot(120, 40);
+    let retina = Screenshot {
+        scale: 2.0,
+        ..shot.clone()
+    };
+    let anns = [measure];
+    let plain = export.render_png_bytes(&shot, &anns).unwrap();
+    let scaled = export.render_png_bytes(&retina, &anns).unwrap();
+    assert_ne!(plain, scaled);
+}
+
 #[test]
 fn test_privacy_scan_basic() {
     let svc = services::PrivacyService::new();
@@ -0,0 +1,105 @@
+use chrono::Utc;
+use screenshot_core::{Annotation, AnnotationKind, AnnotationMeta, LineCap, LineJoin, LineStyle};
+use services::AnnotationService;
+use uuid::Uuid;
+
+fn shape(kind: AnnotationKind) -> Annotation {
+    Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x: 0.0,
+            y: 0.0,
+            w: 0.0,
+            h: 0.0,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: None,
+            stroke_width: None,
+            z: 0,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind,
+    }
+}
+
+fn vertices(svc: &AnnotationService, id: Uuid) -> Vec<(f32, f32)> {
+    svc.list()
+        .iter()
+        .find(|a| a.meta.id == id)
+        .and_then(|a| a.vertices())
+        .unwrap()
+        .to_vec()
+}
+
+#[test]
+fn vertex_drag_is_one_undo_step() {
+    let mut svc = AnnotationService::new();
+    let tri = shape(AnnotationKind::Polygon {
+        points: vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)],
+        join: LineJoin::Round,
+    });
+    let id = tri.meta.id;
+    svc.add(tri);
+
+    for x in [12.0, 14.0, 16.0] {
+        assert!(svc.move_vertex(id, 1, (x, 2.0), Some("vertex")));
+    }
+    assert_eq!(vertices(&svc, id)[1], (16.0, 2.0));
+    // 位置不变、越界时不记录操作
+    assert!(!svc.move_vertex(id, 1, (16.0, 2.0), Some("vertex")));
+    assert!(!svc.move_vertex(id, 3, (1.0, 1.0), None));
+
+    // 包围盒与空间索引随顶点更新
+    assert_eq!(svc.hit_test(15.0, 2.0, 0.0).map(|a| a.meta.id), Some(id));
+    assert!(svc.undo());
+    assert_eq!(vertices(&svc, id)[1], (10.0, 0.0));
+    assert!(svc.hit_test(15.0, 2.0, 0.0).is_none());
+    assert!(svc.redo());
+    assert_eq!(vertices(&svc, id)[1], (16.0, 2.0));
+}
+
+#[test]
+fn insert_and_remove_respect_vertex_minimum() {
+    let mut svc = AnnotationService::new();
+    let line = shape(AnnotationKind::Polyline {
+        points: vec![(0.0, 0.0), (10.0, 0.0)],
+        line_style: LineStyle::Solid,
+        start_arrow: false,
+        end_arrow: true,
+        head_size: 8,
+        cap: LineCap::Round,
+        join: LineJoin::Round,
+    });
+    let id = line.meta.id;
+    svc.add(line);
+
+    assert!(!svc.remove_vertex(id, 0), "polyline keeps two vertices");
+    assert!(svc.insert_vertex(id, 1, (5.0, 5.0)));
+    assert!(svc.insert_vertex(id, 3, (20.0, 0.0)), "append");
+    assert!(!svc.insert_vertex(id, 9, (0.0, 0.0)));
+    assert_eq!(
+        vertices(&svc, id),
+        vec![(0.0, 0.0), (5.0, 5.0), (10.0, 0.0), (20.0, 0.0)]
+    );
+    assert!(svc.remove_vertex(id, 1));
+    assert_eq!(
+        vertices(&svc, id),
+        vec![(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)]
+    );
+
+    assert!(svc.undo());
+    assert!(svc.undo());
+    assert_eq!(
+        vertices(&svc, id),
+        vec![(0.0, 0.0), (5.0, 5.0), (10.0, 0.0)]
+    );
+
+    // 非多点类型不受影响
+    let rect = shape(AnnotationKind::Rect { corner_radius: 0 });
+    let rect_id = rect.meta.id;
+    svc.add(rect);
+    assert!(!svc.insert_vertex(rect_id, 0, (1.0, 1.0)));
+    assert!(!svc.move_vertex(rect_id, 0, (1.0, 1.0), None));
+}
@@ -0,0 +1,301 @@
+use chrono::Utc;
+use renderer::{ExportEncoder, Image, PngEncoder};
+use screenshot_core::{
+    Annotation, AnnotationKind, AnnotationMeta, Color, Frame, FrameSet, PixelFormat, Screenshot,
+    PROJECT_FORMAT_VERSION,
+};
+use services::{load_project, read_project, save_project, AnnotationService};
+use std::io::{Cursor, Write};
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn frame(w: u32, h: u32, fmt: PixelFormat, seed: u8) -> Frame {
+    let bytes: Vec<u8> = (0..w * h * 4)
+        .map(|i| {
+            if i % 4 == 3 {
+                255
+            } else {
+                (i as u8).wrapping_add(seed)
+            }
+        })
+        .collect();
+    Frame {
+        width: w,
+        height: h,
+        pixel_format: fmt,
+        bytes: Arc::from(bytes.into_boxed_slice()),
+    }
+}
+
+fn rect(x: f32, z: i32) -> Annotation {
+    Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x,
+            y: 1.0,
+            w: 2.0,
+            h: 2.0,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: Some(Color::rgb(0xFF, 0x00, 0x00)),
+            fill_color: None,
+            stroke_width: Some(1.0),
+            z,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind: AnnotationKind::Rect { corner_radius: 0 },
+    }
+}
+
+#[test]
+fn project_round_trip_keeps_frames_and_annotations() {
+    let tmp = tempfile::tempdir().unwrap();
+    let primary = frame(6, 4, PixelFormat::Bgra8, 0);
+    let second = frame(3, 2, PixelFormat::Bgra8, 7);
+    let shot = Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet {
+            primary: primary.clone(),
+            all: vec![primary.clone(), second.clone()],
+        }),
+        scale: 2.0,
+        created_at: Utc::now(),
+    };
+    let anns = vec![
+        rect(0.0, 0),
+        Annotation {
+            meta: rect(1.0, 1).meta,
+            kind: AnnotationKind::Text {
+                content: "你好".into(),
+                font_family: "Sans".into(),
+                font_size: 14,
+            },
+        },
+    ];
+
+    let path = tmp.path().join("nested/session.vsproj");
+    let manifest = save_project(&path, &shot, &anns, Some("demo")).unwrap();
+    assert_eq!(manifest.version, PROJECT_FORMAT_VERSION);
+    assert_eq!(
+        manifest.frames[0], manifest.primary,
+        "shared frame stored once"
+    );
+
+    let project = load_project(&path).unwrap();
+    assert_eq!(project.manifest.title.as_deref(), Some("demo"));
+    assert_eq!(project.screenshot.id, shot.id);
+    assert_eq!(project.screenshot.scale, 2.0);
+    assert_eq!(
+        project.screenshot.created_at.timestamp_millis(),
+        shot.created_at.timestamp_millis()
+    );
+    let raw = &project.screenshot.raw;
+    assert_eq!(raw.primary.pixel_format, PixelFormat::Bgra8);
+    assert_eq!(&*raw.primary.bytes, &*primary.bytes);
+    assert_eq!(raw.all.len(), 2);
+    assert_eq!(&*raw.all[1].bytes, &*second.bytes);
+    assert_eq!((raw.all[1].width, raw.all[1].height), (3, 2));
+
+    assert_eq!(project.annotations.len(), 2);
+    assert_eq!(project.annotations[1].meta.id, anns[1].meta.id);
+    assert!(matches!(
+        &project.annotations[1].kind,
+        AnnotationKind::Text { content, .. } if content == "你好"
+    ));
+    let thumb = project.thumbnail.expect("thumbnail");
+    assert_eq!(&thumb[..4], b"\x89PNG");
+}
+
+#[test]
+fn reopened_project_annotations_are_editable() {
+    let tmp = tempfile::tempdir().unwrap();
+    let shot = Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet {
+            primary: frame(8, 8, PixelFormat::Rgba8, 3),
+            all: vec![],
+        }),
+        scale: 1.0,
+        created_at: Utc::now(),
+    };
+    let ann = rect(1.0, 0);
+    let id = ann.meta.id;
+    let path = tmp.path().join("edit.vsproj");
+    save_project(&path, &shot, &[ann], None).unwrap();
+
+    let project = load_project(&path).unwrap();
+    assert_eq!(project.screenshot.raw.all.len(), 1);
+    let mut svc = AnnotationService::from_annotations(project.annotations);
+    svc.update(id, None, |a| {
+        a.meta.x = 5.0;
+        true
+    });
+    save_project(&path, &project.screenshot, svc.list(), None).unwrap();
+
+    let again = load_project(&path).unwrap();
+    assert_eq!(again.annotations[0].meta.x, 5.0);
+    assert!(svc.undo());
+    assert_eq!(svc.list()[0].meta.x, 1.0);
+}
+
+#[test]
+fn newer_or_broken_projects_are_rejected() {
+    let mut buf = Cursor::new(Vec::new());
+    {
+        let mut zip = zip::ZipWriter::new(&mut buf);
+        zip.start_file("manifest.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        let manifest = serde_json::json!({
+            "version": PROJECT_FORMAT_VERSION + 1,
+            "screenshot_id": Uuid::now_v7(),
+            "scale": 1.0,
+            "captured_at": 0,
+            "saved_at": 0,
+            "primary": {"path": "frames/primary.png", "width": 1, "height": 1, "pixel_format": "Rgba8"},
+        });
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+    buf.set_position(0);
+    let err = read_project(buf).unwrap_err();
+    assert!(format!("{err:#}").contains("newer"), "{err:#}");
+
+    assert!(read_project(Cursor::new(b"not a zip".to_vec())).is_err());
+}
+
+fn single_frame_shot() -> Screenshot {
+    Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet {
+            primary: frame(2, 2, PixelFormat::Rgba8, 0),
+            all: vec![],
+        }),
+        scale: 1.0,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn save_leaves_no_temp_files_behind() {
+    let tmp = tempfile::tempdir().unwrap();
+    let shot = single_frame_shot();
+    // 同名 .tmp 是用户自己的文件，不能被覆盖
+    let sibling = tmp.path().join("foo.tmp");
+    std::fs::write(&sibling, b"mine").unwrap();
+
+    save_project(tmp.path().join("foo.vsproj"), &shot, &[], None).unwrap();
+    assert_eq!(std::fs::read(&sibling).unwrap(), b"mine");
+
+    // 目标是目录时替换失败，临时文件也要清掉
+    std::fs::create_dir(tmp.path().join("taken.vsproj")).unwrap();
+    assert!(save_project(tmp.path().join("taken.vsproj"), &shot, &[], None).is_err());
+    let mut names: Vec<_> = std::fs::read_dir(tmp.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["foo.tmp", "foo.vsproj", "taken.vsproj"]);
+}
+
+#[test]
+fn oversized_entry_headers_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("big.vsproj");
+    let shot = single_frame_shot();
+    save_project(&path, &shot, &[rect(0.0, 0)], None).unwrap();
+
+    // 把中央目录里 annotations.json 声明的解压大小改成 ~4 GiB，实际数据不变
+    let mut bytes = std::fs::read(&path).unwrap();
+    let name = b"annotations.json";
+    let at = (0..bytes.len() - 46)
+        .find(|&i| bytes[i..i + 4] == [0x50, 0x4b, 0x01, 0x02] && bytes[i + 46..].starts_with(name))
+        .expect("central directory entry");
+    bytes[at + 24..at + 28].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+
+    let err = read_project(Cursor::new(bytes)).unwrap_err();
+    assert!(format!("{err:#}").contains("too large"), "{err:#}");
+}
+
+#[test]
+fn v1_project_annotations_are_migrated() {
+    // v1 项目：annotations.json 为裸数组，颜色按旧规则只认 6 位十六进制
+    let png = PngEncoder.encode_png(&Image::new(2, 2)).unwrap();
+    let mut buf = Cursor::new(Vec::new());
+    {
+        let mut zip = zip::ZipWriter::new(&mut buf);
+        let opts = zip::write::SimpleFileOptions::default();
+        zip.start_file("frames/primary.png", opts).unwrap();
+        zip.write_all(&png).unwrap();
+        let mut a = serde_json::to_value(rect(3.0, 0)).unwrap();
+        a["meta"]["fill_color"] = "00ff00".into();
+        a["meta"]["stroke_color"] = "#F00".into();
+        zip.start_file("annotations.json", opts).unwrap();
+        zip.write_all(serde_json::json!([a]).to_string().as_bytes())
+            .unwrap();
+        zip.start_file("manifest.json", opts).unwrap();
+        let manifest = serde_json::json!({
+            "version": 1,
+            "screenshot_id": Uuid::now_v7(),
+            "scale": 1.0,
+            "captured_at": 0,
+            "saved_at": 0,
+            "primary": {"path": "frames/primary.png", "width": 2, "height": 2, "pixel_format": "Rgba8"},
+        });
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+    buf.set_position(0);
+    let project = read_project(buf).unwrap();
+    let m = &project.annotations[0].meta;
+    assert_eq!(m.x, 3.0);
+    assert_eq!(m.fill_color, Some(Color::rgb(0, 0xFF, 0)));
+    assert_eq!(m.stroke_color, None);
+}
+
+#[test]
+fn session_history_survives_save_and_load() {
+    let tmp = tempfile::tempdir().unwrap();
+    let shot = Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet {
+            primary: frame(4, 4, PixelFormat::Rgba8, 0),
+            all: vec![],
+        }),
+        scale: 1.0,
+        created_at: Utc::now(),
+    };
+    let mut svc = AnnotationService::new();
+    let a = rect(0.0, 0);
+    let id = a.meta.id;
+    svc.add(a);
+    for x in [1.0, 2.0, 3.0] {
+        svc.update(id, Some("drag"), |a| {
+            a.meta.x = x;
+            true
+        });
+    }
+    svc.add(rect(9.0, 1));
+    svc.remove(id);
+    assert!(svc.undo()); // 撤销删除，留在 redo 栈
+
+    let path = tmp.path().join("session.vsproj");
+    let manifest = services::save_session(&path, &shot, &svc, None).unwrap();
+    assert!(manifest.history.is_some());
+
+    let mut restored = load_project(&path).unwrap().into_annotation_service();
+    assert_eq!(restored.list().len(), 2);
+    assert_eq!(restored.undo.len(), 3, "add + merged drag + add");
+    assert!(restored.redo());
+    assert_eq!(restored.list().len(), 1);
+    assert!(restored.undo());
+    assert!(restored.undo());
+    assert!(restored.undo());
+    assert_eq!(restored.list()[0].meta.x, 0.0, "drag undone in one step");
+
+    // 未保存历史的项目加载后撤销栈为空
+    save_project(&path, &shot, svc.list(), None).unwrap();
+    let plain = load_project(&path).unwrap();
+    assert!(plain.undo.is_none());
+    assert!(plain.into_annotation_service().undo.is_empty());
+}
@@ -0,0 +1,228 @@
+//! 涂黑打码（Redact）合规测试：逐个导出路径解码输出，确认涂黑区域内的原像素值没有残留。
+//!
+//! 底图约定：涂黑区域内的"敏感"像素蓝色通道为 200，其余像素蓝色通道为 0；
+//! 涂黑色为黑色，因此无损输出中任何蓝色通道大于 0 的像素都意味着原像素泄漏。
+//! 有损的 JPEG 无法逐值判断，改为与"敏感像素已被抹掉的底图"的导出结果逐字节比较。
+use chrono::Utc;
+use parking_lot::Mutex;
+use screenshot_core::{
+    Annotation, AnnotationKind, AnnotationMeta, Color, Frame, FrameSet, PixelFormat,
+    Result as CoreResult, Screenshot,
+};
+use services::{Clipboard, ExportService, HistoryService};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const W: u32 = 320;
+const H: u32 = 240;
+/// 敏感区域 (x, y, w, h)
+const SECRET: (u32, u32, u32, u32) = (60, 40, 120, 80);
+
+fn in_secret(x: u32, y: u32) -> bool {
+    let (sx, sy, sw, sh) = SECRET;
+    x >= sx && x < sx + sw && y >= sy && y < sy + sh
+}
+
+fn screenshot() -> Screenshot {
+    make_screenshot(|x, y| [(x % 256) as u8, (y % 256) as u8, 200, 255])
+}
+
+/// 对照组：敏感区域的原像素被替换成无关的值
+fn scrubbed_screenshot() -> Screenshot {
+    make_screenshot(|_, _| [7, 7, 0, 255])
+}
+
+fn make_screenshot(secret: impl Fn(u32, u32) -> [u8; 4]) -> Screenshot {
+    let mut bytes = Vec::with_capacity((W * H * 4) as usize);
+    for y in 0..H {
+        for x in 0..W {
+            if in_secret(x, y) {
+                bytes.extend_from_slice(&secret(x, y));
+            } else {
+                bytes.extend_from_slice(&[(x % 256) as u8, (y % 256) as u8, 0, 255]);
+            }
+        }
+    }
+    let frame = Frame {
+        width: W,
+        height: H,
+        pixel_format: PixelFormat::Rgba8,
+        bytes: Arc::from(bytes.into_boxed_slice()),
+    };
+    Screenshot {
+        id: Uuid::now_v7(),
+        raw: Arc::new(FrameSet {
+            primary: frame.clone(),
+            all: vec![frame],
+        }),
+        scale: 1.0,
+        created_at: Utc::now(),
+    }
+}
+
+fn ann(x: f32, y: f32, w: f32, h: f32, z: i32, kind: AnnotationKind) -> Annotation {
+    Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x,
+            y,
+            w,
+            h,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: None,
+            stroke_width: None,
+            z,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind,
+    }
+}
+
+/// 半透明设置的涂黑 + 跨越涂黑边界的模糊与马赛克 + 涂黑之上的半透明高亮
+fn annotations() -> Vec<Annotation> {
+    let (sx, sy, sw, sh) = SECRET;
+    let mut redact = ann(
+        sx as f32,
+        sy as f32,
+        sw as f32,
+        sh as f32,
+        0,
+        AnnotationKind::Redact {},
+    );
+    redact.meta.opacity = 0.2;
+    let blur = ann(
+        40.0,
+        30.0,
+        100.0,
+        40.0,
+        1,
+        AnnotationKind::Blur { radius: 8 },
+    );
+    let mosaic = ann(
+        150.0,
+        90.0,
+        60.0,
+        60.0,
+        1,
+        AnnotationKind::Mosaic {
+            level: 2,
+            corner_radius: 0,
+        },
+    );
+    let mut highlight = ann(
+        80.0,
+        60.0,
+        40.0,
+        40.0,
+        2,
+        AnnotationKind::Highlight {
+            mode: screenshot_core::BlendMode::Screen,
+            corner_radius: 0,
+        },
+    );
+    highlight.meta.fill_color = Some(Color::rgba(0x40, 0x40, 0x00, 0x80));
+    vec![redact, blur, mosaic, highlight]
+}
+
+/// 解码后最大的蓝色通道值（原图中只有敏感像素的蓝色通道非 0）
+fn max_blue(encoded: &[u8]) -> u8 {
+    let img = image::load_from_memory(encoded).unwrap().to_rgba8();
+    img.pixels().map(|p| p.0[2]).max().unwrap()
+}
+
+#[derive(Default)]
+struct RecordingClipboard {
+    writes: Mutex<Vec<Vec<u8>>>,
+}
+
+impl Clipboard for RecordingClipboard {
+    fn write_image(&self, bytes: &[u8]) -> CoreResult<()> {
+        self.writes.lock().push(bytes.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn baseline_without_redact_leaks() {
+    // 对照组：没有涂黑时敏感像素确实会出现在导出结果中
+    let export = ExportService::new(Arc::new(RecordingClipboard::default()));
+    let png = export.render_png_bytes(&screenshot(), &[]).unwrap();
+    assert_eq!(max_blue(&png), 200);
+}
+
+#[test]
+fn png_and_clipboard_contain_no_secret_pixels() {
+    let clipboard = Arc::new(RecordingClipboard::default());
+    let export = ExportService::new(clipboard.clone());
+    let shot = screenshot();
+    let anns = annotations();
+
+    let png = export.render_png_bytes(&shot, &anns).unwrap();
+    assert_eq!(max_blue(&png), 0);
+    // 涂黑区域内全部为不透明黑色（高亮叠加在涂黑之上，只影响其覆盖部分）
+    let img = image::load_from_memory(&png).unwrap().to_rgba8();
+    let (sx, sy, _, _) = SECRET;
+    assert_eq!(img.get_pixel(sx + 2, sy + 70).0, [0, 0, 0, 255]);
+
+    export.export_png_to_clipboard(&shot, &anns).unwrap();
+    let writes = clipboard.writes.lock();
+    assert_eq!(writes.len(), 1);
+    assert_eq!(max_blue(&writes[0]), 0);
+}
+
+#[test]
+fn exports_do_not_depend_on_secret_pixels() {
+    let export = ExportService::new(Arc::new(RecordingClipboard::default()));
+    let anns = annotations();
+    let (shot, scrubbed) = (screenshot(), scrubbed_screenshot());
+    assert_eq!(
+        export.render_png_bytes(&shot, &anns).unwrap(),
+        export.render_png_bytes(&scrubbed, &anns).unwrap()
+    );
+    for quality in [50, 95] {
+        assert_eq!(
+            export.render_jpeg_bytes(&shot, &anns, quality).unwrap(),
+            export.render_jpeg_bytes(&scrubbed, &anns, quality).unwrap(),
+            "jpeg quality {quality}"
+        );
+    }
+    // 对照：没有涂黑时 JPEG 输出确实受敏感像素影响
+    assert_ne!(
+        export.render_jpeg_bytes(&shot, &[], 90).unwrap(),
+        export.render_jpeg_bytes(&scrubbed, &[], 90).unwrap()
+    );
+}
+
+#[test]
+fn files_and_history_thumbnails_contain_no_secret_pixels() {
+    let tmp = tempfile::tempdir().unwrap();
+    let history = Arc::new(Mutex::new(HistoryService::new(tmp.path(), 10).unwrap()));
+    let export =
+        ExportService::new(Arc::new(RecordingClipboard::default())).with_history(history.clone());
+    let shot = screenshot();
+    let anns = annotations();
+
+    let png_path = tmp.path().join("redacted.png");
+    export.export_png_to_file(&shot, &anns, &png_path).unwrap();
+    assert_eq!(max_blue(&std::fs::read(&png_path).unwrap()), 0);
+
+    let jpeg_path = tmp.path().join("redacted.jpg");
+    export
+        .export_jpeg_to_file(&shot, &anns, &jpeg_path, 90)
+        .unwrap();
+    assert_eq!(
+        std::fs::read(&jpeg_path).unwrap(),
+        export
+            .render_jpeg_bytes(&scrubbed_screenshot(), &anns, 90)
+            .unwrap()
+    );
+
+    let h = history.lock();
+    let thumb = h.list()[0].thumb.as_ref().expect("thumbnail generated");
+    let decoded = image::load_from_memory(thumb).unwrap();
+    assert!(decoded.width() < W, "thumbnail is downscaled");
+    assert_eq!(max_blue(thumb), 0);
+}
@@ -0,0 +1,120 @@
+use chrono::Utc;
+use screenshot_core::{Annotation, AnnotationKind, AnnotationMeta};
+use services::AnnotationService;
+use uuid::Uuid;
+
+fn marker(number: u32) -> Annotation {
+    Annotation {
+        meta: AnnotationMeta {
+            id: Uuid::now_v7(),
+            x: 0.0,
+            y: 0.0,
+            w: 24.0,
+            h: 24.0,
+            rotation: 0,
+            opacity: 1.0,
+            stroke_color: None,
+            fill_color: None,
+            stroke_width: None,
+            z: 0,
+            locked: false,
+            created_at: Utc::now(),
+        },
+        kind: AnnotationKind::StepMarker { number },
+    }
+}
+
+/// 按列表顺序返回 (id, 编号)
+fn numbers(svc: &AnnotationService) -> Vec<(Uuid, u32)> {
+    svc.list()
+        .iter()
+        .filter_map(|a| match a.kind {
+            AnnotationKind::StepMarker { number } => Some((a.meta.id, number)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn number_of(svc: &AnnotationService, id: Uuid) -> u32 {
+    numbers(svc).into_iter().find(|(i, _)| *i == id).unwrap().1
+}
+
+#[test]
+fn markers_are_numbered_automatically() {
+    let mut svc = AnnotationService::new();
+    let ids: Vec<Uuid> = (0..3)
+        .map(|_| {
+            let m = marker(0);
+            let id = m.meta.id;
+            svc.add(m);
+            id
+        })
+        .collect();
+    assert_eq!(numbers(&svc), vec![(ids[0], 1), (ids[1], 2), (ids[2], 3)]);
+
+    // 指定编号插入：原 2、3 顺延
+    let m = marker(2);
+    let inserted = m.meta.id;
+    svc.add(m);
+    assert_eq!(number_of(&svc, inserted), 2);
+    assert_eq!(number_of(&svc, ids[1]), 3);
+    assert_eq!(number_of(&svc, ids[2]), 4);
+
+    // 插入与顺延是一次撤销
+    assert!(svc.undo());
+    assert_eq!(numbers(&svc), vec![(ids[0], 1), (ids[1], 2), (ids[2], 3)]);
+}
+
+#[test]
+fn removing_a_marker_renumbers_the_rest() {
+    let mut svc = AnnotationService::new();
+    let ids: Vec<Uuid> = (0..4)
+        .map(|_| {
+            let m = marker(0);
+            let id = m.meta.id;
+            svc.add(m);
+            id
+        })
+        .collect();
+    svc.remove(ids[1]).unwrap();
+    assert_eq!(numbers(&svc), vec![(ids[0], 1), (ids[2], 2), (ids[3], 3)]);
+
+    assert!(svc.undo());
+    assert_eq!(
+        numbers(&svc),
+        vec![(ids[0], 1), (ids[1], 2), (ids[2], 3), (ids[3], 4)]
+    );
+    assert!(svc.redo());
+    assert_eq!(numbers(&svc), vec![(ids[0], 1), (ids[2], 2), (ids[3], 3)]);
+}
+
+#[test]
+fn move_step_reorders_sequence() {
+    let mut svc = AnnotationService::new();
+    let ids: Vec<Uuid> = (0..4)
+        .map(|_| {
+            let m = marker(0);
+            let id = m.meta.id;
+            svc.add(m);
+            id
+        })
+        .collect();
+    assert!(svc.move_step(ids[3], 1));
+    let order = |svc: &AnnotationService| {
+        let mut v = numbers(svc);
+        v.sort_by_key(|(_, n)| *n);
+        v.into_iter().map(|(id, _)| id).collect::<Vec<_>>()
+    };
+    assert_eq!(order(&svc), vec![ids[3], ids[0], ids[1], ids[2]]);
+
+    // 超出范围移到末尾；位置不变时不记录操作
+    assert!(svc.move_step(ids[3], 99));
+    assert_eq!(order(&svc), ids);
+    assert!(!svc.move_step(ids[3], 4));
+    assert!(!svc.move_step(Uuid::now_v7(), 1));
+
+    assert!(svc.undo());
+    assert_eq!(order(&svc), vec![ids[3], ids[0], ids[1], ids[2]]);
+    assert!(svc.undo());
+    assert_eq!(order(&svc), ids);
+}